@@ -0,0 +1,63 @@
+#ifndef ENTROPYSCAN_H
+#define ENTROPYSCAN_H
+
+/* The C API of the entropyscan library, mirroring src/ffi.rs. Regenerate with cbindgen --config cbindgen.toml. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The value es_scan_path returns when its arguments are invalid or the scan couldn't run.
+ */
+#define ES_ERROR -1
+
+/**
+ * Holds the result of scanning one file, as handed to an EsCallback.
+ *
+ * The `path` field holds the path to the file as a NUL-terminated string. It is only valid until the callback returns.
+ *
+ * The `entropy` field holds the entropy of the file, between 0.0 and 8.0.
+ *
+ * The `size` field holds the size of the file in bytes.
+ *
+ * The `low_confidence` field is set when the file is too small for its entropy to be meaningful.
+ *
+ * The `printable_ratio` and `null_ratio` fields hold the fractions of bytes that are printable ASCII and 0x00.
+ */
+typedef struct EsResult {
+  const char *path;
+  double entropy;
+  uint64_t size;
+  bool low_confidence;
+  double printable_ratio;
+  double null_ratio;
+} EsResult;
+
+/**
+ * The callback es_scan_path hands each EsResult to, along with the caller's `user_data`.
+ */
+typedef void (*EsCallback)(const struct EsResult *result, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Scan the file or directory at `path` with `jobs` worker threads, calling `callback` once per scanned file.
+ *
+ * 0 jobs means one per CPU. Files are reported in a stable order, always on the calling thread.
+ *
+ * Returns the number of files that couldn't be read, so 0 means every file was scanned, or ES_ERROR if `path` or `callback` is missing, `path` isn't UTF-8 or doesn't exist, or the scan failed.
+ *
+ * # Safety
+ *
+ * `path` must be NULL or point to a NUL-terminated string. `callback` must be safe to call with `user_data` for the duration of the scan.
+ */
+int es_scan_path(const char *path, size_t jobs, EsCallback callback, void *user_data);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ENTROPYSCAN_H */
@@ -0,0 +1,75 @@
+//! Contains the logic for comparing two JSON scan reports.
+//!
+//! [compare_reports] reads two reports written by `scan --format json`, e.g. before and after an incident window, and measures how the entropy distribution of the corpus shifted: the change in its summary statistics and outlier count, and the Kolmogorov-Smirnov distance between the two distributions, whose p-value decides whether the change is material.
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::entropy_scan::{
+    archive::flatten_members,
+    stats::{ entropy_outliers, interquartile_range, ks_distance, ks_p_value, mean, median, variance },
+    structs::{ Comparison, FileEntropy, StatChange },
+};
+
+/// The parts of a JSON scan report needed for a comparison.
+#[derive(Deserialize)]
+struct Report {
+    entropies: Vec<FileEntropy>,
+}
+
+/// Read the entropies of the JSON scan report at `path`, with archive members listed after their archives.
+///
+/// Returns an error message if the report can't be read or parsed, or holds no files.
+pub fn read_entropies(path: &Path) -> Result<Vec<FileEntropy>, String> {
+    let report = fs::read_to_string(path).map_err(|e| format!("Couldn't read report {}: {e}", path.to_string_lossy()))?;
+    let report: Report = serde_json::from_str(&report).map_err(|e|
+        format!("Couldn't parse report {}: {e}", path.to_string_lossy())
+    )?;
+    match report.entropies.is_empty() {
+        true => Err(format!("Report {} holds no files", path.to_string_lossy())),
+        false => Ok(flatten_members(report.entropies)),
+    }
+}
+
+/// Compare the JSON scan reports at `before` and `after`.
+///
+/// The corpus is reported as changed when the Kolmogorov-Smirnov p-value is below `significance`, e.g. 0.05.
+///
+/// Returns the [Comparison] or an error message.
+pub fn compare_reports(before: &Path, after: &Path, significance: f64) -> Result<Comparison, String> {
+    let old = read_entropies(before)?;
+    let new = read_entropies(after)?;
+
+    let mut changes = Vec::new();
+    let mut push = |metric, before: f64, after: f64, count| {
+        changes.push(StatChange { metric, before, after, change: after - before, count });
+    };
+    push("files", old.len() as f64, new.len() as f64, true);
+    for (metric, statistic) in [
+        ("mean", mean as fn(&[FileEntropy]) -> Option<f64>),
+        ("median", median),
+        ("variance", variance),
+        ("iqr", |data: &[FileEntropy]| interquartile_range(data).map(|iqr| iqr.range)),
+    ] {
+        // The IQR is left out when either report has too few files for quartiles.
+        if let (Some(before), Some(after)) = (statistic(&old), statistic(&new)) {
+            push(metric, before, after, false);
+        }
+    }
+    let outliers = |data: &[FileEntropy]| entropy_outliers(data).map(|outliers| outliers.len() as f64);
+    if let (Some(before), Some(after)) = (outliers(&old), outliers(&new)) {
+        push("outliers", before, after, true);
+    }
+
+    let distance = ks_distance(&old, &new).unwrap();
+    let p_value = ks_p_value(distance, old.len(), new.len());
+    Ok(Comparison {
+        before: before.to_path_buf(),
+        after: after.to_path_buf(),
+        changes,
+        ks_distance: distance,
+        p_value,
+        changed: p_value < significance,
+    })
+}
@@ -0,0 +1,95 @@
+//! Contains the logic for diffing a tree against a baseline scan.
+//!
+//! [write_baseline] records a scan of the tree as a baseline report.
+//!
+//! [diff_against_baseline] matches the files of a fresh scan to those of a baseline report written by `baseline`, by path, and lists the files that are new, removed, or whose entropy moved by more than a delta. A share whose files all jumped to near 8 bits per byte overnight shows up as a wall of changed files.
+//!
+//! The opposite failure is caught too: files that held data in the baseline but are now empty or a single repeated byte, as a wiper leaves them, are reported as wiped whatever the delta.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::compare::read_entropies;
+use crate::entropy_scan::structs::{ BaselineDiff, FileChange, FileEntropy, ScanMeta };
+
+/// The most entropy a file can have and still count as wiped: empty, or one byte value give or take a stray byte.
+const WIPED_ENTROPY: f64 = 0.01;
+
+/// The least entropy a file needs in the baseline to count as wiped, so files that were always empty or padding don't.
+const NORMAL_ENTROPY: f64 = 1.0;
+
+/// Diff the `current` scan against the baseline report at `baseline`.
+///
+/// Files that had at least [NORMAL_ENTROPY] in the baseline and now have at most [WIPED_ENTROPY] are reported as wiped. Other files whose entropy moved by more than `delta` bits per symbol either way are reported as changed.
+///
+/// Returns the [BaselineDiff], or an error message if the baseline can't be read.
+pub fn diff_against_baseline(baseline: &Path, current: &[FileEntropy], delta: f64) -> Result<BaselineDiff, String> {
+    let mut before: BTreeMap<_, _> = read_entropies(baseline)?
+        .into_iter()
+        .map(|item| (item.path, item.entropy))
+        .collect();
+    let mut diff = BaselineDiff {
+        baseline: baseline.to_path_buf(),
+        delta,
+        new: 0,
+        removed: 0,
+        changed: 0,
+        wiped: 0,
+        unchanged: 0,
+        changes: Vec::new(),
+    };
+    for item in current {
+        match before.remove(&item.path) {
+            None => {
+                diff.new += 1;
+                diff.changes.push(FileChange { path: item.path.clone(), change: "new", before: None, after: Some(item.entropy) });
+            }
+            Some(entropy) if entropy >= NORMAL_ENTROPY && item.entropy <= WIPED_ENTROPY => {
+                diff.wiped += 1;
+                diff.changes.push(FileChange {
+                    path: item.path.clone(),
+                    change: "wiped",
+                    before: Some(entropy),
+                    after: Some(item.entropy),
+                });
+            }
+            Some(entropy) if (item.entropy - entropy).abs() > delta => {
+                diff.changed += 1;
+                diff.changes.push(FileChange {
+                    path: item.path.clone(),
+                    change: "changed",
+                    before: Some(entropy),
+                    after: Some(item.entropy),
+                });
+            }
+            Some(_) => {
+                diff.unchanged += 1;
+            }
+        }
+    }
+    // Whatever is left in the baseline wasn't found this time.
+    for (path, entropy) in before {
+        diff.removed += 1;
+        diff.changes.push(FileChange { path, change: "removed", before: Some(entropy), after: None });
+    }
+    diff.changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(diff)
+}
+
+/// Write a baseline report of `entropies` to `path`, in the same form as `scan --format json`, so it also serves `compare-stats` and `--anomaly-baseline`.
+///
+/// Returns an error message if there is nothing to record or the report can't be written.
+pub fn write_baseline(path: &Path, meta: &ScanMeta, entropies: &[FileEntropy]) -> Result<(), String> {
+    if entropies.is_empty() {
+        return Err("No files to record in the baseline".to_string());
+    }
+    let error = |e: std::io::Error| format!("Couldn't write baseline {}: {e}", path.to_string_lossy());
+    let mut report = json!(meta);
+    report["entropies"] = json!(entropies);
+    let mut out = BufWriter::new(File::create(path).map_err(error)?);
+    serde_json::to_writer_pretty(&mut out, &report).map_err(|e| error(e.into()))?;
+    out.flush().map_err(error)
+}
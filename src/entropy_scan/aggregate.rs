@@ -0,0 +1,151 @@
+//! Contains the logic for grouping scan results by the unit they belong to.
+//!
+//! The [aggregate] function folds the files of each bundle or package into a single [Aggregate], so a bundle is reported as one row instead of thousands.
+//!
+//! The [AggregateBy] enum picks the grouping. Bundles are recognised from the path alone, packages from the system package database (dpkg or rpm).
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{ Component, Path, PathBuf };
+use std::str::FromStr;
+
+use super::forensic::open_file;
+use super::magic::sniff;
+use super::packages::PackageDb;
+use super::structs::{ Aggregate, FileEntropy };
+
+/// The number of bytes read from each grouped file to recognise its format.
+const SNIFF_WINDOW: u64 = 8;
+
+/// How scan results are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateBy {
+    /// Group files by the bundle they sit in: a macOS app bundle, a `node_modules` package, or a Python package.
+    Bundle,
+    /// Group files by the system package (dpkg or rpm) that owns them.
+    Package,
+}
+
+impl FromStr for AggregateBy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "bundle" => Ok(AggregateBy::Bundle),
+            "package" => Ok(AggregateBy::Package),
+            _ => Err(format!("Invalid grouping: {value} (expected bundle or package)")),
+        }
+    }
+}
+
+/// Find the outermost app bundle `path` is inside of.
+fn enclosing_app(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .filter(|ancestor| ancestor.extension().is_some_and(|extension| extension == "app"))
+        .last()
+        .map(Path::to_path_buf)
+}
+
+/// Find the package directory following the last `marker` directory in `path`, e.g. `node_modules/left-pad`.
+///
+/// Scoped names such as `node_modules/@types/node` are kept whole when `scoped` is set.
+fn after_marker(path: &Path, marker: &str, scoped: bool) -> Option<PathBuf> {
+    let components: Vec<Component> = path.components().collect();
+    let position = components.iter().rposition(|component| component.as_os_str() == marker)?;
+    // The package is a directory, so the file itself can't be the package name.
+    let mut end = position + 2;
+    if scoped && components.get(position + 1)?.as_os_str().to_string_lossy().starts_with('@') {
+        end += 1;
+    }
+    match end < components.len() {
+        true => Some(components[..end].iter().collect()),
+        false => None,
+    }
+}
+
+/// Find the bundle `path` belongs to and what kind of bundle it is.
+///
+/// App bundles win over the packages inside them, and the innermost `node_modules` package wins over the packages that depend on it.
+fn enclosing_bundle(path: &Path) -> Option<(PathBuf, &'static str)> {
+    if let Some(app) = enclosing_app(path) {
+        return Some((app, "app bundle"));
+    }
+    if let Some(package) = after_marker(path, "node_modules", true) {
+        return Some((package, "node package"));
+    }
+    ["site-packages", "dist-packages"]
+        .iter()
+        .find_map(|marker| after_marker(path, marker, false))
+        .map(|package| (package, "python package"))
+}
+
+/// Check whether the file at `path` is a Mach-O binary.
+fn is_mach_o(path: &Path) -> bool {
+    let mut head = Vec::new();
+    let read = open_file(path).and_then(|file| file.take(SNIFF_WINDOW).read_to_end(&mut head));
+    read.is_ok() && sniff(&head).is_some_and(|magic| magic.name.starts_with("Mach-O"))
+}
+
+/// Check whether the app bundle at `bundle` carries a code signature.
+fn is_signed(bundle: &Path) -> bool {
+    bundle.join("Contents/_CodeSignature/CodeResources").exists()
+}
+
+/// Group `entropies` as asked by `by`.
+///
+/// Files outside any group are kept as a group of one. Package groups are named after the package. Groups are returned in the order their first file appears.
+pub fn aggregate(entropies: &[FileEntropy], by: AggregateBy) -> Vec<Aggregate> {
+    let packages = match by {
+        AggregateBy::Bundle => PackageDb::default(),
+        AggregateBy::Package => {
+            let paths: Vec<PathBuf> = entropies
+                .iter()
+                .map(|entropy| entropy.path.clone())
+                .collect();
+            PackageDb::load(&paths, false)
+        }
+    };
+
+    let mut groups: Vec<Aggregate> = Vec::new();
+    // The running entropy total of each group, to average them at the end.
+    let mut totals: Vec<f64> = Vec::new();
+    let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+    for entropy in entropies {
+        let group = match by {
+            AggregateBy::Bundle => enclosing_bundle(&entropy.path),
+            AggregateBy::Package =>
+                packages.owner(&entropy.path).map(|package| (PathBuf::from(package), packages.kind())),
+        };
+        let app = group.as_ref().is_some_and(|(_, kind)| *kind == "app bundle");
+        let mach_o = app && is_mach_o(&entropy.path);
+        match group.as_ref().and_then(|(path, _)| positions.get(path).copied()) {
+            Some(index) => {
+                let group = &mut groups[index];
+                group.files += 1;
+                group.size += entropy.size;
+                group.max_entropy = group.max_entropy.max(entropy.entropy);
+                group.mach_o += mach_o as usize;
+                totals[index] += entropy.entropy;
+            }
+            None => {
+                let (path, kind) = group.unwrap_or_else(|| (entropy.path.clone(), "file"));
+                positions.insert(path.clone(), groups.len());
+                groups.push(Aggregate {
+                    signed: app.then(|| is_signed(&path)),
+                    path,
+                    kind,
+                    files: 1,
+                    size: entropy.size,
+                    max_entropy: entropy.entropy,
+                    mean_entropy: entropy.entropy,
+                    mach_o: mach_o as usize,
+                });
+                totals.push(entropy.entropy);
+            }
+        }
+    }
+    for (group, total) in groups.iter_mut().zip(totals) {
+        group.mean_entropy = total / (group.files as f64);
+    }
+    groups
+}
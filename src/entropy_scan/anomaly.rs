@@ -0,0 +1,116 @@
+//! Contains an opt-in anomaly scorer trained on a baseline scan.
+//!
+//! The [AnomalyModel] struct learns what a typical file of the baseline looks like from a JSON scan report, and [AnomalyModel::score] says how far a newly scanned file is from it.
+//!
+//! The model is a robust one: each feature is centred on the baseline median and scaled by its median absolute deviation, so a handful of odd files in the baseline don't hide the next one.
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::archive::flatten_members;
+use super::structs::FileEntropy;
+
+/// The number of features each file is described by, see [features].
+const FEATURES: usize = 5;
+
+/// The smallest spread used for each feature, so a feature that never varies in the baseline doesn't make any change look infinitely odd.
+const MIN_SPREADS: [f64; FEATURES] = [0.25, 0.05, 0.05, 1.0, 0.05];
+
+/// Scales the median absolute deviation to the standard deviation of normally distributed data.
+const MAD_SCALE: f64 = 1.4826;
+
+/// The fewest files a baseline needs for its medians to mean anything.
+const MIN_BASELINE: usize = 10;
+
+/// The parts of a JSON scan report needed to train a model.
+#[derive(Deserialize)]
+struct Baseline {
+    #[serde(default)]
+    symbol_width: Option<u8>,
+    entropies: Vec<FileEntropy>,
+}
+
+/// Describe a file as its entropy, printable ratio, null ratio, log2 size, and longest zero run as a fraction of its size.
+fn features(entropy: &FileEntropy) -> [f64; FEATURES] {
+    [
+        entropy.entropy,
+        entropy.printable_ratio,
+        entropy.null_ratio,
+        ((entropy.size as f64) + 1.0).log2(),
+        (entropy.longest_zero_run as f64) / (entropy.size.max(1) as f64),
+    ]
+}
+
+/// The median of `values`, which must not be empty.
+fn median_of(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[middle - 1] + values[middle]) / 2.0,
+        _ => values[middle],
+    }
+}
+
+/// Holds the median and spread of every feature over a baseline scan.
+#[derive(Debug, Clone)]
+pub struct AnomalyModel {
+    medians: [f64; FEATURES],
+    spreads: [f64; FEATURES],
+}
+
+impl AnomalyModel {
+    /// Train a model on the files of a baseline scan.
+    ///
+    /// Returns an error message if the baseline has fewer than [MIN_BASELINE] files.
+    pub fn train(baseline: &[FileEntropy]) -> Result<AnomalyModel, String> {
+        if baseline.len() < MIN_BASELINE {
+            return Err(format!("A baseline needs at least {MIN_BASELINE} files, this one has {}", baseline.len()));
+        }
+        let rows: Vec<[f64; FEATURES]> = baseline.iter().map(features).collect();
+        let mut medians = [0.0; FEATURES];
+        let mut spreads = [0.0; FEATURES];
+        for feature in 0..FEATURES {
+            let mut values: Vec<f64> = rows
+                .iter()
+                .map(|row| row[feature])
+                .collect();
+            let median = median_of(&mut values);
+            let mut deviations: Vec<f64> = values
+                .iter()
+                .map(|value| (value - median).abs())
+                .collect();
+            medians[feature] = median;
+            spreads[feature] = (median_of(&mut deviations) * MAD_SCALE).max(MIN_SPREADS[feature]);
+        }
+        Ok(AnomalyModel { medians, spreads })
+    }
+
+    /// Train a model on the JSON scan report at `path`, written by `scan --format json`.
+    ///
+    /// `symbol_width` is the symbol width of the current scan, or [None] for 8 bits, and must match the baseline's. Returns an error message if the report can't be read or trained on.
+    pub fn load(path: &Path, symbol_width: Option<u8>) -> Result<AnomalyModel, String> {
+        let contents = fs::read_to_string(path).map_err(|e|
+            format!("Couldn't read {}: {e}", path.to_string_lossy())
+        )?;
+        let baseline: Baseline = serde_json::from_str(&contents).map_err(|e|
+            format!("{} is not a JSON scan report: {e}", path.to_string_lossy())
+        )?;
+        if baseline.symbol_width != symbol_width {
+            return Err("The baseline was scanned with a different --symbol-width".to_string());
+        }
+        AnomalyModel::train(&flatten_members(baseline.entropies))
+    }
+
+    /// Score how unusual `entropy` is compared to the baseline.
+    ///
+    /// The score is the root mean square of the file's robust z-scores, so 0.0 is a perfectly typical file. Scores are only comparable between files scored against the same baseline.
+    pub fn score(&self, entropy: &FileEntropy) -> f64 {
+        let squares: f64 = features(entropy)
+            .iter()
+            .zip(self.medians.iter().zip(self.spreads.iter()))
+            .map(|(value, (median, spread))| ((value - median) / spread).powi(2))
+            .sum();
+        (squares / (FEATURES as f64)).sqrt()
+    }
+}
@@ -0,0 +1,565 @@
+//! Contains the logic for scanning the members of zip, tar, and gzip archives, and of the archives nested inside them.
+//!
+//! The [archive_members] function recognises an archive by its signature and measures every member on its own, so an encrypted payload can be told apart from the files packed around it. Members are named with a virtual path, e.g. `bundle.zip!inner/file.bin`, or `mail.zip!attachment.tar!inner/file.bin` when nested.
+//!
+//! Tar members are streamed. Deflated zip members and gzip files are decompressed in memory with [super::inflate::inflate_into], up to [MAX_INFLATED_SIZE]. Nothing is written to disk, and with the `no_persist` option every buffer is wiped once measured. Buffers are allocated for the size the archive claims and never grow, so a member that turns out larger than its header says is reported rather than reallocated. A gzip file holding a tar archive is read as a `.tar.gz`.
+//!
+//! Members are reported after their archive, as entries of their own. JSON reports [nest](nest_members) them in their archive's `archive_members` instead, and [flatten_members] undoes that when a report is read back.
+//!
+//! Nested archives are opened up to the `container_depth` in [ScanOptions]. Everything decompressed from one archive counts against [MAX_TOTAL_INFLATED_SIZE], and an archive that contains a copy of itself is not opened again, so decompression bombs and quines stop early.
+use std::io::{ self, Cursor, Read, Seek, SeekFrom };
+use std::path::{ Path, PathBuf };
+
+use super::digest::{ md5_file, Md5 };
+use super::forensic::open_file;
+use super::inflate::inflate_into;
+use super::magic::sniff;
+use super::measure;
+use super::options::ScanOptions;
+use super::structs::{ FileEntropy, Unscanned };
+use super::units::format_size;
+use super::wipe::Wiped;
+
+/// The most bytes a zip member or gzip file is decompressed to in memory, and the largest nested archive opened.
+///
+/// This is set to 256MB.
+pub const MAX_INFLATED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The most bytes decompressed from one archive, counting every archive nested inside it.
+///
+/// This is set to 1GB.
+pub const MAX_TOTAL_INFLATED_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// The number of bytes read from the start of a file to recognise an archive. Covers the `ustar` signature at offset 257.
+const SNIFF_WINDOW: u64 = 512;
+
+/// The size of a tar header block, and the unit tar members are padded to.
+const TAR_BLOCK_SIZE: u64 = 512;
+
+/// The size of the zip end of central directory record, without its comment.
+const ZIP_END_SIZE: usize = 22;
+
+/// The most bytes searched from the end of a zip file for the end of central directory record: the record plus the longest comment.
+const ZIP_END_SEARCH: u64 = (ZIP_END_SIZE as u64) + 65535;
+
+/// The most bytes of a zip central directory read, so a corrupt header can't exhaust memory.
+const MAX_ZIP_DIRECTORY_SIZE: usize = 16 * 1024 * 1024;
+
+/// The result of scanning one archive member.
+type Member = Result<FileEntropy, Unscanned>;
+
+/// The archive formats members are read from.
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Zip,
+    Tar,
+    Gzip,
+}
+
+/// Recognise the archive format `head`, the start of a file, belongs to.
+fn archive_format(head: &[u8]) -> Option<Format> {
+    match sniff(head)?.name {
+        "ZIP" => Some(Format::Zip),
+        "tar" => Some(Format::Tar),
+        "gzip" => Some(Format::Gzip),
+        _ => None,
+    }
+}
+
+/// Name the member `name` of the archive at `archive`, e.g. `bundle.zip!inner/file.bin`.
+fn member_path(archive: &Path, name: &str) -> PathBuf {
+    PathBuf::from(format!("{}!{name}", archive.to_string_lossy()))
+}
+
+/// Record that the member at `path` wasn't scanned, on purpose if `skipped` is set.
+fn unscanned(path: PathBuf, reason: String, skipped: bool) -> Member {
+    Err(Unscanned { path, reason, skipped })
+}
+
+/// Skip the member at `path` for being `size` bytes, more than the member size limit.
+fn too_large(path: PathBuf, size: u64) -> Member {
+    unscanned(path, format!("larger than the member size limit ({})", format_size(size)), true)
+}
+
+/// The MD5 digest of `bytes`, to recognise an archive nested inside itself.
+fn digest(bytes: &[u8]) -> String {
+    let mut md5 = Md5::default();
+    md5.update(bytes);
+    md5.hex()
+}
+
+/// Holds the state of reading one archive and everything nested inside it.
+///
+/// The `members` field holds the results so far, in archive order.
+///
+/// The `budget` field holds how many more bytes may be decompressed, out of [MAX_TOTAL_INFLATED_SIZE].
+///
+/// The `ancestors` field holds the digests of the archives being read, outermost first, when nested archives are opened.
+///
+struct Walk<'a> {
+    options: &'a ScanOptions,
+    members: Vec<Member>,
+    budget: u64,
+    ancestors: Vec<String>,
+}
+
+impl Walk<'_> {
+    /// The largest member to scan: [MAX_INFLATED_SIZE] for members held in memory, or the `max_file_size` in [ScanOptions] if it is smaller.
+    fn limit(&self, in_memory: bool) -> u64 {
+        let limit = self.options.max_file_size.unwrap_or(u64::MAX);
+        match in_memory {
+            true => limit.min(MAX_INFLATED_SIZE),
+            false => limit,
+        }
+    }
+
+    /// Check whether members at `depth`, 1 for the members of the archive being scanned, may be opened as archives in turn.
+    fn opens(&self, depth: usize) -> bool {
+        depth < self.options.container_depth
+    }
+
+    /// Hold up to `capacity` bytes of member contents, wiped once dropped if the `no_persist` option is set.
+    fn buffer(&self, capacity: u64) -> Wiped {
+        Wiped::with_capacity(capacity as usize, self.options.no_persist)
+    }
+
+    /// Decompress `data`, expected to grow to `size` bytes, charging the result to the budget.
+    ///
+    /// Records why and returns [None] if the result would be too large, including larger than `size`, or `data` is corrupt.
+    fn inflate(&mut self, data: &[u8], size: u64, path: &Path) -> Option<Wiped> {
+        let limit = self.limit(true);
+        if size > limit {
+            self.members.push(too_large(path.to_path_buf(), size));
+            return None;
+        }
+        if size > self.budget {
+            let reason = format!("past the total decompressed size cap ({})", format_size(MAX_TOTAL_INFLATED_SIZE));
+            self.members.push(unscanned(path.to_path_buf(), reason, true));
+            return None;
+        }
+        let mut inflated = self.buffer(size);
+        match inflated.fill_with(|out, capacity| inflate_into(data, out, capacity)) {
+            Ok(()) => {
+                self.budget -= inflated.len() as u64;
+                Some(inflated)
+            }
+            Err(reason) => {
+                self.members.push(unscanned(path.to_path_buf(), reason, false));
+                None
+            }
+        }
+    }
+
+    /// Measure a member of `size` bytes read from `reader`, then open it in turn if it is an archive and `depth` allows.
+    fn visit<R: Read>(&mut self, reader: R, path: PathBuf, size: u64, depth: usize) -> io::Result<()> {
+        if size > self.limit(false) {
+            self.members.push(too_large(path, size));
+            return Ok(());
+        }
+        // Only a member that might be opened needs holding in memory.
+        if !self.opens(depth) || size > self.limit(true) {
+            let member = measure(reader, &path, self.options).map_err(|reason| Unscanned { path, reason, skipped: false });
+            self.members.push(member);
+            return Ok(());
+        }
+        let mut bytes = self.buffer(size);
+        match bytes.read_from(reader) {
+            Ok(()) => self.visit_bytes(&bytes, path, depth),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                self.members.push(unscanned(path, "holds more data than its header says".to_string(), false));
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Measure a member held in memory, then open it in turn if it is an archive and `depth` allows.
+    fn visit_bytes(&mut self, bytes: &[u8], path: PathBuf, depth: usize) {
+        let member = measure(bytes, &path, self.options).map_err(|reason| Unscanned {
+            path: path.clone(),
+            reason,
+            skipped: false,
+        });
+        self.members.push(member);
+        if !self.opens(depth) {
+            return;
+        }
+        let Some(format) = archive_format(bytes) else {
+            return;
+        };
+        let hash = digest(bytes);
+        if self.ancestors.contains(&hash) {
+            self.members.push(unscanned(path, "contains the archive it is nested in".to_string(), true));
+            return;
+        }
+        self.ancestors.push(hash);
+        let read = match format {
+            Format::Zip => self.zip(&mut Cursor::new(bytes), &path, depth + 1),
+            Format::Tar => self.tar(bytes, &path, depth + 1),
+            Format::Gzip => self.gzip(bytes, &path, depth + 1),
+        };
+        self.ancestors.pop();
+        if let Err(e) = read {
+            self.members.push(unscanned(path, format!("Couldn't read archive: {e}"), false));
+        }
+    }
+
+    /// Measure every regular file in the tar archive `reader` yields, naming members after `archive`.
+    fn tar<R: Read>(&mut self, mut reader: R, archive: &Path, depth: usize) -> io::Result<()> {
+        let mut header = [0u8; TAR_BLOCK_SIZE as usize];
+        // The name of the next member, when a GNU long name or pax header gave it.
+        let mut long_name: Option<String> = None;
+        loop {
+            if reader.read_exact(&mut header).is_err() || header.iter().all(|b| *b == 0) {
+                return Ok(());
+            }
+            // Sizes too large for octal are stored in base-256, flagged by the high bit.
+            let size = match header[124] & 0x80 {
+                0 => tar_number(&header[124..136]),
+                _ => None,
+            };
+            let size = size.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unsupported tar member size"))?;
+            let padding = (TAR_BLOCK_SIZE - (size % TAR_BLOCK_SIZE)) % TAR_BLOCK_SIZE;
+            let name = long_name.take().unwrap_or_else(|| {
+                let name = tar_name(&header[..100]);
+                match &header[257..262] == b"ustar" && header[345] != 0 {
+                    true => format!("{}/{name}", tar_name(&header[345..500])),
+                    false => name,
+                }
+            });
+
+            match header[156] {
+                b'0' | 0 | b'7' => {
+                    let mut data = reader.by_ref().take(size);
+                    self.visit(&mut data, member_path(archive, &name), size, depth)?;
+                    // A skipped member is still in the stream.
+                    let left = data.limit();
+                    skip(&mut data, left)?;
+                }
+                b'L' => {
+                    let mut name = Vec::new();
+                    reader.by_ref().take(size).read_to_end(&mut name)?;
+                    long_name = Some(tar_name(&name));
+                }
+                b'x' => {
+                    let mut records = Vec::new();
+                    reader.by_ref().take(size).read_to_end(&mut records)?;
+                    long_name = pax_path(&records);
+                }
+                _ => skip(&mut reader, size)?,
+            }
+            skip(&mut reader, padding)?;
+        }
+    }
+
+    /// Measure every file in the zip archive `reader` holds, naming members after `archive`.
+    fn zip<R: Read + Seek>(&mut self, reader: &mut R, archive: &Path, depth: usize) -> io::Result<()> {
+        for entry in zip_directory(reader)? {
+            if !entry.name.ends_with('/') {
+                self.zip_member(reader, archive, &entry, depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Measure one zip member, reading its data from `reader`.
+    fn zip_member<R: Read + Seek>(&mut self, reader: &mut R, archive: &Path, entry: &ZipEntry, depth: usize) -> io::Result<()> {
+        let path = member_path(archive, &entry.name);
+        if entry.compressed == (u32::MAX as u64) || entry.size == (u32::MAX as u64) {
+            self.members.push(unscanned(path, "ZIP64 member, not supported".to_string(), true));
+            return Ok(());
+        }
+        let mut header = [0u8; 30];
+        reader.seek(SeekFrom::Start(entry.header_offset))?;
+        reader.read_exact(&mut header)?;
+        if header[..4] != [0x50, 0x4b, 0x03, 0x04] {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt zip local header"));
+        }
+        let data_offset = entry.header_offset + 30 + (u16_at(&header, 26) as u64) + (u16_at(&header, 28) as u64);
+        reader.seek(SeekFrom::Start(data_offset))?;
+        let data = reader.by_ref().take(entry.compressed);
+
+        // Encrypted data can't be decompressed, but its entropy is exactly what gives it away.
+        if entry.flags & 1 == 1 {
+            if entry.compressed > self.limit(false) {
+                self.members.push(too_large(path, entry.compressed));
+                return Ok(());
+            }
+            let mut member = measure(data, &path, self.options).map_err(|reason| Unscanned { path, reason, skipped: false });
+            if let Ok(member) = member.as_mut() {
+                member.container = Some("encrypted zip member".to_string());
+            }
+            self.members.push(member);
+            return Ok(());
+        }
+        match entry.method {
+            0 => self.visit(data, path, entry.size, depth),
+            8 => {
+                if entry.compressed > self.limit(true) {
+                    self.members.push(too_large(path, entry.compressed));
+                    return Ok(());
+                }
+                let mut compressed = self.buffer(entry.compressed);
+                compressed.read_from(data)?;
+                if let Some(inflated) = self.inflate(&compressed, entry.size, &path) {
+                    self.visit_bytes(&inflated, path, depth);
+                }
+                Ok(())
+            }
+            method => {
+                self.members.push(unscanned(path, format!("compression method {method}, not supported"), true));
+                Ok(())
+            }
+        }
+    }
+
+    /// Measure the file in the gzip data `bytes`, or every member when it holds a tar archive, naming members after `archive`.
+    fn gzip(&mut self, bytes: &[u8], archive: &Path, depth: usize) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if bytes.len() < 18 || bytes[2] != 8 {
+            return Err(invalid("unsupported gzip file"));
+        }
+        let flags = bytes[3];
+        let mut at = 10usize;
+        if flags & 0x04 != 0 {
+            at += 2 + (u16_at(bytes, at) as usize);
+        }
+        // The original file name, used to name the member.
+        let mut name = None;
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                let end = bytes
+                    .get(at..)
+                    .and_then(|rest| rest.iter().position(|b| *b == 0))
+                    .ok_or_else(|| invalid("corrupt gzip header"))?;
+                if flag == 0x08 {
+                    name = Some(String::from_utf8_lossy(&bytes[at..at + end]).to_string());
+                }
+                at += end + 1;
+            }
+        }
+        if flags & 0x02 != 0 {
+            at += 2;
+        }
+        let name = name.unwrap_or_else(|| {
+            archive
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        let path = member_path(archive, &name);
+        // The size of the original file, modulo 4GB.
+        let size = u32_at(bytes, bytes.len() - 4) as u64;
+        let data = bytes.get(at..).ok_or_else(|| invalid("corrupt gzip header"))?;
+        let Some(inflated) = self.inflate(data, size, &path) else {
+            return Ok(());
+        };
+        // A tarball's members are what matter, not the tar stream around them.
+        match inflated.get(257..262) == Some(b"ustar") {
+            true => self.tar(&inflated[..], archive, depth),
+            false => {
+                self.visit_bytes(&inflated, path, depth);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Read a little-endian `u16` at `offset` of `bytes`.
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Read a little-endian `u32` at `offset` of `bytes`.
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Skip `count` bytes of `reader`.
+fn skip<R: Read>(reader: &mut R, count: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.by_ref().take(count), &mut io::sink())?;
+    match skipped == count {
+        true => Ok(()),
+        false => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "archive ends early")),
+    }
+}
+
+/// Parse a NUL- or space-terminated octal number from a tar header field.
+fn tar_number(field: &[u8]) -> Option<u64> {
+    let digits: String = field
+        .iter()
+        .skip_while(|b| **b == b' ')
+        .take_while(|b| (b'0'..=b'7').contains(*b))
+        .map(|b| *b as char)
+        .collect();
+    match digits.is_empty() {
+        true => Some(0),
+        false => u64::from_str_radix(&digits, 8).ok(),
+    }
+}
+
+/// Read a NUL-terminated name from a tar header field.
+fn tar_name(field: &[u8]) -> String {
+    let name: Vec<u8> = field
+        .iter()
+        .copied()
+        .take_while(|b| *b != 0)
+        .collect();
+    String::from_utf8_lossy(&name).to_string()
+}
+
+/// Find the `path` record of a pax extended header, whose records look like `27 path=some/long/name.bin\n`.
+fn pax_path(records: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(records)
+        .lines()
+        .filter_map(|record| record.split_once(' '))
+        .find_map(|(_, record)| record.strip_prefix("path=").map(str::to_string))
+}
+
+/// A zip central directory entry, before its data is read.
+struct ZipEntry {
+    name: String,
+    flags: u16,
+    method: u16,
+    compressed: u64,
+    size: u64,
+    header_offset: u64,
+}
+
+/// Read the central directory of the zip archive `reader` holds.
+fn zip_directory<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<ZipEntry>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let length = reader.seek(SeekFrom::End(0))?;
+    let start = length.saturating_sub(ZIP_END_SEARCH);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    reader.by_ref().read_to_end(&mut tail)?;
+    let end = (0..tail.len().saturating_sub(ZIP_END_SIZE - 1))
+        .rev()
+        .find(|at| tail[*at..*at + 4] == [0x50, 0x4b, 0x05, 0x06])
+        .ok_or_else(|| invalid("no zip central directory"))?;
+    let count = u16_at(&tail, end + 10) as usize;
+    let size = (u32_at(&tail, end + 12) as usize).min(MAX_ZIP_DIRECTORY_SIZE);
+    let offset = u32_at(&tail, end + 16) as u64;
+
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut directory = Vec::with_capacity(size);
+    reader.by_ref()
+        .take(size as u64)
+        .read_to_end(&mut directory)?;
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 0usize;
+    for _ in 0..count {
+        if at + 46 > directory.len() || directory[at..at + 4] != [0x50, 0x4b, 0x01, 0x02] {
+            return Err(invalid("corrupt zip central directory"));
+        }
+        let name_length = u16_at(&directory, at + 28) as usize;
+        let extra_length = u16_at(&directory, at + 30) as usize;
+        let comment_length = u16_at(&directory, at + 32) as usize;
+        let name = directory
+            .get(at + 46..at + 46 + name_length)
+            .ok_or_else(|| invalid("corrupt zip central directory"))?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).to_string(),
+            flags: u16_at(&directory, at + 8),
+            method: u16_at(&directory, at + 10),
+            compressed: u32_at(&directory, at + 20) as u64,
+            size: u32_at(&directory, at + 24) as u64,
+            header_offset: u32_at(&directory, at + 42) as u64,
+        });
+        at += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(entries)
+}
+
+/// Measure every member of the archive at `path`, if it is a zip, tar, or gzip file, opening nested archives up to the `container_depth` in [ScanOptions].
+///
+/// Returns [None] if `path` is not a recognised archive. Members that are too large or use an unsupported feature are skipped, and an archive that can't be read is reported as unscanned.
+pub fn archive_members(path: &Path, options: &ScanOptions) -> Option<Vec<Member>> {
+    let mut head = Vec::new();
+    open_file(path)
+        .and_then(|file| file.take(SNIFF_WINDOW).read_to_end(&mut head))
+        .ok()?;
+    let format = archive_format(&head)?;
+    let mut walk = Walk {
+        options,
+        members: Vec::new(),
+        budget: MAX_TOTAL_INFLATED_SIZE,
+        ancestors: Vec::new(),
+    };
+    let read = open_file(path).and_then(|mut file| {
+        if walk.opens(1) {
+            walk.ancestors.push(md5_file(path)?);
+        }
+        match format {
+            Format::Zip => walk.zip(&mut file, path, 1),
+            Format::Tar => walk.tar(file, path, 1),
+            Format::Gzip => {
+                let length = file.metadata()?.len();
+                match length > walk.limit(true) {
+                    true => {
+                        walk.members.push(too_large(path.to_path_buf(), length));
+                        Ok(())
+                    }
+                    false => {
+                        let mut bytes = walk.buffer(length);
+                        bytes.read_from(file)?;
+                        walk.gzip(&bytes, path, 1)
+                    }
+                }
+            }
+        }
+    });
+    if let Err(e) = read {
+        walk.members.push(unscanned(path.to_path_buf(), format!("Couldn't read archive: {e}"), false));
+    }
+    Some(walk.members)
+}
+
+/// Check whether `entropy` is a member of the archive `archive`, named by [member_path], at any depth.
+fn is_member_of(entropy: &FileEntropy, archive: &FileEntropy) -> bool {
+    entropy.path
+        .to_string_lossy()
+        .strip_prefix(archive.path.to_string_lossy().as_ref())
+        .is_some_and(|rest| rest.starts_with('!'))
+}
+
+/// Nest each archive member in `entropies` in the `archive_members` of the archive it came from, as the JSON report does.
+///
+/// Members follow their archive, as a scan reports them, so an archive's members end at the first entry that isn't one. A member whose archive isn't in `entropies`, e.g. because it was filtered out, is left at the top level.
+pub fn nest_members(entropies: &[FileEntropy]) -> Vec<FileEntropy> {
+    // The archives still taking members, outermost first.
+    let mut open: Vec<FileEntropy> = Vec::new();
+    let mut nested = Vec::new();
+    let close = |open: &mut Vec<FileEntropy>, nested: &mut Vec<FileEntropy>| {
+        if let Some(done) = open.pop() {
+            match open.last_mut() {
+                Some(archive) => archive.archive_members.get_or_insert_with(Vec::new).push(done),
+                None => nested.push(done),
+            }
+        }
+    };
+    for entropy in entropies {
+        while open.last().is_some_and(|archive| !is_member_of(entropy, archive)) {
+            close(&mut open, &mut nested);
+        }
+        open.push(entropy.clone());
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut nested);
+    }
+    nested
+}
+
+/// Undo [nest_members], listing each archive's members right after it, so a JSON report can be read back as a scan produced it.
+pub fn flatten_members(entropies: Vec<FileEntropy>) -> Vec<FileEntropy> {
+    let mut flat = Vec::with_capacity(entropies.len());
+    for mut entropy in entropies {
+        let members = entropy.archive_members.take();
+        flat.push(entropy);
+        flat.extend(flatten_members(members.unwrap_or_default()));
+    }
+    flat
+}
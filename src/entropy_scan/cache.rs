@@ -0,0 +1,62 @@
+//! Contains the logic for playing well with the operating system's page cache.
+//!
+//! The [read_sequentially] function tells the kernel a file is about to be read from start to end, so it reads ahead more aggressively, and [prefetch] asks it to start reading a file that is next in the queue before a worker gets to it.
+//!
+//! The [drop_cached] function hands a file's pages back once it has been measured, so a nightly scan doesn't evict the pages busy services depend on.
+//!
+//! These are hints: failures are ignored, and they do nothing where `posix_fadvise` isn't available. On Windows files are opened for sequential scanning by [open_file] instead.
+use std::fs::File;
+use std::os::raw::c_int;
+#[cfg(target_os = "linux")]
+use std::os::raw::c_long;
+use std::path::Path;
+
+use super::forensic::open_file;
+
+/// The `posix_fadvise` advice that the file will be read sequentially.
+const POSIX_FADV_SEQUENTIAL: c_int = 2;
+
+/// The `posix_fadvise` advice that the file will be needed soon.
+const POSIX_FADV_WILLNEED: c_int = 3;
+
+/// The `posix_fadvise` advice that the file's cached pages won't be needed again.
+const POSIX_FADV_DONTNEED: c_int = 4;
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn posix_fadvise(fd: c_int, offset: c_long, len: c_long, advice: c_int) -> c_int;
+}
+
+/// Give the kernel `advice` about the whole of `file`.
+#[cfg(target_os = "linux")]
+fn advise(file: &File, advice: c_int) {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the descriptor stays open for the call, and a length of 0 means the whole file.
+    unsafe {
+        posix_fadvise(file.as_raw_fd(), 0, 0, advice);
+    }
+}
+
+/// Give the kernel advice about a file. There is no `posix_fadvise` here, so this does nothing.
+#[cfg(not(target_os = "linux"))]
+fn advise(_file: &File, _advice: c_int) {}
+
+/// Tell the kernel `file` is about to be read from start to end.
+pub fn read_sequentially(file: &File) {
+    advise(file, POSIX_FADV_SEQUENTIAL);
+}
+
+/// Ask the kernel to start reading the file at `path` into the cache, so it's ready when its turn comes.
+///
+/// Only call this for regular files: opening a FIFO or device can block.
+pub fn prefetch(path: &Path) {
+    if let Ok(file) = open_file(path) {
+        advise(&file, POSIX_FADV_WILLNEED);
+    }
+}
+
+/// Tell the kernel the cached pages of `file` won't be needed again.
+pub fn drop_cached(file: &File) {
+    advise(file, POSIX_FADV_DONTNEED);
+}
@@ -0,0 +1,113 @@
+//! Contains the logic for splitting files into content-defined chunks with FastCDC, for the `blocks` and `hunt` subcommands.
+//!
+//! Fixed-size blocks start at multiples of the block size, so inserting a single byte near the start of a file shifts every block after it, and its entropy profile no longer lines up with the original's. [FastCDC](fastcdc) picks chunk boundaries from the content itself instead, so after an insertion the boundaries fall back into place and the rest of the profile matches again.
+//!
+//! The [chunk_entropy] function measures each chunk of a file as a [Window], and [chunk_profile] returns just their entropies, to compare as block profiles are.
+//!
+//! Chunks average the size asked for, and are between a quarter and four times as long.
+use std::io::{ self, Read };
+use std::path::Path;
+
+use fastcdc::v2020::{ StreamCDC, AVERAGE_MAX, AVERAGE_MIN };
+use zeroize::Zeroize;
+
+use super::forensic::open_file;
+use super::histogram::ByteHistogram;
+use super::structs::Window;
+
+/// Check that chunks can average `average` bytes.
+///
+/// Returns the average rounded down to an even number of bytes, as FastCDC needs, or an error message if it is out of range.
+fn average_size(average: usize) -> Result<usize, String> {
+    match (AVERAGE_MIN..=AVERAGE_MAX).contains(&average) {
+        true => Ok(average & !1),
+        false => Err(format!("Average chunk size must be between {AVERAGE_MIN} and {AVERAGE_MAX} bytes")),
+    }
+}
+
+/// Split everything `reader` yields into content-defined chunks averaging `average` bytes, handing each chunk and its offset to `f`.
+///
+/// Each chunk is wiped once `f` returns. Returns an error if `reader` fails, or if `average` is out of range.
+pub fn for_each_chunk<R: Read, F: FnMut(u64, &[u8])>(reader: R, average: usize, mut f: F) -> io::Result<()> {
+    let average = average_size(average).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let min = (average / 4) & !1;
+    for chunk in StreamCDC::new(reader, min, average, average * 4) {
+        let mut chunk = chunk?;
+        f(chunk.offset, &chunk.data);
+        chunk.data.zeroize();
+    }
+    Ok(())
+}
+
+/// Calculate the entropy of every content-defined chunk of the file at `path`, with chunks averaging `average` bytes.
+///
+/// Returns a [Vec] of [Window]s, one per chunk, or an error message if the file can't be read or `average` is out of range.
+pub fn chunk_entropy(path: &Path, average: usize) -> Result<Vec<Window>, String> {
+    average_size(average)?;
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let file = open_file(path).map_err(error)?;
+    let mut windows = Vec::new();
+    for_each_chunk(file, average, |offset, chunk| {
+        windows.push(Window {
+            offset,
+            length: chunk.len() as u64,
+            entropy: ByteHistogram::of(chunk).entropy(),
+        });
+    }).map_err(error)?;
+    Ok(windows)
+}
+
+/// Calculate the entropy profile of the file at `path` over content-defined chunks averaging `average` bytes, as [super::block_profile] does over fixed blocks.
+///
+/// Returns a [Vec] of entropies, one per chunk, or an error message.
+pub fn chunk_profile(path: &Path, average: usize) -> Result<Vec<f64>, String> {
+    chunk_entropy(path, average).map(|windows| windows.iter().map(|window| window.entropy).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ average_size, for_each_chunk };
+
+    /// Split `data` into chunks averaging 256 bytes and return their contents.
+    fn chunks(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut chunks = Vec::new();
+        for_each_chunk(data, 256, |_, chunk| chunks.push(chunk.to_vec())).unwrap();
+        chunks
+    }
+
+    #[test]
+    fn chunks_realign_after_an_insertion() {
+        // A simple generator, so the data has content to cut on.
+        let mut state = 0x2545f491u32;
+        let data: Vec<u8> = (0..16 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let mut shifted = data.clone();
+        shifted.insert(10, 0xaa);
+
+        let original = chunks(&data);
+        let after = chunks(&shifted);
+        assert_eq!(original.concat(), data);
+        assert!(original.len() > 8);
+        // Only the chunks up to the insertion differ.
+        let shared = original
+            .iter()
+            .rev()
+            .zip(after.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        assert!(shared >= original.len() - 2, "{shared} of {} chunks realigned", original.len());
+    }
+
+    #[test]
+    fn average_size_must_be_in_range() {
+        assert!(average_size(255).is_err());
+        assert_eq!(average_size(4097), Ok(4096));
+        assert!(average_size(8 * 1024 * 1024).is_err());
+    }
+}
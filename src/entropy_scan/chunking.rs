@@ -0,0 +1,78 @@
+//! Contains the logic for picking a chunk size per file, for the block-based commands' `--adaptive-chunks` option.
+//!
+//! The [adaptive_chunk_size] function aims for about [TARGET_CHUNKS] chunks per file: small files get small chunks, so a short payload isn't averaged away, and huge files get large ones, so they're read in fewer, cheaper steps.
+//!
+//! Executables get chunks no larger than a page, see [EXECUTABLE_MAX_CHUNK], since packed or encrypted sections are often only a few pages long.
+//!
+//! The [chunk_size_for] function reads a file's size and first bytes and picks its chunk size.
+use std::io::{ self, Read };
+use std::path::Path;
+
+use super::forensic::open_file;
+use super::magic::{ sniff, SNIFF_LEN };
+
+/// The number of chunks a file is split into, give or take the rounding to a power of two.
+pub const TARGET_CHUNKS: u64 = 256;
+
+/// The smallest chunk size, below which even random data no longer reads as high entropy.
+pub const MIN_CHUNK: usize = 512;
+
+/// The largest chunk size.
+pub const MAX_CHUNK: usize = 1024 * 1024;
+
+/// The largest chunk size for executables: a memory page.
+pub const EXECUTABLE_MAX_CHUNK: usize = 4096;
+
+/// The MIME types of the executables [EXECUTABLE_MAX_CHUNK] applies to.
+pub const EXECUTABLE_MIMES: &[&str] = &[
+    "application/x-executable",
+    "application/vnd.microsoft.portable-executable",
+    "application/x-mach-binary",
+];
+
+/// Pick the chunk size for a file of `size` bytes starting with `head`.
+///
+/// Returns a power of two between [MIN_CHUNK] and [MAX_CHUNK], or [EXECUTABLE_MAX_CHUNK] for executables.
+pub fn adaptive_chunk_size(size: u64, head: &[u8]) -> usize {
+    let executable = sniff(head).is_some_and(|magic| EXECUTABLE_MIMES.contains(&magic.mime));
+    let max = match executable {
+        true => EXECUTABLE_MAX_CHUNK,
+        false => MAX_CHUNK,
+    };
+    let wanted = (size / TARGET_CHUNKS).max(1).next_power_of_two();
+    usize::try_from(wanted).unwrap_or(max).clamp(MIN_CHUNK, max)
+}
+
+/// Pick the chunk size for the file at `path`, see [adaptive_chunk_size].
+///
+/// Returns the chunk size, or an error message if the file can't be read.
+pub fn chunk_size_for(path: &Path) -> Result<usize, String> {
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let file = open_file(path).map_err(error)?;
+    let size = file.metadata().map_err(error)?.len();
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64).read_to_end(&mut head).map_err(error)?;
+    Ok(adaptive_chunk_size(size, &head))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ adaptive_chunk_size, EXECUTABLE_MAX_CHUNK, MAX_CHUNK, MIN_CHUNK };
+
+    #[test]
+    fn chunks_grow_with_the_file() {
+        assert_eq!(adaptive_chunk_size(0, b""), MIN_CHUNK);
+        assert_eq!(adaptive_chunk_size(16 * 1024, b"text"), MIN_CHUNK);
+        assert_eq!(adaptive_chunk_size(1024 * 1024, b"text"), 4096);
+        assert_eq!(adaptive_chunk_size(1024 * 1024 + 256, b"text"), 8192);
+        assert_eq!(adaptive_chunk_size(64 * 1024 * 1024 * 1024, b"text"), MAX_CHUNK);
+    }
+
+    #[test]
+    fn executables_get_page_sized_chunks() {
+        let size = 512 * 1024 * 1024;
+        assert_eq!(adaptive_chunk_size(size, b"\x7fELF\x02\x01\x01"), EXECUTABLE_MAX_CHUNK);
+        assert_eq!(adaptive_chunk_size(size, b"MZ\x90\x00"), EXECUTABLE_MAX_CHUNK);
+        assert_eq!(adaptive_chunk_size(64 * 1024, b"\x7fELF"), MIN_CHUNK);
+    }
+}
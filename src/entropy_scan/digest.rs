@@ -0,0 +1,279 @@
+//! Contains small, dependency-free MD5 and SHA-256 implementations.
+//!
+//! The [Md5] struct hashes data fed to it in pieces, and [md5_file] hashes a whole file without loading it into memory.
+//!
+//! MD5 is only used to compare against the digests package managers already record, and to spot archives nested in themselves; it is not a security boundary.
+//!
+//! The [Sha256] struct and [sha256_file] do the same with SHA-256, for digests recorded as evidence.
+use std::io;
+use std::path::Path;
+
+use super::for_each_block;
+use super::forensic::open_file;
+
+/// The block size files are hashed in.
+const HASH_BLOCK: usize = 64 * 1024;
+
+/// The per-round shift amounts.
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 4,
+    11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// The per-round constants, the integer parts of `abs(sin(i + 1)) * 2^32`.
+const CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a,
+    0xa8304613, 0xfd469501, 0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be,
+    0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340,
+    0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8,
+    0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+    0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa,
+    0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92,
+    0xffeff47d, 0x85845dd1, 0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1,
+    0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// The SHA-256 round constants, the first 32 bits of the fractional parts of the cube roots of the first 64 primes.
+const SHA256_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// An incremental MD5 hasher.
+#[derive(Debug, Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+impl Default for Md5 {
+    fn default() -> Md5 {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+}
+
+impl Md5 {
+    /// Hash the next piece of data.
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+        if !self.buffer.is_empty() {
+            let wanted = (64 - self.buffer.len()).min(bytes.len());
+            self.buffer.extend_from_slice(&bytes[..wanted]);
+            bytes = &bytes[wanted..];
+            if self.buffer.len() < 64 {
+                return;
+            }
+            let block: [u8; 64] = self.buffer[..].try_into().unwrap();
+            self.compress(&block);
+            self.buffer.clear();
+        }
+        let mut blocks = bytes.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block.try_into().unwrap());
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    /// Finish hashing and return the digest as lowercase hex.
+    pub fn hex(mut self) -> String {
+        let bits = self.length.wrapping_mul(8);
+        let mut padding = vec![0x80u8];
+        padding.resize(1 + ((55usize.wrapping_sub(self.buffer.len())) % 64), 0);
+        padding.extend_from_slice(&bits.to_le_bytes());
+        // Padding shouldn't count towards the message length, which is already fixed above.
+        let length = self.length;
+        self.update(&padding);
+        self.length = length;
+
+        self.state
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Mix one 64-byte block into the state.
+    fn compress(&mut self, block: &[u8; 64]) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i {
+                0..16 => ((b & c) | (!b & d), i),
+                16..32 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                32..48 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(CONSTANTS[i])
+                .wrapping_add(words[g])
+                .rotate_left(SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+/// An incremental SHA-256 hasher.
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Sha256 {
+        Sha256 {
+            state: [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19],
+            buffer: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+}
+
+impl Sha256 {
+    /// Hash the next piece of data.
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+        if !self.buffer.is_empty() {
+            let wanted = (64 - self.buffer.len()).min(bytes.len());
+            self.buffer.extend_from_slice(&bytes[..wanted]);
+            bytes = &bytes[wanted..];
+            if self.buffer.len() < 64 {
+                return;
+            }
+            let block: [u8; 64] = self.buffer[..].try_into().unwrap();
+            self.compress(&block);
+            self.buffer.clear();
+        }
+        let mut blocks = bytes.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block.try_into().unwrap());
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    /// Finish hashing and return the digest as lowercase hex.
+    pub fn hex(mut self) -> String {
+        let bits = self.length.wrapping_mul(8);
+        let mut padding = vec![0x80u8];
+        padding.resize(1 + ((55usize.wrapping_sub(self.buffer.len())) % 64), 0);
+        padding.extend_from_slice(&bits.to_be_bytes());
+        self.update(&padding);
+
+        self.state
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Mix one 64-byte block into the state.
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut words = [0u32; 64];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = words[i - 15].rotate_right(7) ^ words[i - 15].rotate_right(18) ^ (words[i - 15] >> 3);
+            let s1 = words[i - 2].rotate_right(17) ^ words[i - 2].rotate_right(19) ^ (words[i - 2] >> 10);
+            words[i] = words[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(words[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let first = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(SHA256_CONSTANTS[i])
+                .wrapping_add(words[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let second = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(first);
+            d = c;
+            c = b;
+            b = a;
+            a = first.wrapping_add(second);
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+/// Calculate the MD5 digest of the file at `path` as lowercase hex.
+pub fn md5_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Md5::default();
+    for_each_block(open_file(path)?, HASH_BLOCK, |block| hasher.update(block))?;
+    Ok(hasher.hex())
+}
+
+/// Calculate the SHA-256 digest of the file at `path` as lowercase hex.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::default();
+    for_each_block(open_file(path)?, HASH_BLOCK, |block| hasher.update(block))?;
+    Ok(hasher.hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ Md5, Sha256 };
+
+    fn sha256(pieces: &[&[u8]]) -> String {
+        let mut hasher = Sha256::default();
+        for piece in pieces {
+            hasher.update(piece);
+        }
+        hasher.hex()
+    }
+
+    #[test]
+    fn sha256_matches_known_digests() {
+        assert_eq!(sha256(&[]), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(sha256(&[b"abc"]), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            sha256(&[b"abcdbcdecdefdefgefghfghighij", b"hijkijkljklmklmnlmnomnopnopq"]),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn md5_matches_known_digests() {
+        let mut hasher = Md5::default();
+        hasher.update(b"abc");
+        assert_eq!(hasher.hex(), "900150983cd24fb0d6963f7d28e17f72");
+    }
+}
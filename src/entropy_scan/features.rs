@@ -0,0 +1,161 @@
+//! Contains the logic for extracting a numeric feature vector from each scanned file, for training classifiers on scan output.
+//!
+//! [file_features] reads a file once more after it has been measured, in [adaptive](super::chunking::adaptive_chunk_size) chunks, and combines what it finds with the file's [FileEntropy]: its entropy, size, and printable ratio, the chi-square of its byte distribution, the spread of its chunk entropies, and its [type class](type_class).
+//!
+//! The type classes, in [TYPE_CLASSES], are meant to be one-hot encoded, one column per class.
+use std::io::{ self, Read, Seek };
+use std::path::{ Path, PathBuf };
+
+use super::chunking::{ adaptive_chunk_size, EXECUTABLE_MIMES };
+use super::for_each_block;
+use super::forensic::open_file;
+use super::histogram::ByteHistogram;
+use super::magic::{ mime_type, sniff, SNIFF_LEN };
+use super::structs::FileEntropy;
+use super::wipe::Wiped;
+
+/// The type classes files are sorted into, see [type_class].
+pub const TYPE_CLASSES: [&str; 7] = ["executable", "archive", "image", "document", "text", "encrypted", "other"];
+
+/// The MIME types of archives and compressed files.
+const ARCHIVE_MIMES: &[&str] = &[
+    "application/zip",
+    "application/gzip",
+    "application/x-tar",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/zstd",
+];
+
+/// The MIME types of documents and databases.
+const DOCUMENT_MIMES: &[&str] = &["application/pdf", "application/x-ole-storage", "application/vnd.sqlite3"];
+
+/// Holds the feature vector of a single file.
+///
+/// The `path`, `entropy`, `size`, and `printable_ratio` fields are as in the file's [FileEntropy].
+///
+/// The `chi_square` field holds the chi-square statistic of the file's byte counts against a uniform distribution. Random data stays close to 255, its degrees of freedom, while encodings such as base64 that use few byte values evenly score far higher, whatever their entropy.
+///
+/// The `chunk_size` and `chunks` fields hold the size of the chunks the file was split into and how many there were.
+///
+/// The `chunk_entropy_mean`, `chunk_entropy_std_dev`, `chunk_entropy_min`, and `chunk_entropy_max` fields hold the spread of the chunks' entropies. A payload hidden in an ordinary file shows up as a wide spread. They are all 0.0 for an empty file.
+///
+/// The `type_class` field holds one of [TYPE_CLASSES].
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Features {
+    pub path: PathBuf,
+    pub entropy: f64,
+    pub size: u64,
+    pub printable_ratio: f64,
+    pub chi_square: f64,
+    pub chunk_size: usize,
+    pub chunks: usize,
+    pub chunk_entropy_mean: f64,
+    pub chunk_entropy_std_dev: f64,
+    pub chunk_entropy_min: f64,
+    pub chunk_entropy_max: f64,
+    pub type_class: &'static str,
+}
+
+/// Sort a file starting with `head` into one of [TYPE_CLASSES], by its signature or, failing that, whether it looks like text.
+pub fn type_class(head: &[u8]) -> &'static str {
+    if sniff(head).is_some_and(|magic| magic.encrypted) {
+        return "encrypted";
+    }
+    let mime = mime_type(head);
+    match mime {
+        _ if EXECUTABLE_MIMES.contains(&mime) => "executable",
+        _ if ARCHIVE_MIMES.contains(&mime) => "archive",
+        _ if DOCUMENT_MIMES.contains(&mime) => "document",
+        _ if mime.starts_with("image/") => "image",
+        _ if mime.starts_with("text/") => "text",
+        _ => "other",
+    }
+}
+
+/// Calculate the chi-square statistic of byte `counts` against a uniform distribution, 0.0 if there are none.
+pub fn chi_square(counts: &[u64; 256]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let expected = total as f64 / 256.0;
+    counts
+        .iter()
+        .map(|count| (*count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// Extract the feature vector of the file `entropy` was measured from, reading it again.
+///
+/// Returns the [Features], or an error message if the file can't be read, e.g. because it was standard input or an archive member.
+pub fn file_features(entropy: &FileEntropy) -> Result<Features, String> {
+    let path: &Path = &entropy.path;
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let mut file = open_file(path).map_err(error)?;
+    let size = file.metadata().map_err(error)?.len();
+    // Like the chunks, the head is wiped once read.
+    let mut head = Wiped::with_capacity(SNIFF_LEN, true);
+    head.read_from(file.by_ref().take(SNIFF_LEN as u64)).map_err(error)?;
+    file.rewind().map_err(error)?;
+
+    let chunk_size = adaptive_chunk_size(size, &head);
+    let mut histogram = ByteHistogram::default();
+    let mut chunk_entropies = Vec::new();
+    for_each_block(file, chunk_size, |chunk| {
+        histogram.update(chunk);
+        chunk_entropies.push(ByteHistogram::of(chunk).entropy());
+    }).map_err(error)?;
+
+    let chunks = chunk_entropies.len();
+    let mean = match chunks {
+        0 => 0.0,
+        _ => chunk_entropies.iter().sum::<f64>() / chunks as f64,
+    };
+    let variance = match chunks {
+        0 => 0.0,
+        _ => chunk_entropies.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / chunks as f64,
+    };
+    Ok(Features {
+        path: entropy.path.clone(),
+        entropy: entropy.entropy,
+        size: entropy.size,
+        printable_ratio: entropy.printable_ratio,
+        chi_square: chi_square(histogram.counts()),
+        chunk_size,
+        chunks,
+        chunk_entropy_mean: mean,
+        chunk_entropy_std_dev: variance.sqrt(),
+        chunk_entropy_min: chunk_entropies.iter().copied().reduce(f64::min).unwrap_or(0.0),
+        chunk_entropy_max: chunk_entropies.iter().copied().reduce(f64::max).unwrap_or(0.0),
+        type_class: type_class(&head),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ chi_square, type_class };
+
+    #[test]
+    fn chi_square_separates_uniform_from_skewed_bytes() {
+        assert_eq!(chi_square(&[4; 256]), 0.0);
+        let mut counts = [0; 256];
+        counts[..64].fill(16);
+        assert_eq!(chi_square(&counts), 3072.0);
+        assert_eq!(chi_square(&[0; 256]), 0.0);
+    }
+
+    #[test]
+    fn files_are_sorted_into_type_classes() {
+        assert_eq!(type_class(b"\x7fELF\x02\x01"), "executable");
+        assert_eq!(type_class(b"PK\x03\x04"), "archive");
+        assert_eq!(type_class(b"%PDF-1.7"), "document");
+        assert_eq!(type_class(b"\x89PNG\r\n\x1a\n"), "image");
+        assert_eq!(type_class(b"LUKS\xba\xbe\x00\x02"), "encrypted");
+        assert_eq!(type_class(b"plain text"), "text");
+        assert_eq!(type_class(b"\x00\x01\x02"), "other");
+    }
+}
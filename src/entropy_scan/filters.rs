@@ -0,0 +1,160 @@
+//! Contains the filters applied while collecting targets.
+//!
+//! The [TargetFilter] struct decides which files [super::collect_targets] keeps and which directories it walks into, so unwanted files are never opened.
+use std::fs;
+use std::io::Read;
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+
+use super::forensic::open_file;
+use super::glob::Globs;
+use super::magic::{ mime_type, SNIFF_LEN };
+
+/// How symbolic links found while collecting targets are treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Leave out every link.
+    Skip,
+    /// Keep links to files, but don't walk into links to directories.
+    #[default]
+    Files,
+    /// Follow every link, visiting each directory and file only once.
+    Follow,
+}
+
+/// Holds the filters applied to each file found while collecting targets.
+///
+/// The `min_size` and `max_size` fields hold the smallest and largest file sizes, in bytes, to keep.
+///
+/// The `skip_names` field holds file and directory names to leave out entirely, such as `node_modules`. A skipped directory is not walked into.
+///
+/// The `modified_after` and `modified_before` fields hold the earliest and latest modification times to keep, so files unchanged since the last run aren't even opened.
+///
+/// The `skip_hidden` field sets whether hidden files and directories, those whose names start with a `.`, are left out. A skipped directory is not walked into.
+///
+/// The `skip_paths` field holds directories that are not walked into, such as the mount points of other filesystems.
+///
+/// The `max_depth` field holds how many directory levels below a target are walked: 1 keeps only the files directly inside it. [None] walks the whole tree.
+///
+/// The `symlinks` field holds how symbolic links are treated, see [SymlinkPolicy].
+///
+/// The `one_file_system` field sets whether the walk stays on the filesystem each target is on, so mounts beneath it such as `/proc` or NFS shares are not walked into. It needs device IDs, so it has no effect on platforms without them.
+///
+/// The `include` field holds globs a file must match at least one of, if any are given.
+///
+/// The `exclude` field holds globs for files and directories to leave out. An excluded directory is not walked into.
+///
+/// The `extensions` field holds the file extensions to keep, without the leading dot, if any are given. They are compared case-insensitively.
+///
+/// The `mime_types` field holds the MIME types to keep, if any are given, sniffed from each file's first bytes. A type ending in `/*`, such as `image/*`, keeps every subtype.
+///
+/// The `ignore_files` field sets whether the `.gitignore` and `.entropyscanignore` files found while walking are honored, along with the exclude files of git repositories, see [super::ignore].
+///
+/// The default [TargetFilter] keeps every file.
+#[derive(Debug, Clone, Default)]
+pub struct TargetFilter {
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+    pub skip_names: Vec<String>,
+    pub skip_hidden: bool,
+    pub skip_paths: Vec<PathBuf>,
+    pub max_depth: Option<usize>,
+    pub symlinks: SymlinkPolicy,
+    pub one_file_system: bool,
+    pub include: Globs,
+    pub exclude: Globs,
+    pub extensions: Vec<String>,
+    pub mime_types: Vec<String>,
+    pub ignore_files: bool,
+}
+
+impl TargetFilter {
+    /// Check whether `path`, a directory if `is_dir` is set, is named in `skip_names`, is hidden when hidden files are skipped, or matches an `exclude` glob.
+    fn skipped(&self, path: &Path, is_dir: bool) -> bool {
+        path.file_name().is_some_and(|name| {
+            self.skip_names.iter().any(|skip| name == skip.as_str()) ||
+                (self.skip_hidden && name.to_string_lossy().starts_with('.'))
+        }) ||
+            self.exclude.matches(path, is_dir)
+    }
+
+    /// Check whether a file `depth` levels below a target, 1 for files directly inside it, is within `max_depth`.
+    pub fn within_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    /// Check whether the directory at `path` should be walked into.
+    pub fn descends_into(&self, path: &Path) -> bool {
+        !self.skipped(path, true) && !self.skip_paths.iter().any(|skip| skip == path)
+    }
+
+    /// Check whether the file at `path` passes every filter.
+    ///
+    /// Files whose metadata can't be read are kept, so the error surfaces when they are scanned.
+    pub fn accepts(&self, path: &Path) -> bool {
+        if self.skipped(path, false) {
+            return false;
+        }
+        if !self.include.is_empty() && !self.include.matches(path, false) {
+            return false;
+        }
+        if !self.extensions.is_empty() && !self.extension_matches(path) {
+            return false;
+        }
+        let needs_metadata = self.min_size.is_some() ||
+            self.max_size.is_some() ||
+            self.modified_after.is_some() ||
+            self.modified_before.is_some();
+        let kept = match needs_metadata {
+            false => true,
+            true =>
+                match fs::metadata(path) {
+                    Ok(metadata) => self.metadata_matches(&metadata),
+                    Err(_) => true,
+                }
+        };
+        // Sniffing opens the file, so it comes last.
+        kept && (self.mime_types.is_empty() || self.mime_matches(path))
+    }
+
+    /// Check whether a file's size and modification time are within bounds. A file whose modification time can't be read is kept.
+    fn metadata_matches(&self, metadata: &fs::Metadata) -> bool {
+        let sized = self.min_size.is_none_or(|min_size| metadata.len() >= min_size) &&
+            self.max_size.is_none_or(|max_size| metadata.len() <= max_size);
+        let modified = match metadata.modified() {
+            Ok(modified) =>
+                self.modified_after.is_none_or(|after| modified >= after) &&
+                self.modified_before.is_none_or(|before| modified <= before),
+            Err(_) => true,
+        };
+        sized && modified
+    }
+
+    /// Check whether the extension of `path` is one of `extensions`.
+    fn extension_matches(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|extension| {
+            let extension = extension.to_string_lossy();
+            self.extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(&extension))
+        })
+    }
+
+    /// Check whether the MIME type sniffed from the start of the file at `path` is one of `mime_types`.
+    fn mime_matches(&self, path: &Path) -> bool {
+        // Opening a FIFO or device can hang, so special files are let through to be reported when scanned.
+        if !fs::metadata(path).is_ok_and(|metadata| metadata.is_file()) {
+            return true;
+        }
+        let mut head = Vec::new();
+        let read = open_file(path).and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut head));
+        if read.is_err() {
+            return true;
+        }
+        let mime = mime_type(&head);
+        self.mime_types.iter().any(|wanted| match wanted.strip_suffix("/*") {
+            Some(family) => mime.split('/').next() == Some(family),
+            None => wanted.eq_ignore_ascii_case(mime),
+        })
+    }
+}
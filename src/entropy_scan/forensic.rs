@@ -0,0 +1,78 @@
+//! Contains the logic for forensically sound scans.
+//!
+//! The [open_file] function opens every target read-only. Once [preserve_access_times] has been called, it also asks the kernel not to update access times, where the platform and permissions allow it.
+//!
+//! The [provenance] function records the SHA-256 digest of the running binary and the arguments it was given, so a report can be tied to the exact tool and command that produced it.
+use std::env;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use super::digest::sha256_file;
+use super::structs::Provenance;
+
+/// Whether targets are opened without updating their access times.
+static PRESERVE_ACCESS_TIMES: AtomicBool = AtomicBool::new(false);
+
+/// The Windows `CreateFile` flag that tells the cache manager the file will be read from start to end.
+#[cfg(windows)]
+const FILE_FLAG_SEQUENTIAL_SCAN: u32 = 0x08000000;
+
+/// Open every target from now on without updating its access time, where permitted.
+pub fn preserve_access_times() {
+    PRESERVE_ACCESS_TIMES.store(true, Ordering::Relaxed);
+}
+
+/// Open the file at `path` read-only.
+///
+/// After [preserve_access_times], the file is opened with `O_NOATIME`. Only the file's owner, or root, may ask for that, so other files fall back to a plain open.
+#[cfg(target_os = "linux")]
+pub fn open_file(path: &Path) -> io::Result<File> {
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    if PRESERVE_ACCESS_TIMES.load(Ordering::Relaxed) {
+        match OpenOptions::new().read(true).custom_flags(libc::O_NOATIME).open(path) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            opened => {
+                return opened;
+            }
+        }
+    }
+    File::open(path)
+}
+
+/// Open the file at `path` read-only, for sequential scanning.
+///
+/// Access times can only be preserved on Linux, so [preserve_access_times] has no effect here.
+#[cfg(windows)]
+pub fn open_file(path: &Path) -> io::Result<File> {
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+
+    OpenOptions::new().read(true).custom_flags(FILE_FLAG_SEQUENTIAL_SCAN).open(path)
+}
+
+/// Open the file at `path` read-only.
+///
+/// Access times can only be preserved on Linux, so [preserve_access_times] has no effect here.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn open_file(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+/// Describe the running binary and the arguments it was given.
+///
+/// Returns a [Provenance], or an error message if the binary can't be found or read.
+pub fn provenance() -> Result<Provenance, String> {
+    let binary = env::current_exe().map_err(|e| format!("Couldn't find the running binary: {e}"))?;
+    let sha256 = sha256_file(&binary).map_err(|e| format!("Couldn't hash {}: {e}", binary.to_string_lossy()))?;
+    Ok(Provenance {
+        binary,
+        sha256,
+        arguments: env::args_os()
+            .map(|argument| argument.to_string_lossy().to_string())
+            .collect(),
+    })
+}
@@ -0,0 +1,57 @@
+//! Contains the logic for limiting a scan to the files changed in a git commit range.
+//!
+//! The [changed_targets] function asks `git diff` which files a range such as `origin/main..HEAD` touched, so pull request pipelines only scan the delta.
+use std::path::{ Path, PathBuf };
+use std::process::Command;
+
+use super::filters::TargetFilter;
+
+/// Check whether every directory between `root` and `path`, and the file itself, passes `filter`, and the file is within its depth limit.
+fn accepted(root: &Path, path: &Path, filter: &TargetFilter) -> bool {
+    let directories: Vec<&Path> = path
+        .ancestors()
+        .skip(1)
+        .take_while(|ancestor| *ancestor != root)
+        .collect();
+    filter.within_depth(directories.len() + 1) &&
+        directories.iter().all(|ancestor| filter.descends_into(ancestor)) &&
+        filter.accepts(path)
+}
+
+/// Collect the files under `target` that were added, copied, modified, or renamed in the git commit `range`.
+///
+/// Deleted files are left out. Paths are joined to `target` and filtered like [super::collect_targets] would filter them.
+///
+/// Returns a [Vec] of [PathBuf]s, or an error message if `target` isn't in a git repository or `range` can't be resolved.
+pub fn changed_targets(target: &Path, range: &str, filter: &TargetFilter) -> Result<Vec<PathBuf>, String> {
+    // `--relative` limits the diff to a directory, so a single file is looked for in its parent.
+    let directory = match target.is_dir() {
+        true => target,
+        false => target.parent().unwrap_or(Path::new(".")),
+    };
+    // An empty parent means the current directory.
+    let directory = match directory.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => directory,
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["diff", "--name-only", "-z", "--relative", "--diff-filter=ACMR", range, "--"])
+        .output()
+        .map_err(|e| format!("Couldn't run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff {range} failed: {}", stderr.trim()));
+    }
+
+    let targets = output.stdout
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| directory.join(String::from_utf8_lossy(name).as_ref()))
+        .filter(|path| target.is_dir() || path.file_name() == target.file_name())
+        .filter(|path| path.is_file())
+        .filter(|path| accepted(directory, path, filter))
+        .collect();
+    Ok(targets)
+}
@@ -0,0 +1,125 @@
+//! Contains the `--include` and `--exclude` target filters, matched with the [::ignore] crate's [overrides](::ignore::overrides), so globs have the syntax of `.gitignore` lines: `*` and `?` within a path component, `[abc]`, `[a-z]`, and `[!abc]` character classes, `{a,b}` alternatives, and `**` for any number of whole components.
+//!
+//! A glob without a `/` is matched against the file name alone, so `*.iso` matches ISO files in any directory. A glob with a `/` is matched against the trailing components of the path, so `bin/**` matches everything under any `bin` directory. Unlike in a `.gitignore` file, a leading `!` or `#` is part of the name.
+//!
+//! A [Glob] is a single pattern, checked when it is parsed, and [Globs] matches a set of them at once.
+use std::path::Path;
+use std::str::FromStr;
+
+use ::ignore::overrides::{ Override, OverrideBuilder };
+
+/// A single glob pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob(String);
+
+impl FromStr for Glob {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.trim_matches('/').is_empty() {
+            return Err(format!("Invalid glob: {value:?} (empty pattern)"));
+        }
+        let glob = Glob(value.to_string());
+        Globs::build([&glob]).map_err(|e| format!("Invalid glob: {value} ({e})"))?;
+        Ok(glob)
+    }
+}
+
+impl Glob {
+    /// The pattern as it was given.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Write the pattern as an override line, which is matched as a `.gitignore` line is.
+    ///
+    /// A `.gitignore` line with a `/` in it is anchored to the directory it is read from, so it gets a leading `**/` to match anywhere. A leading `!` or `#` is escaped, as it would otherwise be read as a negation or a comment.
+    fn line(&self) -> String {
+        let pattern = self.0.trim_start_matches('/');
+        let pattern = match pattern.contains('/') && !pattern.starts_with("**/") {
+            true => format!("**/{pattern}"),
+            false => pattern.to_string(),
+        };
+        match pattern.starts_with(['!', '#']) {
+            true => format!("\\{pattern}"),
+            false => pattern,
+        }
+    }
+}
+
+/// Holds a set of globs, such as every `--exclude`, matched together.
+#[derive(Debug, Clone)]
+pub struct Globs(Override);
+
+impl Default for Globs {
+    fn default() -> Globs {
+        Globs(Override::empty())
+    }
+}
+
+impl Globs {
+    /// Build a matcher for `globs`.
+    fn build<'a>(globs: impl IntoIterator<Item = &'a Glob>) -> Result<Override, ::ignore::Error> {
+        let mut builder = OverrideBuilder::new("/");
+        for glob in globs {
+            builder.add(&glob.line())?;
+        }
+        builder.build()
+    }
+
+    /// Match `globs` together.
+    pub fn new(globs: &[Glob]) -> Globs {
+        // Each glob was checked when it was parsed.
+        Globs(Globs::build(globs).unwrap_or_else(|_| Override::empty()))
+    }
+
+    /// Check whether there are no globs, which nothing matches.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check whether `path`, a directory if `is_dir` is set, matches any of the globs.
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        self.0.matched(path, is_dir).is_whitelist()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ Glob, Globs };
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Globs::new(&[pattern.parse::<Glob>().unwrap()]).matches(Path::new(path), false)
+    }
+
+    #[test]
+    fn name_globs_match_in_any_directory() {
+        assert!(matches("*.iso", "/data/images/disk.iso"));
+        assert!(matches("*.iso", "images/disk.iso"));
+        assert!(!matches("*.iso", "/data/images/disk.iso.txt"));
+        assert!(matches("file?.bin", "file1.bin"));
+        assert!(matches("[a-c]*.log", "/var/log/boot.log"));
+        assert!(!matches("[!a-c]*.log", "/var/log/boot.log"));
+        assert!(matches("!important", "/notes/!important"));
+    }
+
+    #[test]
+    fn path_globs_match_trailing_components() {
+        assert!(matches("bin/**", "/usr/local/bin/tool"));
+        assert!(matches("bin/**", "bin/nested/deeper/tool"));
+        assert!(!matches("bin/**", "/usr/sbin/tool"));
+        assert!(matches("src/**/*.rs", "/repo/src/a/b/main.rs"));
+        assert!(matches("src/**/*.rs", "/repo/src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "/repo/src/a/main.rs"));
+    }
+
+    #[test]
+    fn invalid_globs_are_rejected() {
+        assert!("[abc".parse::<Glob>().is_err());
+        assert!("/".parse::<Glob>().is_err());
+        assert!("[]]".parse::<Glob>().is_ok());
+    }
+}
@@ -0,0 +1,202 @@
+//! Contains the byte histogram every entropy calculation is built on.
+//!
+//! The [ByteHistogram] struct counts byte values over one or more slices. Histograms of separate chunks can be [merged](ByteHistogram::merge), so streamed, parallel, and chunked scans all reach the same [entropy](ByteHistogram::entropy) as reading the whole file at once.
+//!
+//! The [WordHistogram] struct does the same for 16-bit words, carrying an odd byte over from one slice to the next.
+use super::entropy_from_counts;
+
+/// Counts how often each byte value occurs.
+///
+/// Counts are kept as `u64`, so a histogram can cover files of any size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteHistogram {
+    counts: [u64; 256],
+    total: u64,
+}
+
+impl Default for ByteHistogram {
+    fn default() -> Self {
+        ByteHistogram {
+            counts: [0; 256],
+            total: 0,
+        }
+    }
+}
+
+impl ByteHistogram {
+    /// Build the histogram of a single slice.
+    pub fn of(bytes: &[u8]) -> ByteHistogram {
+        let mut histogram = ByteHistogram::default();
+        histogram.update(bytes);
+        histogram
+    }
+
+    /// Count the bytes of the next slice.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.counts[*byte as usize] += 1;
+        }
+        self.total += bytes.len() as u64;
+    }
+
+    /// Uncount the bytes of a slice previously passed to [ByteHistogram::update], e.g. bytes sliding out of a window.
+    pub fn remove(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.counts[*byte as usize] -= 1;
+        }
+        self.total -= bytes.len() as u64;
+    }
+
+    /// Add the counts of `other`, as if its bytes had been passed to [ByteHistogram::update].
+    pub fn merge(&mut self, other: &ByteHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+        self.total += other.total;
+    }
+
+    /// The number of times each byte value was seen, indexed by value.
+    pub fn counts(&self) -> &[u64; 256] {
+        &self.counts
+    }
+
+    /// The number of bytes seen.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The Shannon entropy of the bytes seen, in bits per byte.
+    ///
+    /// Returns a value between 0.0 and 8.0. An empty histogram has an entropy of 0.0.
+    pub fn entropy(&self) -> f64 {
+        entropy_from_counts(&self.counts, self.total)
+    }
+
+    /// The Shannon entropy of the bytes seen read as 4-bit nibbles, in bits per nibble.
+    ///
+    /// Returns a value between 0.0 and 4.0. An empty histogram has an entropy of 0.0.
+    pub fn nibble_entropy(&self) -> f64 {
+        let mut nibbles = [0u64; 16];
+        for (byte, count) in self.counts.iter().enumerate() {
+            nibbles[byte >> 4] += count;
+            nibbles[byte & 0x0f] += count;
+        }
+        entropy_from_counts(&nibbles, self.total * 2)
+    }
+
+    /// The chi-square statistic of the bytes seen against a uniform distribution.
+    ///
+    /// Random and well-encrypted data stays close to 255, the degrees of freedom, while compressed data is usually far above it. An empty histogram is 0.0.
+    pub fn chi_square(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let expected = (self.total as f64) / 256.0;
+        self.counts
+            .iter()
+            .map(|count| ((*count as f64) - expected).powi(2) / expected)
+            .sum()
+    }
+}
+
+/// Counts how often each 16-bit little-endian word occurs.
+///
+/// A byte left over at the end of one slice is paired with the first byte of the next, so a file can be fed in blocks of any size. A trailing odd byte at the end of the file is ignored.
+#[derive(Debug, Clone)]
+pub struct WordHistogram {
+    counts: Vec<u64>,
+    total: u64,
+    carry: Option<u8>,
+}
+
+impl Default for WordHistogram {
+    fn default() -> Self {
+        WordHistogram {
+            counts: vec![0; 1 << 16],
+            total: 0,
+            carry: None,
+        }
+    }
+}
+
+impl WordHistogram {
+    /// Count the words of the next slice.
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        if let Some(first) = self.carry.take() {
+            let Some((second, rest)) = bytes.split_first() else {
+                self.carry = Some(first);
+                return;
+            };
+            self.counts[u16::from_le_bytes([first, *second]) as usize] += 1;
+            self.total += 1;
+            bytes = rest;
+        }
+        let words = bytes.chunks_exact(2);
+        self.carry = words.remainder().first().copied();
+        for word in words {
+            self.counts[u16::from_le_bytes([word[0], word[1]]) as usize] += 1;
+            self.total += 1;
+        }
+    }
+
+    /// The Shannon entropy of the words seen, in bits per word.
+    ///
+    /// Returns a value between 0.0 and 16.0. An empty histogram has an entropy of 0.0.
+    pub fn entropy(&self) -> f64 {
+        entropy_from_counts(&self.counts, self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ ByteHistogram, WordHistogram };
+
+    #[test]
+    fn empty_histogram_is_zero() {
+        let histogram = ByteHistogram::default();
+        assert_eq!(histogram.total(), 0);
+        assert_eq!(histogram.entropy(), 0.0);
+        assert_eq!(histogram.chi_square(), 0.0);
+    }
+
+    #[test]
+    fn uniform_bytes_have_full_entropy() {
+        let bytes: Vec<u8> = (0..=255u8).cycle().take(256 * 4).collect();
+        let histogram = ByteHistogram::of(&bytes);
+        assert!((histogram.entropy() - 8.0).abs() < 1e-9);
+        assert_eq!(histogram.chi_square(), 0.0);
+    }
+
+    #[test]
+    fn single_value_has_no_entropy() {
+        let histogram = ByteHistogram::of(&[0x41; 1000]);
+        assert_eq!(histogram.entropy(), 0.0);
+        assert_eq!(histogram.counts()[0x41], 1000);
+        assert!((histogram.chi_square() - 255.0 * 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn merged_chunks_match_whole_input() {
+        let bytes: Vec<u8> = (0..10_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let whole = ByteHistogram::of(&bytes);
+        let mut merged = ByteHistogram::default();
+        for chunk in bytes.chunks(777) {
+            merged.merge(&ByteHistogram::of(chunk));
+        }
+        assert_eq!(merged, whole);
+        assert_eq!(merged.entropy(), whole.entropy());
+    }
+
+    #[test]
+    fn words_split_across_slices_are_counted_once() {
+        let bytes: Vec<u8> = (0..=255u8).cycle().take(4097).collect();
+        let mut whole = WordHistogram::default();
+        whole.update(&bytes);
+        let mut split = WordHistogram::default();
+        for chunk in bytes.chunks(333) {
+            split.update(chunk);
+        }
+        assert_eq!(split.total, 2048);
+        assert_eq!(split.counts, whole.counts);
+    }
+}
@@ -0,0 +1,147 @@
+//! Contains the logic for honoring `.gitignore` and `.entropyscanignore` files while collecting targets.
+//!
+//! An [IgnoreFile] holds the rules read from one directory, matched with the [::ignore] crate's gitignore matcher, so the full `.gitignore` syntax is supported. [super::collect_targets] keeps a stack of them as it walks, and [ignored] checks a path against the stack, so build output such as `target/` and `node_modules/` is never walked into.
+//!
+//! As with git, rules in deeper directories win over shallower ones, and a `!` rule re-includes what a shallower file ignored. A directory that is the root of a git repository also adds its `.git/info/exclude` file and the user's global excludes file (`core.excludesFile`, or `~/.config/git/ignore`), which lose to every `.gitignore` in the repository.
+//!
+//! Only ignore files inside the directories being scanned are read: scanning a subdirectory of a repository doesn't honor the `.gitignore` files above it.
+use std::path::{ Path, PathBuf };
+
+use ::ignore::gitignore::{ gitconfig_excludes_path, Gitignore, GitignoreBuilder };
+use ::ignore::Match;
+
+/// The ignore files read in each directory. Rules in later files take precedence.
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".entropyscanignore"];
+
+/// The exclude file of a git repository, relative to its root.
+const GIT_EXCLUDE_FILE: &str = ".git/info/exclude";
+
+/// Holds the ignore rules read from one directory.
+///
+/// The `base` field holds the directory the rules were read from. They only apply to paths under it.
+///
+/// The `matcher` field holds the rules.
+///
+#[derive(Debug, Clone)]
+pub struct IgnoreFile {
+    base: PathBuf,
+    matcher: Gitignore,
+}
+
+impl IgnoreFile {
+    /// Parse the rules in `text`, as read from the directory `base`. Invalid patterns are skipped.
+    pub fn parse(base: &Path, text: &str) -> IgnoreFile {
+        let mut builder = GitignoreBuilder::new(base);
+        for line in text.lines() {
+            let _ = builder.add_line(None, line);
+        }
+        IgnoreFile::build(base, &builder)
+    }
+
+    /// Read the files at `paths`, relative to the directory `base`, skipping those that are missing.
+    fn from_files(base: &Path, paths: &[PathBuf]) -> Option<IgnoreFile> {
+        let mut builder = GitignoreBuilder::new(base);
+        for path in paths {
+            // Unreadable files and invalid patterns are skipped, keeping the rest.
+            let _ = builder.add(path);
+        }
+        let file = IgnoreFile::build(base, &builder);
+        match file.matcher.is_empty() {
+            true => None,
+            false => Some(file),
+        }
+    }
+
+    /// Build the rules added to `builder` for paths under `base`, or no rules at all if it fails.
+    fn build(base: &Path, builder: &GitignoreBuilder) -> IgnoreFile {
+        IgnoreFile {
+            base: base.to_path_buf(),
+            matcher: builder
+                .build()
+                .unwrap_or_else(|_| Gitignore::empty()),
+        }
+    }
+
+    /// Read every file in [IGNORE_FILES] found in the directory `dir`.
+    ///
+    /// Returns [None] if `dir` holds no rules. Unreadable ignore files are treated as empty.
+    pub fn read(dir: &Path) -> Option<IgnoreFile> {
+        let paths: Vec<_> = IGNORE_FILES.iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+            .collect();
+        IgnoreFile::from_files(dir, &paths)
+    }
+
+    /// Read the repository-wide rules of the git repository whose root is `dir`: its `.git/info/exclude` file and the user's global excludes file.
+    ///
+    /// Returns [None] if `dir` isn't the root of a git repository or there are no such rules.
+    pub fn read_repository(dir: &Path) -> Option<IgnoreFile> {
+        if !dir.join(".git").exists() {
+            return None;
+        }
+        let paths: Vec<_> = [Some(dir.join(GIT_EXCLUDE_FILE)), gitconfig_excludes_path()]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.is_file())
+            .collect();
+        IgnoreFile::from_files(dir, &paths)
+    }
+
+    /// Decide whether `path` is ignored by these rules: `Some(true)` if ignored, `Some(false)` if re-included, [None] if no rule matches.
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        if !path.starts_with(&self.base) {
+            return None;
+        }
+        match self.matcher.matched(path, is_dir) {
+            Match::None => None,
+            Match::Ignore(_) => Some(true),
+            Match::Whitelist(_) => Some(false),
+        }
+    }
+}
+
+/// Check whether `path` is ignored by the ignore files in `stack`, outermost first.
+///
+/// `is_dir` says whether `path` is a directory, for rules that only match directories.
+pub fn ignored(stack: &[IgnoreFile], path: &Path, is_dir: bool) -> bool {
+    stack
+        .iter()
+        .rev()
+        .find_map(|file| file.decide(path, is_dir))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ ignored, IgnoreFile };
+
+    #[test]
+    fn rules_follow_gitignore_syntax() {
+        let root = IgnoreFile::parse(Path::new("/repo"), "# build output\ntarget/\n*.log\n!keep.log\n/docs/*.pdf\nbuild/**/*.o\n");
+        let stack = [root];
+        assert!(ignored(&stack, Path::new("/repo/target"), true));
+        assert!(ignored(&stack, Path::new("/repo/crates/a/target"), true));
+        assert!(!ignored(&stack, Path::new("/repo/target"), false));
+        assert!(ignored(&stack, Path::new("/repo/src/debug.log"), false));
+        assert!(!ignored(&stack, Path::new("/repo/src/keep.log"), false));
+        assert!(ignored(&stack, Path::new("/repo/docs/manual.pdf"), false));
+        assert!(!ignored(&stack, Path::new("/repo/src/docs/manual.pdf"), false));
+        assert!(ignored(&stack, Path::new("/repo/build/a/b/main.o"), false));
+    }
+
+    #[test]
+    fn deeper_files_take_precedence() {
+        let stack = [
+            IgnoreFile::parse(Path::new("/repo"), "*.bin\n"),
+            IgnoreFile::parse(Path::new("/repo/fixtures"), "!*.bin\n"),
+            IgnoreFile::parse(Path::new("/repo/fixtures/large"), "huge.bin\n"),
+        ];
+        assert!(ignored(&stack, Path::new("/repo/blob.bin"), false));
+        assert!(!ignored(&stack, Path::new("/repo/fixtures/blob.bin"), false));
+        assert!(ignored(&stack, Path::new("/repo/fixtures/large/huge.bin"), false));
+        assert!(!ignored(&stack, Path::new("/repo/fixtures/large/small.bin"), false));
+    }
+}
@@ -0,0 +1,84 @@
+//! Contains the DEFLATE decoding used to read zip members and gzip files, built on [flate2].
+//!
+//! The [inflate] function decompresses a raw DEFLATE stream ([RFC 1951](https://www.rfc-editor.org/rfc/rfc1951)) held in memory, refusing to produce more than a given number of bytes so a decompression bomb can't exhaust memory.
+use flate2::{ Decompress, FlushDecompress, Status };
+
+/// Decompress the raw DEFLATE stream `data`.
+///
+/// Returns the decompressed bytes, or an error message if the stream is corrupt or decompresses to more than `limit` bytes.
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    inflate_into(data, &mut out, limit)?;
+    Ok(out)
+}
+
+/// Decompress the raw DEFLATE stream `data` onto the end of `out`, so callers can choose its allocation.
+///
+/// `out` never grows past `limit` more bytes, so an `out` with room for them is never reallocated. The whole stream is decompressed in a single call, which writes straight into `out` rather than through the decoder's own window, so with flate2's default Rust backend no decompressed bytes are left anywhere but `out`.
+///
+/// Returns an error message if the stream is corrupt or decompresses to more than `limit` bytes.
+pub fn inflate_into(data: &[u8], out: &mut Vec<u8>, limit: usize) -> Result<(), String> {
+    let start = out.len();
+    out.resize(start + limit, 0);
+    let mut decompress = Decompress::new(false);
+    let status = decompress.decompress(data, &mut out[start..], FlushDecompress::Finish);
+    let written = decompress.total_out() as usize;
+    out.truncate(start + written);
+    match status {
+        Ok(Status::StreamEnd) => Ok(()),
+        Ok(_) if written == limit => Err(format!("Decompresses to more than {limit} bytes")),
+        Ok(_) => Err("Compressed data ends early".to_string()),
+        Err(e) => Err(format!("Corrupt compressed data: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inflate;
+
+    #[test]
+    fn stored_block_is_copied() {
+        assert_eq!(inflate(&[1, 3, 0, 252, 255, 97, 98, 99], 1024).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn fixed_block_expands_back_references() {
+        let compressed = [203, 72, 205, 201, 201, 87, 200, 64, 39, 1];
+        assert_eq!(inflate(&compressed, 1024).unwrap(), b"hello hello hello hello");
+    }
+
+    #[test]
+    fn dynamic_block_is_decoded() {
+        let compressed = [
+            5, 193, 209, 10, 131, 32, 20, 0, 208, 95, 114, 24, 115, 26, 40, 141, 73, 181, 235, 70, 180, 38, 55, 42, 181,
+            38, 105, 208, 255, 63, 239, 28, 194, 12, 141, 187, 192, 90, 119, 199, 96, 250, 100, 84, 213, 145, 25, 55, 25,
+            167, 149, 33, 192, 200, 127, 46, 223, 147, 219, 24, 182, 141, 45, 22, 12, 220, 125, 108, 126, 10, 174, 61, 60,
+            96, 173, 75, 249, 62, 231, 49, 86, 57, 36, 25, 134, 111, 212, 244, 20, 125, 73, 111, 64, 188, 191, 188, 196,
+            85, 89, 245, 7,
+        ];
+        let expected: Vec<u8> = (0..100u32).map(|i| ((i * i * 7 + i / 3) % 61 + 48) as u8).collect();
+        assert_eq!(inflate(&compressed, 1024).unwrap(), expected);
+    }
+
+    #[test]
+    fn output_beyond_limit_is_refused() {
+        let compressed = [203, 72, 205, 201, 201, 87, 200, 64, 39, 1];
+        assert!(inflate(&compressed, 10).is_err());
+        assert_eq!(inflate(&compressed, 23).unwrap().len(), 23);
+    }
+
+    #[test]
+    fn truncated_stream_is_an_error() {
+        assert!(inflate(&[203, 72, 205], 1024).is_err());
+    }
+
+    #[test]
+    fn malformed_streams_are_errors() {
+        // A back-reference before the start of the output.
+        assert!(inflate(&[3, 2, 0], 1024).is_err());
+        // Block type 3, which doesn't exist.
+        assert!(inflate(&[7], 1024).is_err());
+        // A dynamic block declaring more codes than there are.
+        assert!(inflate(&[5, 255, 255], 1024).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Contains a small table of file signatures ("magic numbers") used to recognise file types from their first bytes.
+//!
+//! The [sniff] function returns the [Magic] matching the start of a buffer, if any, and [mime_type] names the buffer's MIME type.
+//!
+//! Besides file formats, the table knows the signatures of common volumes (filesystems, LVM, MD RAID, and encrypted containers) and of swap and hibernation files, so partitions and paging files can be labelled too.
+//!
+//! The [encrypted_container] function names the encrypted container (LUKS, BitLocker, FileVault) a buffer starts with, if any.
+/// Holds a known file signature.
+///
+/// The `name` field holds a short human-readable name for the file type.
+///
+/// The `offset` and `bytes` fields hold where the signature starts and what it is.
+///
+/// The `mime` field holds the file type's MIME type, `application/octet-stream` for volumes and other raw data.
+///
+/// The `encrypted` field is set for encrypted containers and volumes, whose contents are expected to look random.
+#[derive(Debug)]
+pub struct Magic {
+    pub name: &'static str,
+    pub offset: usize,
+    pub bytes: &'static [u8],
+    pub mime: &'static str,
+    pub encrypted: bool,
+}
+
+/// The number of leading bytes [sniff] needs to recognise every signature in [MAGICS].
+pub const SNIFF_LEN: usize = 4100;
+
+/// Every known signature. More specific signatures come first.
+pub const MAGICS: &[Magic] = &[
+    Magic { name: "ELF", offset: 0, bytes: b"\x7fELF", mime: "application/x-executable", encrypted: false },
+    Magic { name: "PE", offset: 0, bytes: b"MZ", mime: "application/vnd.microsoft.portable-executable", encrypted: false },
+    Magic { name: "Mach-O", offset: 0, bytes: b"\xcf\xfa\xed\xfe", mime: "application/x-mach-binary", encrypted: false },
+    Magic { name: "Mach-O", offset: 0, bytes: b"\xce\xfa\xed\xfe", mime: "application/x-mach-binary", encrypted: false },
+    Magic { name: "Mach-O (universal)", offset: 0, bytes: b"\xca\xfe\xba\xbe", mime: "application/x-mach-binary", encrypted: false },
+    Magic { name: "ZIP", offset: 0, bytes: b"PK\x03\x04", mime: "application/zip", encrypted: false },
+    Magic { name: "gzip", offset: 0, bytes: b"\x1f\x8b", mime: "application/gzip", encrypted: false },
+    Magic { name: "tar", offset: 257, bytes: b"ustar", mime: "application/x-tar", encrypted: false },
+    Magic { name: "7-Zip", offset: 0, bytes: b"7z\xbc\xaf\x27\x1c", mime: "application/x-7z-compressed", encrypted: false },
+    Magic { name: "RAR", offset: 0, bytes: b"Rar!\x1a\x07", mime: "application/vnd.rar", encrypted: false },
+    Magic { name: "bzip2", offset: 0, bytes: b"BZh", mime: "application/x-bzip2", encrypted: false },
+    Magic { name: "xz", offset: 0, bytes: b"\xfd7zXZ\x00", mime: "application/x-xz", encrypted: false },
+    Magic { name: "zstd", offset: 0, bytes: b"\x28\xb5\x2f\xfd", mime: "application/zstd", encrypted: false },
+    Magic { name: "PDF", offset: 0, bytes: b"%PDF-", mime: "application/pdf", encrypted: false },
+    Magic { name: "PNG", offset: 0, bytes: b"\x89PNG\r\n\x1a\n", mime: "image/png", encrypted: false },
+    Magic { name: "JPEG", offset: 0, bytes: b"\xff\xd8\xff", mime: "image/jpeg", encrypted: false },
+    Magic { name: "GIF", offset: 0, bytes: b"GIF8", mime: "image/gif", encrypted: false },
+    Magic { name: "OLE2", offset: 0, bytes: b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", mime: "application/x-ole-storage", encrypted: false },
+    Magic { name: "SQLite", offset: 0, bytes: b"SQLite format 3\x00", mime: "application/vnd.sqlite3", encrypted: false },
+    Magic { name: "LUKS1 container", offset: 0, bytes: b"LUKS\xba\xbe\x00\x01", mime: "application/octet-stream", encrypted: true },
+    Magic { name: "LUKS2 container", offset: 0, bytes: b"LUKS\xba\xbe\x00\x02", mime: "application/octet-stream", encrypted: true },
+    Magic { name: "LUKS container", offset: 0, bytes: b"LUKS\xba\xbe", mime: "application/octet-stream", encrypted: true },
+    Magic { name: "BitLocker volume", offset: 3, bytes: b"-FVE-FS-", mime: "application/octet-stream", encrypted: true },
+    Magic { name: "FileVault (Core Storage) volume", offset: 88, bytes: b"CS\x01\x00", mime: "application/octet-stream", encrypted: true },
+    Magic { name: "Apple encrypted disk image", offset: 0, bytes: b"encrcdsa", mime: "application/octet-stream", encrypted: true },
+    Magic { name: "NTFS", offset: 3, bytes: b"NTFS    ", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "FAT32", offset: 82, bytes: b"FAT32   ", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "XFS", offset: 0, bytes: b"XFSB", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "APFS container", offset: 32, bytes: b"NXSB", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "LVM2 PV", offset: 536, bytes: b"LVM2 001", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "ext2/3/4", offset: 1080, bytes: b"\x53\xef", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Linux swap", offset: 4086, bytes: b"SWAPSPACE2", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Linux suspend image", offset: 4086, bytes: b"S1SUSPEND", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Linux suspend image", offset: 4086, bytes: b"S2SUSPEND", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Linux suspend image (userspace)", offset: 4086, bytes: b"ULSUSPEND", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Windows hibernation file", offset: 0, bytes: b"HIBR", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Windows hibernation file", offset: 0, bytes: b"hibr", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Windows hibernation file (resumed)", offset: 0, bytes: b"WAKE", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Windows hibernation file (resumed)", offset: 0, bytes: b"wake", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Windows hibernation file (restore)", offset: 0, bytes: b"RSTR", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Windows hibernation file (restore)", offset: 0, bytes: b"rstr", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Xpress-compressed hibernation data", offset: 0, bytes: b"\x81\x81xpress", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "Linux MD RAID", offset: 4096, bytes: b"\xfc\x4e\x2b\xa9", mime: "application/octet-stream", encrypted: false },
+    Magic { name: "shell script", offset: 0, bytes: b"#!", mime: "text/x-shellscript", encrypted: false },
+];
+
+/// Find the [Magic] matching the start of `bytes`.
+///
+/// Returns [None] if no known signature matches.
+pub fn sniff(bytes: &[u8]) -> Option<&'static Magic> {
+    MAGICS.iter().find(|magic| bytes.get(magic.offset..magic.offset + magic.bytes.len()) == Some(magic.bytes))
+}
+
+/// Find the encrypted container or volume the start of `bytes` belongs to, e.g. `LUKS2 container`.
+///
+/// Returns [None] if `bytes` doesn't start with a known encrypted container signature.
+pub fn encrypted_container(bytes: &[u8]) -> Option<&'static str> {
+    sniff(bytes)
+        .filter(|magic| magic.encrypted)
+        .map(|magic| magic.name)
+}
+
+/// Name the MIME type of a file starting with `bytes`, e.g. `application/zip`.
+///
+/// Files without a known signature are `text/plain` if `bytes` is UTF-8 without NUL bytes, and `application/octet-stream` otherwise. Empty files are `application/x-empty`.
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    if bytes.is_empty() {
+        return "application/x-empty";
+    }
+    if let Some(magic) = sniff(bytes) {
+        return magic.mime;
+    }
+    // The buffer may end partway through a multi-byte character.
+    let text = match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    match text && !bytes.contains(&0) {
+        true => "text/plain",
+        false => "application/octet-stream",
+    }
+}
@@ -0,0 +1,61 @@
+//! Contains cheap auxiliary byte metrics gathered alongside the entropy histogram.
+//!
+//! The [ByteMetrics] struct tracks the fraction of printable and null bytes and the longest run of zeros, which together with entropy tell text, binary, padded, and encrypted files apart.
+/// Check whether a byte is printable ASCII or common whitespace.
+pub fn is_printable(byte: u8) -> bool {
+    matches!(byte, 0x20..=0x7e | b'\t' | b'\n' | b'\r')
+}
+
+/// Accumulates byte metrics over one or more slices of a file.
+///
+/// Zero runs are tracked across calls to [ByteMetrics::update], so a file can be fed in chunks.
+#[derive(Debug, Clone, Default)]
+pub struct ByteMetrics {
+    total: u64,
+    printable: u64,
+    nulls: u64,
+    zero_run: u64,
+    longest_zero_run: u64,
+}
+
+impl ByteMetrics {
+    /// Add the next slice of the file.
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if is_printable(*byte) {
+                self.printable += 1;
+            }
+            if *byte == 0 {
+                self.nulls += 1;
+                self.zero_run += 1;
+                self.longest_zero_run = self.longest_zero_run.max(self.zero_run);
+            } else {
+                self.zero_run = 0;
+            }
+        }
+        self.total += bytes.len() as u64;
+    }
+
+    /// The fraction of bytes that are printable. An empty file is 0.0.
+    pub fn printable_ratio(&self) -> f64 {
+        self.ratio(self.printable)
+    }
+
+    /// The fraction of bytes that are 0x00. An empty file is 0.0.
+    pub fn null_ratio(&self) -> f64 {
+        self.ratio(self.nulls)
+    }
+
+    /// The length of the longest run of 0x00 bytes.
+    pub fn longest_zero_run(&self) -> u64 {
+        self.longest_zero_run
+    }
+
+    /// Divide `count` by the number of bytes seen, treating an empty file as 0.0.
+    fn ratio(&self, count: u64) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => (count as f64) / (total as f64),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Contains the logic for `--mmap`: measuring files through a memory mapping instead of reading them into buffers.
+//!
+//! The [map_file] function maps a whole file read-only, so its pages are measured where the kernel cached them instead of being copied through a userspace buffer first. That saves a copy per page when scanning very large files, such as extracted disk images.
+//!
+//! Mapping is best effort: a file that isn't a regular file, or can't be mapped, is read in blocks as usual.
+//!
+//! A mapped file that another process truncates while it is being measured raises `SIGBUS`, which ends the scan, where a read would just come up short. That is why mapping is opt-in, and best kept to files nothing else is writing, such as evidence copies.
+use std::fs::File;
+
+#[cfg(unix)]
+use memmap2::Advice;
+use memmap2::Mmap;
+
+/// Map the whole of `file` read-only, telling the kernel it is about to be read from start to end.
+///
+/// Returns [None] if `file` isn't a regular file or can't be mapped, in which case it should be read instead.
+pub fn map_file(file: &File) -> Option<Mmap> {
+    if !file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+        return None;
+    }
+    // SAFETY: the mapping is read-only and only lives while the file is measured. Another process truncating the file meanwhile raises SIGBUS, as documented for --mmap.
+    let map = unsafe { Mmap::map(file) }.ok()?;
+    // A hint: failing to give it changes nothing.
+    #[cfg(unix)]
+    let _ = map.advise(Advice::Sequential);
+    Some(map)
+}
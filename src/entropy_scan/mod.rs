@@ -2,65 +2,256 @@
 //!
 //! The main functions are: [calculate_entropy], [collect_entropies], and [collect_targets].
 //!
-//! [calculate_entropy] takes a [PathBuf] and returns a [FileEntropy].
+//! [calculate_entropy] takes a [PathBuf] and returns a [FileEntropy]. [entropy_of_bytes] does the same for bytes already in memory.
 //!
-//! [collect_entropies] takes a [Vec] of [PathBuf]s and returns a [Vec] of [FileEntropy]s.
+//! [collect_entropies] takes a [Vec] of [PathBuf]s and [ScanOptions] and returns a [Vec] of [FileEntropy]s and the [Unscanned] files, with why they weren't scanned.
 //!
-//! [collect_targets] takes a [PathBuf] and returns a [Vec] of [PathBuf]s.
+//! [for_each_entropy] does the same but hands each [FileEntropy] to a callback instead of buffering them. Files are scanned by a pool of `jobs` worker threads.
+//!
+//! [collect_targets] takes a [PathBuf] and a [TargetFilter] and returns a [Vec] of [PathBuf]s.
+//!
+//! [block_profile] takes a [PathBuf] and a block size and returns the entropy of each block.
+//!
+//! [for_each_block] reads any reader one fixed-size block at a time.
+//!
+//! [new_scan_id] returns a random UUID identifying a single scan.
+use std::collections::{ HashMap, HashSet };
 use std::fs;
-use std::path::PathBuf;
+use std::io::{ self, Read };
+use std::num::NonZeroUsize;
+use std::path::{ Path, PathBuf };
+use std::sync::{ mpsc, Mutex };
+use std::thread;
+use std::time::{ Instant, UNIX_EPOCH };
 
+pub mod aggregate;
+pub mod archive;
+pub mod cache;
+pub mod cdc;
+pub mod anomaly;
+pub mod chunking;
+pub mod digest;
+pub mod features;
+pub mod filters;
+pub mod forensic;
+pub mod git;
+pub mod glob;
+pub mod histogram;
+pub mod ignore;
+pub mod inflate;
+pub mod magic;
+pub mod metrics;
+pub mod mmap;
+pub mod mounts;
+pub mod ntfs;
+pub mod options;
+pub mod packages;
+pub mod partitions;
+pub mod periodicity;
+pub mod placeholder;
+pub mod presets;
+pub mod regions;
+pub mod route;
+pub mod sandbox;
+pub mod sampling;
+pub mod scan_cache;
+pub mod sections;
+pub mod similarity;
+pub mod staging;
 pub mod stats;
 pub mod structs;
-use structs::FileEntropy;
+pub mod target_list;
+pub mod throttle;
+pub mod units;
+pub mod windows;
+pub mod wipe;
+pub mod xor;
+use archive::archive_members;
+use cache::{ drop_cached, prefetch, read_sequentially };
+use filters::{ SymlinkPolicy, TargetFilter };
+use forensic::open_file;
+use histogram::{ ByteHistogram, WordHistogram };
+use ignore::{ ignored, IgnoreFile };
+use magic::encrypted_container;
+use metrics::ByteMetrics;
+use mmap::map_file;
+use options::{ ScanOptions, SymbolWidth };
+use periodicity::{ detect_periodicity, PERIODICITY_WINDOW };
+use placeholder::placeholder_kind;
+use regions::region_map;
+use route::{ route, REGION_BLOCK_SIZE };
+use sections::file_sections;
+use xor::try_xor;
+use structs::{ FileEntropy, Unscanned };
+use throttle::throttle;
+use units::format_size;
+use wipe::Wiped;
+
+/// The smallest file size, in bytes, with a meaningful entropy.
+///
+/// A file shorter than this can't contain every byte value, so its entropy is flagged as low confidence. This is set to 256 bytes.
+pub const LOW_CONFIDENCE_SIZE: u64 = 256;
+
+/// The block size files are streamed in, so memory use doesn't grow with the file.
+///
+/// This is set to 64KB.
+const READ_BLOCK_SIZE: usize = 64 * 1024;
+
+/// How many targets each worker may run ahead of the oldest target whose results haven't been handled yet.
+///
+/// Results are handled in target order, so this bounds how many finished results wait behind a slow file.
+const REORDER_WINDOW_PER_JOB: usize = 4;
+
+/// The number of bytes at the start of a file kept for the signature, periodicity, and XOR analyses.
+///
+/// None of them look further than [PERIODICITY_WINDOW].
+const HEAD_WINDOW: usize = PERIODICITY_WINDOW;
+
+/// Calculate the Shannon entropy of a histogram of symbol counts.
+///
+/// `total` is the number of symbols counted. A histogram with no symbols has an entropy of 0.0.
+fn entropy_from_counts(counts: &[u64], total: u64) -> f64 {
+    let total = total as f64;
+    let mut entropy = 0.0f64;
+    for count in counts.iter() {
+        if *count == 0 {
+            continue;
+        }
+        let p = (*count as f64) / total;
+        entropy -= p * p.log2();
+    }
+    entropy
+}
 
-/// The maximum file size we can scan.
+/// Calculate the Shannon entropy of a byte slice.
 ///
-/// This is set to 2GB.
-const MAX_FILE_SIZE: u64 = 2147483648;
+/// Returns a value between 0.0 and 8.0. An empty slice has an entropy of 0.0.
+fn shannon_entropy(bytes: &[u8]) -> f64 {
+    ByteHistogram::of(bytes).entropy()
+}
 
-/// The chunk size for our files.
+/// Measure everything `reader` yields, naming the result after `path`.
+///
+/// The data is streamed in [READ_BLOCK_SIZE] blocks into a single histogram, so memory use stays constant whatever its size. Only the first [HEAD_WINDOW] bytes are kept for the analyses that need them.
 ///
-/// This is set to 2.5MB.
-const MAX_ENTROPY_CHUNK: usize = 2560000;
+/// Returns a [FileEntropy] without the details only a file on disk has, such as its creation time and sections, or an error message if `reader` fails.
+fn measure<R: Read>(reader: R, path: &Path, options: &ScanOptions) -> Result<FileEntropy, String> {
+    measure_blocks(|f| for_each_block(reader, READ_BLOCK_SIZE, f), path, options)
+}
+
+/// Measure every block `read_blocks` hands to the callback it is given, naming the result after `path`.
+///
+/// This is [measure] for data that is already in blocks, such as a [mapped](mmap::map_file) file, which needn't be copied into a buffer first.
+fn measure_blocks<F>(read_blocks: F, path: &Path, options: &ScanOptions) -> Result<FileEntropy, String>
+    where F: FnOnce(&mut dyn FnMut(&[u8])) -> io::Result<()>
+{
+    let started = Instant::now();
+    let mut histogram = ByteHistogram::default();
+    let mut words = (options.symbol_width == SymbolWidth::Word).then(WordHistogram::default);
+    let mut metrics = ByteMetrics::default();
+    let mut head = Wiped::with_capacity(HEAD_WINDOW, options.no_persist);
+    let read = read_blocks(&mut |block| {
+        histogram.update(block);
+        if let Some(words) = words.as_mut() {
+            words.update(block);
+        }
+        metrics.update(block);
+        let wanted = head.remaining().min(block.len());
+        // Never fails: no more than the remaining capacity is added.
+        let _ = head.extend_from_slice(&block[..wanted]);
+    });
+    if read.is_err() {
+        return Err("Couldn't read file!".to_string());
+    }
+    let entropy = match (options.symbol_width, &words) {
+        (SymbolWidth::Nibble, _) => histogram.nibble_entropy(),
+        (_, Some(words)) => words.entropy(),
+        _ => histogram.entropy(),
+    };
+    Ok(FileEntropy {
+        path: path.to_path_buf(),
+        entropy,
+        size: histogram.total(),
+        low_confidence: histogram.total() < LOW_CONFIDENCE_SIZE,
+        printable_ratio: metrics.printable_ratio(),
+        null_ratio: metrics.null_ratio(),
+        longest_zero_run: metrics.longest_zero_run(),
+        container: encrypted_container(&head).map(str::to_string),
+        package: None,
+        package_status: None,
+        anomaly_score: None,
+        staging: None,
+        mount_point: None,
+        link_target: None,
+        periodicity: options.periodicity.then(|| detect_periodicity(&head)),
+        xor: options.try_xor.then(|| try_xor(&head)).flatten(),
+        sections: None,
+        regions: None,
+        archive_members: None,
+        histogram: options.histogram.then(|| histogram.counts().to_vec()),
+        duration_ms: options.timings.then(|| started.elapsed().as_secs_f64() * 1000.0),
+        created: None,
+    })
+}
+
+/// Calculate the entropy of `bytes` already in memory, naming the result after `path`, which need not exist.
+///
+/// Nothing touches the filesystem, so this works where there is none, such as in a browser. Returns a [FileEntropy] without a creation time or sections.
+pub fn entropy_of_bytes(path: &Path, bytes: &[u8], options: &ScanOptions) -> FileEntropy {
+    measure(bytes, path, options).expect("reading a slice can't fail")
+}
+
+/// Calculate the entropy of everything `reader` yields, such as standard input, naming the result after `path`, which need not exist.
+///
+/// Returns a [FileEntropy] without a creation time or sections, or an error message if `reader` fails.
+pub fn entropy_of_reader<R: Read>(path: &Path, reader: R, options: &ScanOptions) -> Result<FileEntropy, String> {
+    measure(reader, path, options)
+}
 
 /// Calculate a file's entropy.
 ///
-/// Takes a [PathBuf] and returns a [Result] with a [FileEntropy] or an error message.
-fn calculate_entropy(filename: &PathBuf) -> Result<FileEntropy, String> {
+/// The file is streamed by [measure], so memory use stays constant whatever the file size.
+///
+/// Takes a [PathBuf] and [ScanOptions] and returns a [Result] with a [FileEntropy] or an error message.
+fn calculate_entropy(filename: &PathBuf, options: &ScanOptions) -> Result<FileEntropy, String> {
+    let started = Instant::now();
     if let Ok(metadata) = fs::metadata(filename) {
-        // Check max size
-        if metadata.len() > MAX_FILE_SIZE {
-            return Err("File too large".to_string());
-        }
         // Check whether it's a directory
         if metadata.is_dir() {
             return Err("Is a directory".to_string());
         }
 
-        if let Ok(file_bytes) = fs::read(filename) {
-            let mut entropy = 0.0f64;
-            for chunk in file_bytes.chunks(MAX_ENTROPY_CHUNK) {
-                let mut frequency: [u32; 256] = [0; 256];
-                let mut total_bytes = 0;
-
-                for byte in chunk {
-                    frequency[*byte as usize] += 1;
-                    total_bytes += 1;
-                }
-
-                for count in frequency.iter() {
-                    if *count == 0 {
-                        continue;
-                    }
-                    let p = (*count as f64) / (total_bytes as f64);
-                    entropy -= p * p.log2();
-                }
+        if let Ok(file) = open_file(filename) {
+            read_sequentially(&file);
+            let measured = match options.mmap.then(|| map_file(&file)).flatten() {
+                Some(map) => measure_blocks(|f| {
+                    map.chunks(READ_BLOCK_SIZE).for_each(f);
+                    Ok(())
+                }, filename, options),
+                None => measure(&file, filename, options),
+            };
+            if options.no_cache_pollution {
+                drop_cached(&file);
+            }
+            let mut entropy = measured?;
+            let analyzers = route(filename, &metadata, options);
+            // Section analysis is best effort: a truncated or malformed table leaves the whole-file entropy standing on its own.
+            if analyzers.sections {
+                entropy.sections = file_sections(filename).ok().flatten();
             }
-            Ok(FileEntropy {
-                path: filename.to_owned(),
-                entropy,
-            })
+            if analyzers.regions {
+                entropy.regions = region_map(filename, REGION_BLOCK_SIZE).ok().map(|map| map.regions);
+            }
+            entropy.link_target = fs::read_link(filename)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string());
+            entropy.created = metadata
+                .created()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_secs());
+            entropy.duration_ms = options.timings.then(|| started.elapsed().as_secs_f64() * 1000.0);
+            Ok(entropy)
         } else {
             Err("Couldn't read file!".to_string())
         }
@@ -69,36 +260,404 @@ fn calculate_entropy(filename: &PathBuf) -> Result<FileEntropy, String> {
     }
 }
 
+/// Scan a single target, skipping it if it is a special file that isn't to be read, larger than the `max_file_size` in [ScanOptions], or a cloud-sync placeholder that isn't to be hydrated.
+///
+/// With a scan cache in [ScanOptions], a file whose size, modification and change times, and inode haven't changed since it was cached isn't read again, and every file read is cached.
+fn scan_target(target: &PathBuf, options: &ScanOptions) -> Result<FileEntropy, Unscanned> {
+    let metadata = fs::metadata(target).ok();
+    if let Some(metadata) = &metadata {
+        if !options.read_special_files {
+            if let Some(kind) = special_file_kind(metadata) {
+                return Err(Unscanned {
+                    path: target.to_owned(),
+                    reason: format!("{kind} (use --read-special-files to scan it)"),
+                    skipped: true,
+                });
+            }
+        }
+        if let Some(max_file_size) = options.max_file_size {
+            if metadata.len() > max_file_size {
+                return Err(Unscanned {
+                    path: target.to_owned(),
+                    reason: format!("larger than --max-file-size ({})", format_size(metadata.len())),
+                    skipped: true,
+                });
+            }
+        }
+        if !options.hydrate_placeholders {
+            if let Some(kind) = placeholder_kind(target, metadata) {
+                return Err(Unscanned {
+                    path: target.to_owned(),
+                    reason: format!("{kind}, not downloaded (use --hydrate-placeholders to scan it)"),
+                    skipped: true,
+                });
+            }
+        }
+        if let Some(cached) = options.cache.as_ref().and_then(|cache| cache.get(target, metadata, options)) {
+            return Ok(cached);
+        }
+    }
+    let entropy = calculate_entropy(target, options).map_err(|reason| Unscanned {
+        path: target.to_owned(),
+        reason,
+        skipped: false,
+    })?;
+    if let (Some(cache), Some(metadata)) = (&options.cache, &metadata) {
+        cache.insert(target, metadata, options, &entropy);
+    }
+    Ok(entropy)
+}
+
+/// Ask the kernel to start reading `target`, queued to be scanned soon, unless [scan_target] would skip it.
+///
+/// Special files are never opened early, as opening a FIFO or device can block, and neither are placeholders, as opening one can download it. Cached files aren't read at all.
+fn prefetch_target(target: Option<&PathBuf>, options: &ScanOptions) {
+    let Some(target) = target else {
+        return;
+    };
+    let Ok(metadata) = fs::metadata(target) else {
+        return;
+    };
+    let skipped = !metadata.is_file() ||
+        options.max_file_size.is_some_and(|max_file_size| metadata.len() > max_file_size) ||
+        (!options.hydrate_placeholders && placeholder_kind(target, &metadata).is_some()) ||
+        options.cache.as_ref().is_some_and(|cache| cache.get(target, &metadata, options).is_some());
+    if !skipped {
+        prefetch(target);
+    }
+}
+
+/// Scan a single target with [scan_target], followed by each of its members when it is an archive and archives are to be opened, or the `deep` option routes it there.
+fn scan_with_members(target: &PathBuf, options: &ScanOptions) -> Vec<Result<FileEntropy, Unscanned>> {
+    let result = scan_target(target, options);
+    let expand = (options.container_depth > 0 || options.deep) && result.is_ok();
+    let mut results = vec![result];
+    if expand {
+        let members = match options.container_depth {
+            // --deep opens archives, but not the archives inside them, unless asked to.
+            0 => archive_members(target, &ScanOptions { container_depth: 1, ..options.clone() }),
+            _ => archive_members(target, options),
+        };
+        results.extend(members.unwrap_or_default());
+    }
+    results
+}
+
 /// Collect entropies from a [Vec] of [PathBuf]s.
 ///
-/// Takes a [Vec] of [PathBuf]s and returns a [Vec] of [FileEntropy]s.
-pub fn collect_entropies(targets: &Vec<PathBuf>) -> Vec<FileEntropy> {
+/// Takes a [Vec] of [PathBuf]s and [ScanOptions] and returns a [Vec] of [FileEntropy]s and the targets that couldn't be, or weren't, scanned.
+pub fn collect_entropies(targets: &[PathBuf], options: &ScanOptions) -> (Vec<FileEntropy>, Vec<Unscanned>) {
     let mut entropies = Vec::with_capacity(targets.len());
+    let unscanned = for_each_entropy(targets, options, |entropy| entropies.push(entropy));
+    (entropies, unscanned)
+}
+
+/// The number of worker threads to scan `targets` files with, given the requested `jobs`.
+///
+/// 0 jobs means one per CPU. There are never more workers than files, and only one where threads can't be spawned, such as WebAssembly without the atomics feature.
+fn worker_count(jobs: usize, targets: usize) -> usize {
+    if cfg!(all(target_family = "wasm", not(target_feature = "atomics"))) {
+        return 1;
+    }
+    let jobs = match jobs {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        jobs => jobs,
+    };
+    jobs.min(targets)
+}
+
+/// Calculate entropies for a slice of [PathBuf]s, handing each [FileEntropy] to `f` as soon as it is ready.
+///
+/// Unlike [collect_entropies], results aren't collected, so callers can stream them. With more than one job, files are scanned in parallel but `f` still sees them in the order of `targets`, on the calling thread. Workers run at most [REORDER_WINDOW_PER_JOB] targets per job ahead of the oldest one not yet handed to `f`, so only that many results are ever held back.
+///
+/// Returns the targets that couldn't be, or weren't, scanned, in the order of `targets`.
+pub fn for_each_entropy<F: FnMut(FileEntropy)>(
+    targets: &[PathBuf],
+    options: &ScanOptions,
+    mut f: F
+) -> Vec<Unscanned> {
+    let mut unscanned = Vec::new();
+    let mut handle = |result: Result<FileEntropy, Unscanned>| {
+        match result {
+            Ok(entropy) => f(entropy),
+            Err(target) => unscanned.push(target),
+        }
+    };
 
-    for target in targets {
-        if let Ok(entropy) = calculate_entropy(target) {
-            entropies.push(entropy);
+    let jobs = worker_count(options.jobs, targets.len());
+    if jobs <= 1 {
+        for (index, target) in targets.iter().enumerate() {
+            throttle(options.max_load, options.pause_on_battery);
+            prefetch_target(targets.get(index + 1), options);
+            scan_with_members(target, options).into_iter().for_each(&mut handle);
         }
+        return unscanned;
     }
-    entropies
+
+    // Targets are handed out no further ahead of the oldest unhandled one than the window, so a slow file holds back a bounded number of results.
+    let window = jobs * REORDER_WINDOW_PER_JOB;
+    let (work, queue) = mpsc::channel::<usize>();
+    let queue = Mutex::new(queue);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let queue = &queue;
+            scope.spawn(move || {
+                // The queue closes once every target is handed out, or if handling results fails.
+                let next = || queue.lock().ok()?.recv().ok();
+                while let Some(index) = next() {
+                    let target = &targets[index];
+                    throttle(options.max_load, options.pause_on_battery);
+                    // The other workers are busy with the targets in between.
+                    prefetch_target(targets.get(index + jobs), options);
+                    if sender.send((index, scan_with_members(target, options))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut work = Some(work);
+        let mut dispatched = 0;
+        let mut dispatch = |until: usize| {
+            while dispatched < until.min(targets.len()) {
+                if let Some(work) = &work {
+                    let _ = work.send(dispatched);
+                }
+                dispatched += 1;
+            }
+            if dispatched == targets.len() {
+                work = None;
+            }
+        };
+        dispatch(window);
+
+        // Results arrive in whatever order the workers finish, so hold them until every earlier target is handled.
+        let mut pending = HashMap::new();
+        let mut expected = 0;
+        for (index, results) in receiver {
+            pending.insert(index, results);
+            while let Some(results) = pending.remove(&expected) {
+                results.into_iter().for_each(&mut handle);
+                expected += 1;
+            }
+            dispatch(expected + window);
+        }
+    });
+    unscanned
 }
 
 /// Collect all files in a directory.
 ///
-/// Takes a [PathBuf] and returns a [Vec] of [PathBuf]s.
-pub fn collect_targets(parent_path: PathBuf) -> Vec<PathBuf> {
-    if parent_path.is_file() {
-        return vec![parent_path];
+/// Takes a [PathBuf] and a [TargetFilter] and returns a [Vec] of the [PathBuf]s the filter accepts. If the filter honors ignore files, paths they ignore are left out too, but a file named directly is always kept.
+///
+/// The walk is iterative, so deep trees can't overflow the stack, and stops at the filter's `max_depth`. Symbolic links are treated as the filter's [SymlinkPolicy] says. When links are followed, every directory and file is visited once, so link loops end and linked trees aren't counted twice. Directories that can't be read are skipped.
+pub fn collect_targets(parent_path: PathBuf, filter: &TargetFilter) -> Vec<PathBuf> {
+    // Special files given directly, such as a FIFO, are targets too, so they are reported rather than silently dropped.
+    if fs::metadata(&parent_path).is_ok_and(|metadata| !metadata.is_dir()) {
+        return match filter.accepts(&parent_path) {
+            true => vec![parent_path],
+            false => Vec::new(),
+        };
+    }
+    let follow = filter.symlinks == SymlinkPolicy::Follow;
+    let device = filter.one_file_system
+        .then(|| file_id(&parent_path))
+        .flatten()
+        .and_then(|id| device_of(&id));
+    let mut visited: HashSet<FileId> = HashSet::new();
+    if follow {
+        visited.extend(file_id(&parent_path));
     }
     let mut targets = Vec::new();
-    let dir = fs::read_dir(parent_path).unwrap();
-    for entry in dir {
-        let path = entry.unwrap().path();
-        if path.is_dir() {
-            targets.extend(collect_targets(path));
-        } else {
+    // Each level holds the unvisited entries of a directory being walked, and how many ignore files it added.
+    let mut levels: Vec<(std::vec::IntoIter<PathBuf>, usize)> = Vec::new();
+    let mut ignores: Vec<IgnoreFile> = Vec::new();
+    let mut next_dir = Some(parent_path);
+    loop {
+        if let Some(dir) = next_dir.take() {
+            let before = ignores.len();
+            if filter.ignore_files {
+                // A repository's own rules go below its .gitignore files, which take precedence over them.
+                ignores.extend(IgnoreFile::read_repository(&dir));
+                ignores.extend(IgnoreFile::read(&dir));
+            }
+            let pushed = ignores.len() - before;
+            // Entries are read up front, so the walk holds no more than one directory open at a time.
+            let entries: Vec<PathBuf> = match fs::read_dir(&dir) {
+                Ok(entries) =>
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .collect(),
+                Err(_) => Vec::new(),
+            };
+            levels.push((entries.into_iter(), pushed));
+        }
+        let depth = levels.len();
+        let Some((entries, pushed)) = levels.last_mut() else {
+            break;
+        };
+        let Some(path) = entries.next() else {
+            ignores.truncate(ignores.len() - *pushed);
+            levels.pop();
+            continue;
+        };
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let link = metadata.file_type().is_symlink();
+        if link && filter.symlinks == SymlinkPolicy::Skip {
+            continue;
+        }
+        let is_dir = metadata.is_dir() || (link && path.is_dir());
+        if ignored(&ignores, &path, is_dir) {
+            continue;
+        }
+        // Broken links have no identity and are kept, so they are reported when scanned.
+        if follow && file_id(&path).is_some_and(|id| !visited.insert(id)) {
+            continue;
+        }
+        if is_dir {
+            let other_device = device.is_some_and(|device| {
+                file_id(&path)
+                    .and_then(|id| device_of(&id))
+                    .is_some_and(|other| other != device)
+            });
+            if (!link || follow) && !other_device && filter.within_depth(depth + 1) && filter.descends_into(&path) {
+                next_dir = Some(path);
+            }
+        } else if filter.within_depth(depth) && filter.accepts(&path) {
             targets.push(path);
         }
     }
     targets
 }
+
+/// Identifies a file or directory whatever path it is reached by: its device and inode numbers.
+#[cfg(unix)]
+type FileId = (u64, u64);
+
+/// Identifies a file or directory whatever path it is reached by: its canonical path.
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+/// Find the identity of the file or directory `path` resolves to, or [None] if it can't be resolved.
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// Find the identity of the file or directory `path` resolves to, or [None] if it can't be resolved.
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> Option<FileId> {
+    path.canonicalize().ok()
+}
+
+/// Name the kind of special file `metadata` belongs to, e.g. `FIFO`, or [None] for regular files and directories.
+#[cfg(unix)]
+fn special_file_kind(metadata: &fs::Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    match file_type {
+        _ if file_type.is_char_device() => Some("character device"),
+        _ if file_type.is_block_device() => Some("block device"),
+        _ if file_type.is_fifo() => Some("FIFO"),
+        _ if file_type.is_socket() => Some("socket"),
+        _ => None,
+    }
+}
+
+/// Name the kind of special file `metadata` belongs to. Only Unix has special files, so this is always [None].
+#[cfg(not(unix))]
+fn special_file_kind(_metadata: &fs::Metadata) -> Option<&'static str> {
+    None
+}
+
+/// Find the device a file with identity `id` is on.
+#[cfg(unix)]
+fn device_of(id: &FileId) -> Option<u64> {
+    Some(id.0)
+}
+
+/// Find the device a file with identity `id` is on. Device IDs aren't available here, so this is always [None].
+#[cfg(not(unix))]
+fn device_of(_id: &FileId) -> Option<u64> {
+    None
+}
+
+/// Calculate the entropy of each fixed-size block of a file.
+///
+/// Takes a [PathBuf] and a block size in bytes and returns a [Result] with a [Vec] of entropies, one per block, or an error message.
+pub fn block_profile(filename: &PathBuf, block_size: usize) -> Result<Vec<f64>, String> {
+    if block_size == 0 {
+        return Err("Block size must be greater than zero".to_string());
+    }
+    if let Ok(metadata) = fs::metadata(filename) {
+        if metadata.is_dir() {
+            return Err("Is a directory".to_string());
+        }
+
+        if let Ok(file) = open_file(filename) {
+            let mut profile = Vec::new();
+            match for_each_block(file, block_size, |block| profile.push(shannon_entropy(block))) {
+                Ok(()) => Ok(profile),
+                Err(_) => Err("Couldn't read file!".to_string()),
+            }
+        } else {
+            Err("Couldn't read file!".to_string())
+        }
+    } else {
+        Err("Couldn't read file metadata!".to_string())
+    }
+}
+
+/// Read `reader` to the end in `block_size` blocks, handing each block to `f`.
+///
+/// Only the last block can be shorter than `block_size`. Nothing is buffered beyond a single block, so files of any size can be read. The block is [wiped](wipe::Wiped) before returning, so no file contents are left behind in freed memory.
+pub fn for_each_block<R: Read, F: FnMut(&[u8])>(mut reader: R, block_size: usize, mut f: F) -> io::Result<()> {
+    let mut block = Wiped::zeroed(block_size, true);
+    loop {
+        let mut filled = 0;
+        while filled < block_size {
+            match reader.read(&mut block[filled..])? {
+                0 => {
+                    break;
+                }
+                read => {
+                    filled += read;
+                }
+            }
+        }
+        if filled == 0 {
+            return Ok(());
+        }
+        f(&block[..filled]);
+        if filled < block_size {
+            return Ok(());
+        }
+    }
+}
+
+/// Generate a random (version 4) UUID identifying a single scan.
+///
+/// The ID is included in every report so results from overlapping scans can be correlated downstream. Returns the ID, or an error message if the operating system can't supply random bytes.
+pub fn new_scan_id() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| format!("Couldn't generate a scan ID: {e}"))?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok(format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]))
+}
@@ -0,0 +1,194 @@
+//! Contains the logic for discovering the mounted filesystems of a host, so a full sweep doesn't need every mount listed by hand.
+//!
+//! The [mounts] function lists the mounted filesystems, and [scan_roots] picks the local ones worth scanning: pseudo filesystems such as `/proc`, anything mounted beneath them, and, unless asked for, network filesystems are left out.
+//!
+//! The [mount_point_of] function finds which of the scanned mount points a file belongs to.
+use std::path::{ Path, PathBuf };
+
+/// Kernel and virtual filesystems that hold no files worth scanning.
+pub const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// Filesystems served over the network, including their FUSE clients, e.g. `fuse.sshfs`.
+pub const NETWORK_FILESYSTEMS: &[&str] = &[
+    "9p",
+    "afs",
+    "ceph",
+    "cifs",
+    "davfs",
+    "glusterfs",
+    "lustre",
+    "ncpfs",
+    "nfs",
+    "nfs4",
+    "rclone",
+    "smb3",
+    "smbfs",
+    "sshfs",
+];
+
+/// The Linux list of the calling process's mounts.
+#[cfg(target_os = "linux")]
+const MOUNTS_FILE: &str = "/proc/self/mounts";
+
+/// Holds a mounted filesystem.
+///
+/// The `point` field holds the directory it is mounted on.
+///
+/// The `fs_type` field holds its filesystem type, e.g. `ext4` or `nfs4`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub point: PathBuf,
+    pub fs_type: String,
+}
+
+impl Mount {
+    /// Check whether the filesystem is a kernel or virtual one, see [PSEUDO_FILESYSTEMS].
+    pub fn is_pseudo(&self) -> bool {
+        PSEUDO_FILESYSTEMS.contains(&self.fs_type.as_str())
+    }
+
+    /// Check whether the filesystem is served over the network, see [NETWORK_FILESYSTEMS].
+    pub fn is_network(&self) -> bool {
+        let fs_type = self.fs_type.strip_prefix("fuse.").unwrap_or(&self.fs_type);
+        NETWORK_FILESYSTEMS.contains(&fs_type)
+    }
+}
+
+/// Undo the octal escapes (`\040` for a space) the kernel writes in mount points.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4)
+            .filter(|digits| bytes[i] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit)))
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+        match escape {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).to_string()
+}
+
+/// Parse a mount table in the `/proc/self/mounts` format: device, mount point, type, and options on each line.
+///
+/// A directory mounted over more than once is listed once, with the filesystem mounted last, which is the one visible.
+pub fn parse_mounts(table: &str) -> Vec<Mount> {
+    let mut mounts: Vec<Mount> = Vec::new();
+    for line in table.lines() {
+        let mut fields = line.split_whitespace().skip(1);
+        let (Some(point), Some(fs_type)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let mount = Mount { point: PathBuf::from(unescape(point)), fs_type: fs_type.to_string() };
+        mounts.retain(|other| other.point != mount.point);
+        mounts.push(mount);
+    }
+    mounts
+}
+
+/// List the filesystems mounted on this host.
+///
+/// Returns the [Mount]s, or an error message if the mount table can't be read.
+#[cfg(target_os = "linux")]
+pub fn mounts() -> Result<Vec<Mount>, String> {
+    use std::fs;
+
+    let table = fs::read_to_string(MOUNTS_FILE).map_err(|e| format!("Couldn't read {MOUNTS_FILE}: {e}"))?;
+    Ok(parse_mounts(&table))
+}
+
+/// List the filesystems mounted on this host.
+///
+/// Only Linux's mount table can be read, so this always returns an error message.
+#[cfg(not(target_os = "linux"))]
+pub fn mounts() -> Result<Vec<Mount>, String> {
+    Err("Listing mounted filesystems is only supported on Linux".to_string())
+}
+
+/// Pick the mount points of `mounts` worth scanning.
+///
+/// Pseudo filesystems and anything mounted beneath one, such as a `tmpfs` on `/sys/fs/cgroup`, are left out, as are network filesystems unless `network` is set.
+pub fn scan_roots(mounts: &[Mount], network: bool) -> Vec<PathBuf> {
+    let pseudo: Vec<&Path> = mounts
+        .iter()
+        .filter(|mount| mount.is_pseudo())
+        .map(|mount| mount.point.as_path())
+        .collect();
+    mounts
+        .iter()
+        .filter(|mount| !mount.is_pseudo() && (network || !mount.is_network()))
+        .filter(|mount| !pseudo.iter().any(|point| mount.point.starts_with(point)))
+        .map(|mount| mount.point.clone())
+        .collect()
+}
+
+/// Find the mount point in `points` that `path` belongs to: the longest one it lies beneath.
+pub fn mount_point_of<'a>(path: &Path, points: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    points
+        .iter()
+        .filter(|point| path.starts_with(point))
+        .max_by_key(|point| point.components().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{ Path, PathBuf };
+
+    use super::{ mount_point_of, parse_mounts, scan_roots };
+
+    const TABLE: &str =
+        "proc /proc proc rw 0 0
+sysfs /sys sysfs rw 0 0
+tmpfs /sys/fs/cgroup tmpfs rw 0 0
+/dev/sda1 / ext4 rw 0 0
+/dev/sdb1 /mnt/USB\\040Drive vfat rw 0 0
+server:/export /srv/share nfs4 rw 0 0
+tmpfs /dev/shm tmpfs rw 0 0
+";
+
+    #[test]
+    fn local_filesystems_are_scanned() {
+        let mounts = parse_mounts(TABLE);
+        let roots = scan_roots(&mounts, false);
+        assert_eq!(roots, ["/", "/mnt/USB Drive", "/dev/shm"].map(PathBuf::from));
+        assert!(scan_roots(&mounts, true).contains(&PathBuf::from("/srv/share")));
+    }
+
+    #[test]
+    fn files_belong_to_the_deepest_mount() {
+        let points = ["/", "/dev/shm"].map(PathBuf::from);
+        assert_eq!(mount_point_of(Path::new("/dev/shm/payload"), &points), Some(&points[1]));
+        assert_eq!(mount_point_of(Path::new("/etc/passwd"), &points), Some(&points[0]));
+    }
+}
@@ -0,0 +1,492 @@
+//! Contains the logic for reading the master file table (`$MFT`) of a raw NTFS volume.
+//!
+//! The [mft_data] function walks every file record and measures the two places small payloads hide from file-level scans: data stored inside the record itself (resident data, for files of up to about 700 bytes), and file slack, the unused tail of a file's last cluster, where wipers and droppers leave residue.
+//!
+//! Named data streams (alternate data streams) are included, reported as `name:stream`. Compressed files, and data described only in attribute list extensions, are left out.
+//!
+//! The [unallocated] function reads the volume's cluster bitmap (`$Bitmap`) and maps the entropy of the clusters no file owns, as a first pass before carving: encrypted remnants stand out near 8, wiped space at 0.
+use std::fs::File;
+use std::io::{ self, Read, Seek, SeekFrom };
+use std::path::Path;
+
+use super::forensic::open_file;
+use super::shannon_entropy;
+use super::structs::MftData;
+
+/// The OEM ID of an NTFS boot sector.
+const NTFS_OEM_ID: &[u8; 8] = b"NTFS    ";
+
+/// The attribute type holding a file's name.
+const ATTRIBUTE_FILE_NAME: u32 = 0x30;
+
+/// The attribute type holding a file's data.
+const ATTRIBUTE_DATA: u32 = 0x80;
+
+/// The attribute type marking the end of a record's attributes.
+const ATTRIBUTE_END: u32 = 0xffff_ffff;
+
+/// The attribute flags of compressed data, whose clusters don't line up with the file's bytes.
+const ATTRIBUTE_COMPRESSED: u16 = 0x0001;
+
+/// The `$FILE_NAME` namespace of 8.3 short names, used only when a file has no other name.
+const NAMESPACE_DOS: u8 = 2;
+
+/// The stride of the update sequence (fixup) values in a record, whatever the sector size.
+const FIXUP_STRIDE: usize = 512;
+
+/// The largest file record size accepted, so a corrupt boot sector can't exhaust memory.
+const MAX_RECORD_SIZE: u64 = 64 * 1024;
+
+/// The largest cluster size NTFS supports, 2MB.
+const MAX_CLUSTER_SIZE: u64 = 2 * 1024 * 1024;
+
+/// The file record number of `$Bitmap`, which marks the clusters in use.
+const BITMAP_RECORD: u64 = 6;
+
+/// The most unallocated bytes measured as one entry of the map, 1MB.
+const UNALLOCATED_CHUNK: u64 = 1024 * 1024;
+
+/// The geometry of an NTFS volume, read from its boot sector.
+struct Volume {
+    cluster_size: u64,
+    cluster_count: u64,
+    record_size: u64,
+    mft_cluster: u64,
+}
+
+/// A run of clusters of a non-resident attribute. `cluster` is [None] for a sparse run.
+struct Run {
+    vcn: u64,
+    cluster: Option<u64>,
+    length: u64,
+}
+
+/// Read a little-endian `u16` at `offset` of `bytes`.
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+/// Read a little-endian `u32` at `offset` of `bytes`.
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Read a little-endian `u64` at `offset` of `bytes`.
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Read the volume geometry from an NTFS boot sector.
+///
+/// Returns [None] if `sector` is not a plausible NTFS boot sector.
+fn parse_boot_sector(sector: &[u8]) -> Option<Volume> {
+    if sector.len() < 512 || &sector[3..11] != NTFS_OEM_ID {
+        return None;
+    }
+    let sector_size = u16_at(sector, 0x0b) as u64;
+    // Values above 0x80 give the number of sectors per cluster as a negative power of two.
+    let cluster_size = match sector[0x0d] {
+        0 => {
+            return None;
+        }
+        sectors @ 1..=0x80 => sector_size * (sectors as u64),
+        shift => sector_size.checked_shl(256 - (shift as u32))?,
+    };
+    // Positive values count clusters per record, negative ones give the record size as a power of two.
+    let record_size = match sector[0x40] as i8 {
+        clusters @ 1.. => cluster_size * (clusters as u64),
+        shift => 1u64.checked_shl(-(shift as i32) as u32)?,
+    };
+    let plausible =
+        sector_size.is_power_of_two() &&
+        sector_size >= 256 &&
+        (cluster_size as usize) >= FIXUP_STRIDE &&
+        cluster_size <= MAX_CLUSTER_SIZE &&
+        (FIXUP_STRIDE as u64..=MAX_RECORD_SIZE).contains(&record_size);
+    plausible.then(|| Volume {
+        cluster_size,
+        cluster_count: u64_at(sector, 0x28).saturating_mul(sector_size) / cluster_size,
+        record_size,
+        mft_cluster: u64_at(sector, 0x30),
+    })
+}
+
+/// Restore the last two bytes of every 512-byte stride of a record from its update sequence array.
+///
+/// Returns `false` if the record is torn or corrupt, i.e. a stride doesn't end in the update sequence number.
+fn apply_fixups(record: &mut [u8]) -> bool {
+    let offset = u16_at(record, 4) as usize;
+    let count = u16_at(record, 6) as usize;
+    if count == 0 || offset + count * 2 > record.len() || (count - 1) * FIXUP_STRIDE > record.len() {
+        return false;
+    }
+    let number = [record[offset], record[offset + 1]];
+    for i in 1..count {
+        let end = i * FIXUP_STRIDE;
+        if record[end - 2..end] != number {
+            return false;
+        }
+        record[end - 2] = record[offset + i * 2];
+        record[end - 1] = record[offset + i * 2 + 1];
+    }
+    true
+}
+
+/// Decode the run list (mapping pairs) of a non-resident attribute starting at `start_vcn`.
+///
+/// Decoding stops at the end of the list or at the first malformed run.
+fn data_runs(bytes: &[u8], start_vcn: u64) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let mut at = 0usize;
+    let mut vcn = start_vcn;
+    let mut cluster = 0i64;
+    while let Some(&header) = bytes.get(at) {
+        let length_size = (header & 0x0f) as usize;
+        let offset_size = (header >> 4) as usize;
+        if header == 0 || length_size == 0 || length_size > 8 || offset_size > 8 {
+            break;
+        }
+        let Some(fields) = bytes.get(at + 1..at + 1 + length_size + offset_size) else {
+            break;
+        };
+        let mut length = [0u8; 8];
+        length[..length_size].copy_from_slice(&fields[..length_size]);
+        let length = u64::from_le_bytes(length);
+        // Cluster offsets are signed and relative to the previous run. A run without one is sparse.
+        let delta = match offset_size {
+            0 => None,
+            size => {
+                let fill = match fields[length_size + size - 1] & 0x80 != 0 {
+                    true => 0xff,
+                    false => 0x00,
+                };
+                let mut delta = [fill; 8];
+                delta[..size].copy_from_slice(&fields[length_size..]);
+                Some(i64::from_le_bytes(delta))
+            }
+        };
+        if let Some(delta) = delta {
+            cluster = cluster.wrapping_add(delta);
+        }
+        runs.push(Run {
+            vcn,
+            cluster: delta.and_then(|_| u64::try_from(cluster).ok()),
+            length,
+        });
+        vcn = vcn.saturating_add(length);
+        at += 1 + length_size + offset_size;
+    }
+    runs
+}
+
+/// Find the volume cluster holding virtual cluster `vcn`, or [None] if it is sparse or not mapped.
+fn cluster_of(runs: &[Run], vcn: u64) -> Option<u64> {
+    runs.iter()
+        .find(|run| vcn >= run.vcn && vcn - run.vcn < run.length)
+        .and_then(|run| Some(run.cluster? + (vcn - run.vcn)))
+}
+
+/// Decode `length` UTF-16 code units at `offset` of `bytes`.
+fn utf16_at(bytes: &[u8], offset: usize, length: usize) -> Option<String> {
+    let units: Vec<u16> = bytes
+        .get(offset..offset + length * 2)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+/// A `$DATA` attribute found in a file record, before its bytes are read.
+enum Data {
+    /// Resident data at `offset` of the record.
+    Resident {
+        stream: String,
+        offset: usize,
+        length: usize,
+    },
+    /// Non-resident data of `size` bytes stored in `runs`.
+    NonResident {
+        stream: String,
+        size: u64,
+        runs: Vec<Run>,
+    },
+}
+
+/// The parts of a file record that matter here: its best name and its data attributes.
+struct Record {
+    name: Option<String>,
+    data: Vec<Data>,
+}
+
+/// Walk the attributes of an in-use, fixed-up file `record`.
+fn parse_record(record: &[u8]) -> Record {
+    let mut parsed = Record { name: None, data: Vec::new() };
+    // Whether the name found so far is only an 8.3 short name.
+    let mut short_name = true;
+    let mut at = u16_at(record, 0x14) as usize;
+    while at + 16 <= record.len() {
+        let kind = u32_at(record, at);
+        let length = u32_at(record, at + 4) as usize;
+        if kind == ATTRIBUTE_END || length < 16 || at + length > record.len() {
+            break;
+        }
+        let attribute = &record[at..at + length];
+        let non_resident = attribute[8] != 0;
+        let stream = utf16_at(attribute, u16_at(attribute, 0x0a) as usize, attribute[9] as usize).unwrap_or_default();
+        match (kind, non_resident) {
+            (ATTRIBUTE_FILE_NAME, false) if attribute.len() >= 0x18 => {
+                let value = u16_at(attribute, 0x14) as usize;
+                if let Some(header) = attribute.get(value..value + 0x42) {
+                    let namespace = header[0x41];
+                    if parsed.name.is_none() || (short_name && namespace != NAMESPACE_DOS) {
+                        parsed.name = utf16_at(attribute, value + 0x42, header[0x40] as usize);
+                        short_name = namespace == NAMESPACE_DOS;
+                    }
+                }
+            }
+            (ATTRIBUTE_DATA, false) if attribute.len() >= 0x18 => {
+                let length = u32_at(attribute, 0x10) as usize;
+                let offset = u16_at(attribute, 0x14) as usize;
+                if length > 0 && offset + length <= attribute.len() {
+                    parsed.data.push(Data::Resident { stream, offset: at + offset, length });
+                }
+            }
+            (ATTRIBUTE_DATA, true) if attribute.len() >= 0x40 => {
+                let compressed = u16_at(attribute, 0x0c) & ATTRIBUTE_COMPRESSED != 0;
+                let runs_at = u16_at(attribute, 0x20) as usize;
+                if !compressed && runs_at < attribute.len() {
+                    parsed.data.push(Data::NonResident {
+                        stream,
+                        size: u64_at(attribute, 0x30),
+                        runs: data_runs(&attribute[runs_at..], u64_at(attribute, 0x10)),
+                    });
+                }
+            }
+            _ => {}
+        }
+        at += length;
+    }
+    parsed
+}
+
+/// Name a data stream of the file `name`: the file itself for the unnamed stream, `name:stream` otherwise.
+fn stream_name(name: &str, stream: &str) -> String {
+    match stream.is_empty() {
+        true => name.to_string(),
+        false => format!("{name}:{stream}"),
+    }
+}
+
+/// Read up to `len` bytes at `offset`. Fewer bytes are returned at the end of the file.
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(len);
+    file.seek(SeekFrom::Start(offset))?;
+    file.by_ref()
+        .take(len as u64)
+        .read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// An NTFS volume opened for reading, with the size and data runs of its `$MFT`.
+struct Mft {
+    file: File,
+    volume: Volume,
+    size: u64,
+    runs: Vec<Run>,
+}
+
+/// Open the NTFS volume starting `offset` bytes into the image at `path` and find its `$MFT`.
+///
+/// Returns the opened [Mft], or an error message if the image can't be read or holds no NTFS volume at `offset`.
+fn open_mft(path: &Path, offset: u64) -> Result<Mft, String> {
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let mut file = open_file(path).map_err(error)?;
+    let boot = read_at(&mut file, offset, 512).map_err(error)?;
+    let volume = parse_boot_sector(&boot).ok_or_else(|| format!("No NTFS boot sector found at offset {offset}"))?;
+    let record_size = volume.record_size as usize;
+
+    // The $MFT describes itself in record 0.
+    let mut first = read_at(&mut file, offset + volume.mft_cluster * volume.cluster_size, record_size).map_err(error)?;
+    if first.len() < record_size || &first[..4] != b"FILE" || !apply_fixups(&mut first) {
+        return Err("The $MFT's own record is missing or corrupt".to_string());
+    }
+    let (size, runs) = parse_record(&first)
+        .data
+        .into_iter()
+        .find_map(|data| match data {
+            Data::NonResident { stream, size, runs } if stream.is_empty() => Some((size, runs)),
+            _ => None,
+        })
+        .ok_or("The $MFT's own record has no data runs")?;
+    Ok(Mft { file, volume, size, runs })
+}
+
+/// Read `len` bytes from `start` of the non-resident data stored in `runs`, on a volume starting at `offset`.
+///
+/// Sparse and unmapped clusters read as zeros. Returns an error if a mapped cluster lies past the end of the image.
+fn read_runs(file: &mut File, offset: u64, cluster_size: u64, runs: &[Run], start: u64, len: u64) -> io::Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(len as usize);
+    let end = start + len;
+    let mut at = start;
+    while at < end {
+        let within = at % cluster_size;
+        let take = (cluster_size - within).min(end - at);
+        match cluster_of(runs, at / cluster_size) {
+            Some(cluster) => {
+                let bytes = read_at(file, offset + cluster * cluster_size + within, take as usize)?;
+                if (bytes.len() as u64) < take {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "data runs past the end of the image"));
+                }
+                data.extend_from_slice(&bytes);
+            }
+            None => data.resize(data.len() + (take as usize), 0),
+        }
+        at += take;
+    }
+    Ok(data)
+}
+
+/// Map the entropy of the unallocated clusters of the NTFS volume starting `offset` bytes into the image at `path`.
+///
+/// Each run of clusters `$Bitmap` marks free is measured in chunks of up to 1MB, so large free areas still show where remnants lie.
+///
+/// Returns a [Vec] of [MftData] in volume order, or an error message if the image can't be read, holds no NTFS volume at `offset`, or its `$Bitmap` is missing or corrupt.
+pub fn unallocated(path: &Path, offset: u64) -> Result<Vec<MftData>, String> {
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let Mft { mut file, volume, runs: mft_runs, .. } = open_mft(path, offset)?;
+    let cluster_size = volume.cluster_size;
+
+    let mut record = read_runs(
+        &mut file,
+        offset,
+        cluster_size,
+        &mft_runs,
+        BITMAP_RECORD * volume.record_size,
+        volume.record_size
+    ).map_err(error)?;
+    if &record[..4] != b"FILE" || !apply_fixups(&mut record) {
+        return Err("The $Bitmap record is missing or corrupt".to_string());
+    }
+    let bitmap = parse_record(&record)
+        .data
+        .into_iter()
+        .find_map(|data| match data {
+            Data::Resident { stream, offset: at, length } if stream.is_empty() => Some(Ok(record[at..at + length].to_vec())),
+            Data::NonResident { stream, size, runs } if stream.is_empty() =>
+                Some(read_runs(&mut file, offset, cluster_size, &runs, 0, size)),
+            _ => None,
+        })
+        .ok_or("The $Bitmap record has no data")?
+        .map_err(error)?;
+
+    // Bit n of the bitmap is set when cluster n is in use. Bits past the last cluster are padding.
+    let clusters = volume.cluster_count.min((bitmap.len() as u64) * 8);
+    let free = |cluster: u64| bitmap[(cluster / 8) as usize] & (1 << (cluster % 8)) == 0;
+    let chunk_clusters = (UNALLOCATED_CHUNK / cluster_size).max(1);
+    let mut found = Vec::new();
+    let mut cluster = 0;
+    while cluster < clusters {
+        if !free(cluster) {
+            cluster += 1;
+            continue;
+        }
+        let first = cluster;
+        while cluster < clusters && free(cluster) && cluster - first < chunk_clusters {
+            cluster += 1;
+        }
+        let bytes = read_at(&mut file, offset + first * cluster_size, ((cluster - first) * cluster_size) as usize).map_err(
+            error
+        )?;
+        if bytes.is_empty() {
+            break;
+        }
+        found.push(MftData {
+            record: BITMAP_RECORD,
+            name: format!("clusters {first}-{}", cluster - 1),
+            kind: "unallocated",
+            offset: offset + first * cluster_size,
+            length: bytes.len() as u64,
+            entropy: shannon_entropy(&bytes),
+        });
+    }
+    Ok(found)
+}
+
+/// Measure the resident data and, if asked, the file slack of every file in the NTFS volume starting `offset` bytes into the image at `path`.
+///
+/// `resident` and `slack` pick what is measured. Records that are torn or not in use are skipped.
+///
+/// Returns a [Vec] of [MftData] in record order, or an error message if the image can't be read or holds no NTFS volume at `offset`.
+pub fn mft_data(path: &Path, offset: u64, resident: bool, slack: bool) -> Result<Vec<MftData>, String> {
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let Mft { mut file, volume, size: mft_size, runs: mft_runs } = open_mft(path, offset)?;
+    let record_size = volume.record_size as usize;
+
+    let mut found = Vec::new();
+    for run in &mft_runs {
+        let Some(cluster) = run.cluster else {
+            continue;
+        };
+        let start = offset + cluster * volume.cluster_size;
+        file.seek(SeekFrom::Start(start)).map_err(error)?;
+        let mut reader = io::BufReader::new(file.by_ref().take(run.length * volume.cluster_size));
+        let mut record = vec![0u8; record_size];
+        for index in 0.. {
+            let number = (run.vcn * volume.cluster_size) / volume.record_size + index;
+            if number * volume.record_size >= mft_size || reader.read_exact(&mut record).is_err() {
+                break;
+            }
+            // Bit 0 of the flags marks a record in use.
+            if &record[..4] != b"FILE" || u16_at(&record, 0x16) & 1 == 0 || !apply_fixups(&mut record) {
+                continue;
+            }
+            let position = start + index * volume.record_size;
+            let parsed = parse_record(&record);
+            // Extension records carry no name of their own, so they are named after their base record.
+            let base = u64_at(&record, 0x20) & 0xffff_ffff_ffff;
+            let name = match (parsed.name, base) {
+                (Some(name), _) => name,
+                (None, 0) => format!("#{number}"),
+                (None, base) => format!("#{base}"),
+            };
+            for data in parsed.data {
+                match data {
+                    Data::Resident { stream, offset: at, length } if resident => {
+                        found.push(MftData {
+                            record: number,
+                            name: stream_name(&name, &stream),
+                            kind: "resident",
+                            offset: position + (at as u64),
+                            length: length as u64,
+                            entropy: shannon_entropy(&record[at..at + length]),
+                        });
+                    }
+                    Data::NonResident { stream, size, runs } if slack => {
+                        // Slack runs from the end of the file to the end of its last cluster.
+                        let used = size % volume.cluster_size;
+                        let last = cluster_of(&runs, size / volume.cluster_size);
+                        if let Some(last) = last.filter(|_| used != 0) {
+                            found.push(MftData {
+                                record: number,
+                                name: stream_name(&name, &stream),
+                                kind: "slack",
+                                offset: offset + last * volume.cluster_size + used,
+                                length: volume.cluster_size - used,
+                                entropy: 0.0,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Slack is read once the $MFT has been walked, so the walk itself stays sequential.
+    for data in found.iter_mut().filter(|data| data.kind == "slack") {
+        let bytes = read_at(&mut file, data.offset, data.length as usize).map_err(error)?;
+        data.length = bytes.len() as u64;
+        data.entropy = shannon_entropy(&bytes);
+    }
+    Ok(found)
+}
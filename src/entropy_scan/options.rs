@@ -0,0 +1,105 @@
+//! Contains the options that control how each file's entropy is calculated.
+//!
+//! The [ScanOptions] struct is passed to [super::collect_entropies] and [super::for_each_entropy].
+//!
+//! The [SymbolWidth] enum picks the size of the symbols entropy is measured over.
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::scan_cache::ScanCache;
+
+/// The size of the symbols entropy is calculated over.
+///
+/// [SymbolWidth::Byte] is the default. The maximum entropy is the symbol width in bits, so 4.0, 8.0, and 16.0 respectively.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymbolWidth {
+    Nibble,
+    #[default]
+    Byte,
+    Word,
+}
+
+impl SymbolWidth {
+    /// The width of a symbol in bits.
+    pub fn bits(&self) -> u8 {
+        match self {
+            SymbolWidth::Nibble => 4,
+            SymbolWidth::Byte => 8,
+            SymbolWidth::Word => 16,
+        }
+    }
+}
+
+impl FromStr for SymbolWidth {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "4" => Ok(SymbolWidth::Nibble),
+            "8" => Ok(SymbolWidth::Byte),
+            "16" => Ok(SymbolWidth::Word),
+            _ => Err(format!("Invalid symbol width: {value} (expected 4, 8, or 16)")),
+        }
+    }
+}
+
+/// Holds the options that control how each file's entropy is calculated.
+///
+/// The `symbol_width` field holds the size of the symbols entropy is measured over.
+///
+/// The `periodicity` field enables looking for repeating structure, such as a repeating XOR key.
+///
+/// The `try_xor` field enables trying simple XOR keys against each file.
+///
+/// The `container_depth` field holds how many levels of zip, tar, and gzip archives are opened, each member scanned after the archive it is in. 0 leaves archives closed, and 1 opens archives but not the archives inside them.
+///
+/// The `sections` field enables measuring each section of executables on its own.
+///
+/// The `deep` field enables [routing](super::route) each file to the analyzers that suit its signature: executables to section analysis, archives to member scanning, and paging files to a region map.
+///
+/// The `histogram` field enables keeping each file's byte frequency table.
+///
+/// The `jobs` field holds the number of files scanned at once. 0 uses one worker per CPU.
+///
+/// The `timings` field enables timing how long each file takes to scan.
+///
+/// The `hydrate_placeholders` field enables reading cloud-sync placeholder files, which downloads them. By default they are skipped and reported.
+///
+/// The `read_special_files` field enables reading character and block devices, FIFOs, and sockets. By default they are skipped and reported, as reading them can hang or never end.
+///
+/// The `max_file_size` field holds the largest file size, in bytes, to scan. Larger files are skipped and reported. [None] scans files of any size.
+///
+/// The `no_cache_pollution` field enables dropping each file from the page cache once it has been measured, so a scan doesn't evict the pages other programs rely on.
+///
+/// The `mmap` field enables measuring regular files through a [memory mapping](super::mmap), falling back to reading them when mapping fails.
+///
+/// The `max_load` field holds the one-minute load average above which workers wait before starting their next file. [None] never waits for the load to drop.
+///
+/// The `pause_on_battery` field enables waiting before each file while the host runs on battery.
+///
+/// The `cache` field holds the [ScanCache] files are looked up in before they are read, and added to after. [None] reads every file.
+///
+/// The `no_persist` field enables [wiping](super::wipe) every buffer that held file contents, including decompressed archive members, once it has been measured.
+///
+/// The default [ScanOptions] measure entropy over bytes.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub symbol_width: SymbolWidth,
+    pub periodicity: bool,
+    pub try_xor: bool,
+    pub container_depth: usize,
+    pub sections: bool,
+    pub deep: bool,
+    pub histogram: bool,
+    pub jobs: usize,
+    pub timings: bool,
+    pub max_file_size: Option<u64>,
+    pub hydrate_placeholders: bool,
+    pub read_special_files: bool,
+    pub no_cache_pollution: bool,
+    pub mmap: bool,
+    pub max_load: Option<f64>,
+    pub pause_on_battery: bool,
+    pub cache: Option<Arc<ScanCache>>,
+    pub no_persist: bool,
+}
@@ -0,0 +1,149 @@
+//! Contains the logic for cross-referencing files against the system package database (dpkg or rpm).
+//!
+//! The [PackageDb] struct knows which package owns each file and, when loaded for verification, which files no longer match the package's recorded digest.
+//!
+//! Unowned or modified high-entropy binaries under `/usr` are rarely benign, so [PackageDb::verify] labels every scanned file.
+use std::collections::{ HashMap, HashSet };
+use std::fs;
+use std::path::{ self, Path, PathBuf };
+use std::process::Command;
+
+use super::digest::md5_file;
+
+/// The directory holding dpkg's per-package file lists and digests.
+const DPKG_INFO: &str = "/var/lib/dpkg/info";
+
+/// The number of files passed to a single `rpm` call.
+const RPM_BATCH: usize = 512;
+
+/// Holds what the system package database says about a set of files.
+#[derive(Debug, Default)]
+pub struct PackageDb {
+    kind: &'static str,
+    owners: HashMap<PathBuf, String>,
+    digests: HashMap<PathBuf, String>,
+    modified: HashSet<PathBuf>,
+}
+
+/// Make `path` absolute, keeping it as-is if that fails.
+fn absolute(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Run `rpm` with `args` followed by a batch of `paths`, returning its standard output.
+///
+/// Returns [None] if `rpm` can't be run.
+fn rpm(args: &[&str], paths: &[PathBuf]) -> Option<String> {
+    let output = Command::new("rpm").args(args).args(paths).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+impl PackageDb {
+    /// Load the package database entries for `paths`.
+    ///
+    /// Reads dpkg's file lists when present, otherwise asks `rpm`. Digests (dpkg) or verification results (rpm) are only loaded when `verify` is set.
+    pub fn load(paths: &[PathBuf], verify: bool) -> PackageDb {
+        let mut db = PackageDb::default();
+        if let Ok(entries) = fs::read_dir(DPKG_INFO) {
+            db.kind = "deb package";
+            for entry in entries.flatten() {
+                let file = entry.path();
+                let Some(extension) = file.extension() else {
+                    continue;
+                };
+                let is_list = extension == "list";
+                if !(is_list || (verify && extension == "md5sums")) {
+                    continue;
+                }
+                let Ok(contents) = fs::read_to_string(&file) else {
+                    continue;
+                };
+                // Multi-arch packages are listed as `name:arch`.
+                let package = file.file_stem().unwrap().to_string_lossy();
+                let package = package.split(':').next().unwrap().to_string();
+                for line in contents.lines() {
+                    match is_list {
+                        true => {
+                            db.owners.insert(PathBuf::from(line), package.clone());
+                        }
+                        false => {
+                            if let Some((digest, path)) = line.split_once("  ") {
+                                db.digests.insert(Path::new("/").join(path), digest.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+            return db;
+        }
+
+        db.kind = "rpm package";
+        let paths: Vec<PathBuf> = paths
+            .iter()
+            .map(|path| absolute(path))
+            .collect();
+        for batch in paths.chunks(RPM_BATCH) {
+            let Some(owners) = rpm(&["-qf", "--queryformat", "%{NAME}\n"], batch) else {
+                break;
+            };
+            // rpm prints one line per file, in order, with a message for unowned files.
+            for (path, line) in batch.iter().zip(owners.lines()) {
+                if !line.contains(' ') {
+                    db.owners.insert(path.clone(), line.to_string());
+                }
+            }
+            if verify {
+                // Each changed file is printed as e.g. `S.5....T.  c /etc/foo`, where `5` is a digest mismatch.
+                for line in rpm(&["-Vf"], batch).unwrap_or_default().lines() {
+                    let flags = line.split_whitespace().next().unwrap_or_default();
+                    if flags.as_bytes().get(2) == Some(&b'5') {
+                        if let Some(path) = line.split_whitespace().last() {
+                            db.modified.insert(PathBuf::from(path));
+                        }
+                    }
+                }
+            }
+        }
+        db
+    }
+
+    /// The kind of package in this database, e.g. `deb package`.
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// Look up `path` in `map`, also trying the unmerged path on merged-/usr systems where packages may still list `/bin/ls` for `/usr/bin/ls`.
+    fn lookup<'a, T>(map: &'a HashMap<PathBuf, T>, path: &Path) -> Option<&'a T> {
+        let path = absolute(path);
+        let unmerged = path.strip_prefix("/usr").ok().map(|rest| Path::new("/").join(rest));
+        map.get(&path).or_else(|| unmerged.and_then(|unmerged| map.get(&unmerged)))
+    }
+
+    /// The package owning the file at `path`, if any.
+    pub fn owner(&self, path: &Path) -> Option<&str> {
+        PackageDb::lookup(&self.owners, path).map(String::as_str)
+    }
+
+    /// Check the file at `path` against its package.
+    ///
+    /// Returns the owning package, if any, and a status: `unowned`, `modified` when the digest differs, `ok`, or `unverified` when no digest is recorded.
+    pub fn verify(&self, path: &Path) -> (Option<String>, &'static str) {
+        let Some(owner) = self.owner(path) else {
+            return (None, "unowned");
+        };
+        let status = match self.kind {
+            "rpm package" =>
+                match self.modified.contains(&absolute(path)) {
+                    true => "modified",
+                    false => "ok",
+                }
+            _ =>
+                match (PackageDb::lookup(&self.digests, path), md5_file(path)) {
+                    (Some(expected), Ok(actual)) if *expected == actual => "ok",
+                    (Some(_), Ok(_)) => "modified",
+                    _ => "unverified",
+                }
+        };
+        (Some(owner.to_string()), status)
+    }
+}
@@ -0,0 +1,401 @@
+//! Contains the logic for reading MBR and GPT partition tables from whole disks and disk images.
+//!
+//! The [scan_partitions] function finds every partition of a disk and measures its entropy one block at a time.
+//!
+//! A partition whose blocks all look random, or whose first bytes carry an encrypted container signature such as LUKS or BitLocker, is flagged as a likely encrypted volume.
+use std::fs::File;
+use std::io::{ self, Read, Seek, SeekFrom };
+use std::path::Path;
+
+use super::forensic::open_file;
+use super::magic::sniff;
+use super::{ for_each_block, shannon_entropy };
+use super::structs::Partition;
+
+/// The sector size MBR tables are addressed in.
+const MBR_SECTOR_SIZE: u64 = 512;
+
+/// The sector sizes a GPT header is looked for at, in order.
+const GPT_SECTOR_SIZES: [u64; 2] = [512, 4096];
+
+/// The most GPT entries read from a table, so a corrupt header can't exhaust memory.
+const MAX_GPT_ENTRIES: u32 = 1024;
+
+/// The number of bytes at the start of a partition checked for a volume signature.
+const SIGNATURE_WINDOW: usize = 8192;
+
+/// The entropy every block of a partition must reach for it to be flagged as likely encrypted.
+const ENCRYPTED_BLOCK_ENTROPY: f64 = 7.9;
+
+/// Names for common MBR partition type bytes.
+const MBR_TYPES: &[(u8, &str)] = &[
+    (0x01, "FAT12"),
+    (0x04, "FAT16"),
+    (0x05, "Extended"),
+    (0x06, "FAT16"),
+    (0x07, "NTFS/exFAT"),
+    (0x0b, "FAT32"),
+    (0x0c, "FAT32 (LBA)"),
+    (0x0e, "FAT16 (LBA)"),
+    (0x0f, "Extended (LBA)"),
+    (0x27, "Windows recovery"),
+    (0x82, "Linux swap"),
+    (0x83, "Linux"),
+    (0x85, "Linux extended"),
+    (0x8e, "Linux LVM"),
+    (0xa5, "FreeBSD"),
+    (0xa6, "OpenBSD"),
+    (0xaf, "HFS+"),
+    (0xee, "GPT protective"),
+    (0xef, "EFI System"),
+    (0xfd, "Linux RAID"),
+];
+
+/// Names for common GPT partition type GUIDs.
+const GPT_TYPES: &[(&str, &str)] = &[
+    ("C12A7328-F81F-11D2-BA4B-00A0C93EC93B", "EFI System"),
+    ("21686148-6449-6E6F-744E-656564454649", "BIOS boot"),
+    ("E3C9E316-0B5C-4DB8-817D-F92DF00215AE", "Microsoft reserved"),
+    ("EBD0A0A2-B9E5-4433-87C0-68B6B72699C7", "Microsoft basic data"),
+    ("DE94BBA4-06D1-4D40-A16A-BFD50179D6AC", "Windows recovery"),
+    ("5808C8AA-7E8F-42E0-85D2-E1E90434CFB3", "Windows LDM metadata"),
+    ("AF9B60A0-1431-4F62-BC68-3311714A69AD", "Windows LDM data"),
+    ("0FC63DAF-8483-4772-8E79-3D69D8477DE4", "Linux filesystem"),
+    ("0657FD6D-A4AB-43C4-84E5-0933C84B4F4F", "Linux swap"),
+    ("E6D6D379-F507-44C2-A23C-238F2A3DF928", "Linux LVM"),
+    ("A19D880F-05FC-4D3B-A006-743F0F84911E", "Linux RAID"),
+    ("CA7D7CCB-63ED-4C53-861C-1742536059CC", "Linux LUKS"),
+    ("933AC7E1-2EB4-4F13-B844-0E14E2AEF915", "Linux home"),
+    ("4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709", "Linux root (x86-64)"),
+    ("7C3457EF-0000-11AA-AA11-00306543ECAC", "Apple APFS"),
+    ("48465300-0000-11AA-AA11-00306543ECAC", "Apple HFS+"),
+    ("53746F72-6167-11AA-AA11-00306543ECAC", "Apple Core Storage"),
+    ("516E7CB4-6ECF-11D6-8FF8-00022D09712B", "FreeBSD"),
+];
+
+/// A partition table entry, before its contents are read.
+struct Entry {
+    scheme: &'static str,
+    start: u64,
+    size: u64,
+    kind: String,
+    label: Option<String>,
+}
+
+/// Read up to `len` bytes at `offset`. Fewer bytes are returned at the end of the file.
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(len);
+    file.seek(SeekFrom::Start(offset))?;
+    file.by_ref()
+        .take(len as u64)
+        .read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Read a little-endian `u32` at `offset` of `bytes`.
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Read a little-endian `u64` at `offset` of `bytes`.
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Format a GUID stored in its mixed-endian on-disk layout, e.g. `C12A7328-F81F-11D2-BA4B-00A0C93EC93B`.
+fn guid_string(bytes: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{}-{}",
+        u32_at(bytes, 0),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8..10]
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<String>(),
+        bytes[10..16]
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<String>()
+    )
+}
+
+/// Name an MBR partition type byte, falling back to its hex value.
+fn mbr_type_name(kind: u8) -> String {
+    MBR_TYPES.iter()
+        .find(|(byte, _)| *byte == kind)
+        .map_or_else(|| format!("0x{kind:02x}"), |(_, name)| name.to_string())
+}
+
+/// Name a GPT partition type GUID, falling back to the GUID itself.
+fn gpt_type_name(guid: &str) -> String {
+    GPT_TYPES.iter()
+        .find(|(known, _)| *known == guid)
+        .map_or_else(|| guid.to_string(), |(_, name)| name.to_string())
+}
+
+/// Parse the four primary entries of an MBR.
+///
+/// Returns [None] if `sector` is not an MBR. Logical partitions inside an extended partition are not followed.
+fn parse_mbr(sector: &[u8]) -> Option<Vec<Entry>> {
+    if sector.len() < 512 || sector[510..512] != [0x55, 0xaa] {
+        return None;
+    }
+    let records: Vec<&[u8]> = (0..4).map(|i| &sector[446 + i * 16..462 + i * 16]).collect();
+    // A volume boot record also ends in 0x55AA, but its "entries" rarely have valid status bytes.
+    if records.iter().any(|record| record[0] != 0x00 && record[0] != 0x80) {
+        return None;
+    }
+
+    let entries = records
+        .iter()
+        .filter(|record| record[4] != 0 && u32_at(record, 12) != 0)
+        .map(|record| Entry {
+            scheme: "MBR",
+            start: (u32_at(record, 8) as u64) * MBR_SECTOR_SIZE,
+            size: (u32_at(record, 12) as u64) * MBR_SECTOR_SIZE,
+            kind: mbr_type_name(record[4]),
+            label: None,
+        })
+        .collect();
+    Some(entries)
+}
+
+/// Parse a GPT, trying each of [GPT_SECTOR_SIZES].
+///
+/// Returns [None] if no GPT header is found, or an error if the header's entries don't fit in the file.
+fn parse_gpt(file: &mut File) -> io::Result<Option<Vec<Entry>>> {
+    for sector_size in GPT_SECTOR_SIZES {
+        let header = read_at(file, sector_size, 92)?;
+        if header.len() < 92 || &header[..8] != b"EFI PART" {
+            continue;
+        }
+        let entries_lba = u64_at(&header, 72);
+        let count = u32_at(&header, 80).min(MAX_GPT_ENTRIES) as usize;
+        let entry_size = u32_at(&header, 84) as usize;
+        if !(128..=4096).contains(&entry_size) {
+            continue;
+        }
+
+        let table_start = entries_lba
+            .checked_mul(sector_size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "GPT partition entries start past the end of the disk"))?;
+        let table = read_at(file, table_start, count * entry_size)?;
+        if table.len() < count * entry_size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "GPT partition entries run past the end of the disk"));
+        }
+        let entries = table
+            .chunks_exact(entry_size)
+            .filter(|entry| entry[..16].iter().any(|b| *b != 0))
+            .map(|entry| {
+                let first = u64_at(entry, 32);
+                let last = u64_at(entry, 40);
+                let name: Vec<u16> = entry[56..128]
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .take_while(|unit| *unit != 0)
+                    .collect();
+                let name = String::from_utf16_lossy(&name);
+                let extent = (
+                    first.checked_mul(sector_size),
+                    (last.saturating_sub(first))
+                        .checked_add(1)
+                        .and_then(|sectors| sectors.checked_mul(sector_size)),
+                );
+                let (Some(start), Some(size)) = extent else {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "GPT partition lies past the end of the disk"));
+                };
+                Ok(Entry {
+                    scheme: "GPT",
+                    start,
+                    size,
+                    kind: gpt_type_name(&guid_string(&entry[..16])),
+                    label: (!name.is_empty()).then_some(name),
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        return Ok(Some(entries));
+    }
+    Ok(None)
+}
+
+/// Calculate the entropy of each `block_size` block of the `size` bytes at `start`.
+///
+/// Stops early at the end of the file.
+fn partition_profile(file: &mut File, start: u64, size: u64, block_size: usize) -> io::Result<Vec<f64>> {
+    file.seek(SeekFrom::Start(start))?;
+    let mut profile = Vec::new();
+    for_each_block(file.by_ref().take(size), block_size, |block| profile.push(shannon_entropy(block)))?;
+    Ok(profile)
+}
+
+/// Read the partition table of the disk or disk image at `path` and profile every partition.
+///
+/// A protective MBR defers to the GPT behind it. Returns a [Vec] of [Partition]s, or an error message if the file can't be read or has no partition table.
+pub fn scan_partitions(path: &Path, block_size: usize) -> Result<Vec<Partition>, String> {
+    if block_size == 0 {
+        return Err("Block size must be greater than zero".to_string());
+    }
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let mut file = open_file(path).map_err(error)?;
+
+    let mbr = read_at(&mut file, 0, MBR_SECTOR_SIZE as usize).map_err(error)?;
+    let entries = match parse_mbr(&mbr) {
+        Some(entries) if entries.iter().any(|entry| entry.kind == "GPT protective") =>
+            parse_gpt(&mut file).map_err(error)?,
+        Some(entries) => Some(entries),
+        None => parse_gpt(&mut file).map_err(error)?,
+    };
+    let entries = entries.ok_or("No MBR or GPT partition table found")?;
+
+    let mut partitions = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.into_iter().enumerate() {
+        let head = read_at(&mut file, entry.start, SIGNATURE_WINDOW.min(entry.size as usize)).map_err(
+            error
+        )?;
+        let magic = sniff(&head);
+        let signature = magic.map(|magic| magic.name);
+        let blocks = partition_profile(&mut file, entry.start, entry.size, block_size).map_err(
+            error
+        )?;
+        let entropy = match blocks.len() {
+            0 => 0.0,
+            count => blocks.iter().sum::<f64>() / (count as f64),
+        };
+        let min_entropy = match blocks.is_empty() {
+            true => 0.0,
+            false => blocks.iter().copied().fold(f64::INFINITY, f64::min),
+        };
+        let likely_encrypted =
+            magic.is_some_and(|magic| magic.encrypted) ||
+            (!blocks.is_empty() && min_entropy >= ENCRYPTED_BLOCK_ENTROPY);
+
+        partitions.push(Partition {
+            index: index + 1,
+            scheme: entry.scheme,
+            start: entry.start,
+            size: entry.size,
+            kind: entry.kind,
+            label: entry.label,
+            signature,
+            entropy,
+            min_entropy,
+            likely_encrypted,
+            blocks,
+        });
+    }
+    Ok(partitions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::scan_partitions;
+    use crate::entropy_scan::structs::Partition;
+
+    /// The on-disk bytes of the Linux filesystem type GUID.
+    const LINUX_FILESYSTEM: [u8; 16] = [
+        0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47, 0x7d, 0xe4,
+    ];
+
+    /// Build an MBR sector with one partition of type `kind` covering `sectors` sectors from `start`.
+    fn mbr(kind: u8, start: u32, sectors: u32) -> Vec<u8> {
+        let mut sector = vec![0; 512];
+        sector[446] = 0x80;
+        sector[450] = kind;
+        sector[454..458].copy_from_slice(&start.to_le_bytes());
+        sector[458..462].copy_from_slice(&sectors.to_le_bytes());
+        sector[510..512].copy_from_slice(&[0x55, 0xaa]);
+        sector
+    }
+
+    /// Build a protective MBR and a GPT header whose table of `count` 128-byte entries starts at `entries_lba`.
+    fn gpt(entries_lba: u64, count: u32) -> Vec<u8> {
+        let mut image = mbr(0xee, 1, u32::MAX);
+        let mut header = vec![0; 512];
+        header[..8].copy_from_slice(b"EFI PART");
+        header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        header[80..84].copy_from_slice(&count.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+        image.extend(header);
+        image
+    }
+
+    /// Build a GPT entry of the Linux filesystem type from sector `first` to `last`, named `name`.
+    fn gpt_entry(first: u64, last: u64, name: &str) -> Vec<u8> {
+        let mut entry = vec![0; 128];
+        entry[..16].copy_from_slice(&LINUX_FILESYSTEM);
+        entry[32..40].copy_from_slice(&first.to_le_bytes());
+        entry[40..48].copy_from_slice(&last.to_le_bytes());
+        for (i, unit) in name.encode_utf16().enumerate() {
+            entry[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        entry
+    }
+
+    /// Write `image` to a temporary file named after `name` and scan its partitions.
+    fn scan(name: &str, image: &[u8]) -> Result<Vec<Partition>, String> {
+        let path = env::temp_dir().join(format!("entropyscan-{}-partitions-{name}.img", std::process::id()));
+        fs::write(&path, image).unwrap();
+        let partitions = scan_partitions(&path, 512);
+        fs::remove_file(&path).unwrap();
+        partitions
+    }
+
+    #[test]
+    fn mbr_partitions_are_listed() {
+        let mut image = mbr(0x83, 1, 2);
+        image.resize(3 * 512, 0);
+        let partitions = scan("mbr", &image).unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].scheme, "MBR");
+        assert_eq!(partitions[0].kind, "Linux");
+        assert_eq!((partitions[0].start, partitions[0].size), (512, 1024));
+        assert_eq!(partitions[0].blocks, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn gpt_partitions_are_listed_behind_a_protective_mbr() {
+        let mut image = gpt(2, 4);
+        image.extend(gpt_entry(6, 7, "root"));
+        image.resize(8 * 512, 0);
+        let partitions = scan("gpt", &image).unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].scheme, "GPT");
+        assert_eq!(partitions[0].kind, "Linux filesystem");
+        assert_eq!(partitions[0].label.as_deref(), Some("root"));
+        assert_eq!((partitions[0].start, partitions[0].size), (6 * 512, 1024));
+    }
+
+    #[test]
+    fn protective_mbrs_without_a_gpt_have_no_table() {
+        let mut image = mbr(0xee, 1, u32::MAX);
+        image.resize(4 * 512, 0);
+        assert_eq!(scan("protective", &image).unwrap_err(), "No MBR or GPT partition table found");
+    }
+
+    #[test]
+    fn truncated_headers_have_no_table() {
+        assert_eq!(scan("short-mbr", &mbr(0x83, 1, 2)[..300]).unwrap_err(), "No MBR or GPT partition table found");
+        let image = gpt(2, 4);
+        assert_eq!(scan("short-gpt", &image[..512 + 80]).unwrap_err(), "No MBR or GPT partition table found");
+    }
+
+    #[test]
+    fn gpt_tables_past_the_end_are_errors() {
+        let mut image = gpt(2, 128);
+        image.extend(gpt_entry(6, 7, "root"));
+        image.resize(8 * 512, 0);
+        assert!(scan("gpt-count", &image).unwrap_err().contains("past the end of the disk"));
+
+        let image = gpt(u64::MAX / 4, 4);
+        assert!(scan("gpt-lba", &image).unwrap_err().contains("past the end of the disk"));
+
+        let mut image = gpt(2, 4);
+        image.extend(gpt_entry(0, u64::MAX, "huge"));
+        image.resize(8 * 512, 0);
+        assert!(scan("gpt-size", &image).unwrap_err().contains("past the end of the disk"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Contains the autocorrelation analysis used to spot periodic structure in a file.
+//!
+//! Data XORed with a repeating key keeps the plaintext's coincidences at multiples of the key length, so it looks random byte by byte but repeats itself at a fixed distance.
+//!
+//! The [detect_periodicity] function compares how often bytes repeat at each distance with how often they would by chance.
+use super::histogram::ByteHistogram;
+use super::structs::Periodicity;
+
+/// The largest period looked for, in bytes.
+pub const MAX_PERIOD: usize = 256;
+
+/// The number of bytes from the start of a file that are analysed.
+pub const PERIODICITY_WINDOW: usize = 64 * 1024;
+
+/// How many times more often than chance bytes must repeat for a period to be reported.
+const MIN_STRENGTH: f64 = 3.0;
+
+/// Peaks within this fraction of the strongest are treated as equal, so the shortest period wins over its multiples.
+const PEAK_TOLERANCE: f64 = 0.8;
+
+/// Detect periodic structure in `bytes` using autocorrelation.
+///
+/// Only the first [PERIODICITY_WINDOW] bytes are used. Returns a [Periodicity] whose `period` is set when some distance up to [MAX_PERIOD] repeats at least [MIN_STRENGTH] times more often than chance.
+pub fn detect_periodicity(bytes: &[u8]) -> Periodicity {
+    let window = &bytes[..bytes.len().min(PERIODICITY_WINDOW)];
+    let max_lag = MAX_PERIOD.min(window.len() / 4);
+
+    let histogram = ByteHistogram::of(window);
+    let total = histogram.total() as f64;
+    // The chance that two bytes picked at random are equal.
+    let chance: f64 = histogram
+        .counts()
+        .iter()
+        .map(|count| ((*count as f64) / total).powi(2))
+        .sum();
+
+    let strengths: Vec<(usize, f64)> = (1..=max_lag)
+        .map(|lag| {
+            let pairs = window.len() - lag;
+            let matches = window
+                .iter()
+                .zip(&window[lag..])
+                .filter(|(a, b)| a == b)
+                .count();
+            (lag, (matches as f64) / (pairs as f64) / chance)
+        })
+        .collect();
+
+    let strongest = strengths
+        .iter()
+        .map(|(_, strength)| *strength)
+        .fold(0.0f64, f64::max);
+    let period = strengths
+        .iter()
+        .find(|(_, strength)| *strength >= strongest * PEAK_TOLERANCE)
+        .filter(|_| strongest >= MIN_STRENGTH)
+        .map(|(lag, _)| *lag);
+
+    Periodicity {
+        period,
+        strength: strongest,
+    }
+}
@@ -0,0 +1,78 @@
+//! Contains the logic for recognising cloud-sync placeholder ("dehydrated") files.
+//!
+//! OneDrive, Dropbox, and iCloud keep files that live only in the cloud as placeholders, which are downloaded the moment they are read. Reading one to measure its entropy would trigger the download. Scanning a synced folder that way means downloading all of it.
+//!
+//! The [placeholder_kind] function names the kind of placeholder a file is, so scans can skip it unless asked to hydrate it.
+use std::fs::Metadata;
+use std::path::Path;
+
+/// The file attribute Windows sets on files whose data is fetched when read, e.g. OneDrive files-on-demand.
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+
+/// The file attribute Windows sets on files whose data is fetched when opened.
+#[cfg(windows)]
+const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+
+/// The file attribute Windows sets on files moved to offline storage.
+#[cfg(windows)]
+const FILE_ATTRIBUTE_OFFLINE: u32 = 0x0000_1000;
+
+/// The file flag macOS sets on dataless files, whose contents the File Provider (iCloud Drive, Dropbox) fetches when read.
+#[cfg(target_os = "macos")]
+const SF_DATALESS: u32 = 0x4000_0000;
+
+/// Name the kind of placeholder the file at `path` is from its attributes.
+#[cfg(windows)]
+fn attribute_kind(_path: &Path, metadata: &Metadata) -> Option<&'static str> {
+    use std::os::windows::fs::MetadataExt;
+
+    let attributes = metadata.file_attributes();
+    match attributes {
+        _ if attributes & (FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN) != 0 =>
+            Some("cloud placeholder"),
+        _ if attributes & FILE_ATTRIBUTE_OFFLINE != 0 => Some("offline file"),
+        _ => None,
+    }
+}
+
+/// Name the kind of placeholder the file at `path` is from its flags.
+#[cfg(target_os = "macos")]
+fn attribute_kind(_path: &Path, metadata: &Metadata) -> Option<&'static str> {
+    use std::os::macos::fs::MetadataExt;
+
+    match metadata.st_flags() & SF_DATALESS != 0 {
+        true => Some("dataless file"),
+        false => None,
+    }
+}
+
+/// Name the kind of placeholder the file at `path` is from its allocation.
+///
+/// FUSE sync clients report a placeholder's full size with no blocks allocated behind it.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn attribute_kind(_path: &Path, metadata: &Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::MetadataExt;
+
+    match metadata.len() > 0 && metadata.blocks() == 0 {
+        true => Some("unallocated file"),
+        false => None,
+    }
+}
+
+/// Placeholders can't be recognised from attributes on this platform.
+#[cfg(not(any(windows, unix)))]
+fn attribute_kind(_path: &Path, _metadata: &Metadata) -> Option<&'static str> {
+    None
+}
+
+/// Name the kind of cloud-sync placeholder the file at `path` is, e.g. `iCloud placeholder` or `cloud placeholder`.
+///
+/// Evicted iCloud Drive files left as `.name.icloud` stubs are recognised by name on every platform. Returns [None] for ordinary files.
+pub fn placeholder_kind(path: &Path, metadata: &Metadata) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy();
+    if name.starts_with('.') && name.ends_with(".icloud") {
+        return Some("iCloud placeholder");
+    }
+    attribute_kind(path, metadata)
+}
@@ -0,0 +1,93 @@
+//! Contains presets: named lists of locations worth scanning on a given platform.
+//!
+//! The [MACOS_ARTIFACTS] preset covers the places macOS responders look first, and [preset_targets] turns a preset into the paths that exist on this machine.
+//!
+//! The [STAGING_LOCATIONS] preset covers the places data is usually gathered before it is exfiltrated.
+//!
+//! The [CI_SKIP_NAMES] preset lists the vendored, generated, and lock files a repository scan in CI leaves out, with [CI_MIN_ENTROPY] as its threshold.
+use std::env;
+use std::path::PathBuf;
+
+/// Locations of interest on macOS: applications, launch agents and daemons, the quarantine and TCC databases, and common drop locations.
+///
+/// A leading `~/` is the current user's home directory.
+pub const MACOS_ARTIFACTS: &[&str] = &[
+    "/Applications",
+    "~/Applications",
+    "/Library/LaunchAgents",
+    "/Library/LaunchDaemons",
+    "~/Library/LaunchAgents",
+    "~/Library/Preferences/com.apple.LaunchServices.QuarantineEventsV2",
+    "/Library/Application Support/com.apple.TCC/TCC.db",
+    "~/Library/Application Support/com.apple.TCC/TCC.db",
+    "~/Downloads",
+    "/Users/Shared",
+    "/private/tmp",
+];
+
+/// Exfiltration staging areas: the trash, temporary directories, shared memory, downloads, and cloud-sync caches.
+///
+/// A leading `~/` is the current user's home directory, and a leading `%NAME%` is the value of the environment variable `NAME`.
+pub const STAGING_LOCATIONS: &[&str] = &[
+    "/tmp",
+    "/var/tmp",
+    "/dev/shm",
+    "/private/tmp",
+    "~/Downloads",
+    "~/.Trash",
+    "~/.local/share/Trash",
+    "~/Dropbox/.dropbox.cache",
+    "C:\\$Recycle.Bin",
+    "%TEMP%",
+    "%USERPROFILE%\\Downloads",
+];
+
+/// Directories and files skipped by `--ci`: vendored dependencies, build output, virtual environments, and lock files.
+///
+/// Their contents are either not the repository's own or are full of hashes that look random by design.
+pub const CI_SKIP_NAMES: &[&str] = &[
+    ".git",
+    "node_modules",
+    "bower_components",
+    "vendor",
+    "target",
+    "venv",
+    ".venv",
+    "__pycache__",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "go.sum",
+];
+
+/// The entropy `--ci` reports files from when no `--min-entropy` is given, i.e. compressed or encrypted blobs.
+pub const CI_MIN_ENTROPY: f64 = 7.5;
+
+/// Expand a leading `~/` in `location` to the current user's home directory, and a leading `%NAME%` to the value of `NAME`.
+///
+/// Returns [None] if the location needs a home directory or variable and none is set.
+fn expand_home(location: &str) -> Option<PathBuf> {
+    if let Some(rest) = location.strip_prefix('%') {
+        let (name, rest) = rest.split_once('%')?;
+        let value = env::var_os(name).filter(|value| !value.is_empty())?;
+        return Some(PathBuf::from(format!("{}{rest}", value.to_string_lossy())));
+    }
+    match location.strip_prefix("~/") {
+        Some(rest) => env::var_os("HOME").map(|home| PathBuf::from(home).join(rest)),
+        None => Some(PathBuf::from(location)),
+    }
+}
+
+/// The locations of `preset` that exist on this machine.
+pub fn preset_targets(preset: &[&str]) -> Vec<PathBuf> {
+    preset
+        .iter()
+        .filter_map(|location| expand_home(location))
+        .filter(|path| path.exists())
+        .collect()
+}
@@ -0,0 +1,101 @@
+//! Contains the logic for building region-level entropy maps of large files such as swap and hibernation files.
+//!
+//! The [region_map] function streams a file one block at a time, so it has no size limit, and merges neighbouring blocks of the same [class](classify) into [Region]s.
+//!
+//! Paging files are recognised by name (`pagefile.sys`, `swapfile.sys`, `hiberfil.sys`) and by their headers, including the signatures of compressed Windows hibernation files and Linux suspend images.
+use std::io;
+use std::path::Path;
+
+use super::forensic::open_file;
+use super::magic::sniff;
+use super::structs::{ Region, RegionMap };
+use super::{ for_each_block, shannon_entropy };
+
+/// The number of bytes at the start of a file checked for a header signature.
+const HEADER_WINDOW: usize = 8192;
+
+/// Blocks below this entropy are classed as `low`.
+const LOW_ENTROPY: f64 = 3.0;
+
+/// Blocks at or above this entropy are classed as `high`, i.e. compressed or encrypted.
+const HIGH_ENTROPY: f64 = 7.0;
+
+/// Paging and hibernation files recognised by their file name, compared case-insensitively.
+const PAGING_FILE_NAMES: &[(&str, &str)] = &[
+    ("pagefile.sys", "Windows page file"),
+    ("swapfile.sys", "Windows swap file"),
+    ("hiberfil.sys", "Windows hibernation file"),
+    ("swapfile", "Linux swap file"),
+    ("swap.img", "Linux swap file"),
+];
+
+/// Class a block by its contents and entropy: `zero`, `low`, `mixed`, or `high`.
+fn classify(block: &[u8], entropy: f64) -> &'static str {
+    match entropy {
+        _ if block.iter().all(|b| *b == 0) => "zero",
+        e if e < LOW_ENTROPY => "low",
+        e if e < HIGH_ENTROPY => "mixed",
+        _ => "high",
+    }
+}
+
+/// Name the paging or hibernation file `path` is, judging by its file name.
+pub fn paging_file_kind(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    PAGING_FILE_NAMES.iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, kind)| *kind)
+}
+
+/// Build the region map of the file at `path` from `block_size` blocks.
+///
+/// Returns a [RegionMap], or an error message if the file can't be read.
+pub fn region_map(path: &Path, block_size: usize) -> Result<RegionMap, String> {
+    if block_size == 0 {
+        return Err("Block size must be greater than zero".to_string());
+    }
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let file = open_file(path).map_err(error)?;
+
+    let mut header = Vec::with_capacity(HEADER_WINDOW);
+    let mut regions: Vec<Region> = Vec::new();
+    // The running entropy total of the last region, to average it once the region is complete.
+    let mut total = 0.0f64;
+    let mut blocks = 0usize;
+    let mut offset = 0u64;
+    for_each_block(file, block_size, |block| {
+        if header.len() < HEADER_WINDOW {
+            let wanted = (HEADER_WINDOW - header.len()).min(block.len());
+            header.extend_from_slice(&block[..wanted]);
+        }
+        let entropy = shannon_entropy(block);
+        let class = classify(block, entropy);
+        match regions.last_mut() {
+            Some(region) if region.class == class => {
+                region.length += block.len() as u64;
+                total += entropy;
+                blocks += 1;
+                region.entropy = total / (blocks as f64);
+            }
+            _ => {
+                regions.push(Region {
+                    start: offset,
+                    length: block.len() as u64,
+                    class: class.to_string(),
+                    entropy,
+                });
+                total = entropy;
+                blocks = 1;
+            }
+        }
+        offset += block.len() as u64;
+    }).map_err(error)?;
+
+    Ok(RegionMap {
+        path: path.to_path_buf(),
+        kind: paging_file_kind(path),
+        signature: sniff(&header).map(|magic| magic.name),
+        size: offset,
+        regions,
+    })
+}
@@ -0,0 +1,66 @@
+//! Contains the logic for `scan --deep`: routing each file to the specialized analyzers that suit it, judged by its signature.
+//!
+//! Without `--deep`, each analyzer runs on every file or on none, as its own flag says. With it, [route] reads the start of each file and picks:
+//!
+//! - [section analysis](super::sections::file_sections) for PE, ELF, and Mach-O executables,
+//! - the [region map](super::regions::region_map) for paging and hibernation files, recognised by name or signature, and
+//! - [archive members](super::archive::archive_members) for zip, tar, and gzip files, which recognises its archives itself, so every file is offered to it.
+//!
+//! The analyzers' results are kept in the file's [FileEntropy](super::structs::FileEntropy).
+use std::fs::Metadata;
+use std::io::Read;
+use std::path::Path;
+
+use super::chunking::EXECUTABLE_MIMES;
+use super::forensic::open_file;
+use super::magic::{ sniff, SNIFF_LEN };
+use super::options::ScanOptions;
+use super::regions::paging_file_kind;
+use super::wipe::Wiped;
+
+/// The block size paging and hibernation files are mapped with, as `regions` does by default.
+///
+/// This is set to 64KB.
+pub const REGION_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Holds which analyzers a file is routed to, besides the whole-file measurement.
+///
+/// The `sections` field is set to measure each section of an executable.
+///
+/// The `regions` field is set to build a region map of a paging or hibernation file.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Analyzers {
+    pub sections: bool,
+    pub regions: bool,
+}
+
+/// Decide which analyzers a file starting with `head`, at `path`, suits.
+pub fn analyzers_for(path: &Path, head: &[u8]) -> Analyzers {
+    let magic = sniff(head);
+    Analyzers {
+        sections: magic.is_some_and(|magic| EXECUTABLE_MIMES.contains(&magic.mime)),
+        regions: paging_file_kind(path).is_some() ||
+        magic.is_some_and(|magic| magic.name.contains("swap") || magic.name.contains("suspend") || magic.name.contains("hibernation")),
+    }
+}
+
+/// Decide which analyzers the file at `path`, with `metadata`, is run through under `options`.
+///
+/// Without the `deep` option these are just the ones asked for. With it, regular files are routed by [analyzers_for] as well; other files are never opened to be routed, as opening a FIFO or device can block.
+pub fn route(path: &Path, metadata: &Metadata, options: &ScanOptions) -> Analyzers {
+    let requested = Analyzers { sections: options.sections, regions: false };
+    if !options.deep || !metadata.is_file() {
+        return requested;
+    }
+    let mut head = Wiped::with_capacity(SNIFF_LEN, options.no_persist);
+    // A file that can't be read isn't routed anywhere; measuring it reports why.
+    if open_file(path).and_then(|file| head.read_from(file.take(SNIFF_LEN as u64))).is_err() {
+        return requested;
+    }
+    let routed = analyzers_for(path, &head);
+    Analyzers {
+        sections: requested.sections || routed.sections,
+        regions: routed.regions,
+    }
+}
@@ -0,0 +1,82 @@
+//! Contains the seedable randomness used by sampling features.
+//!
+//! [XorShift] is a small deterministic pseudo-random generator, so the same seed always picks the same sample.
+//!
+//! [sample_targets] picks a random subset of targets, and [random_seed] provides a seed when none is given.
+use std::collections::hash_map::RandomState;
+use std::hash::{ BuildHasher, Hasher };
+use std::path::PathBuf;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/// The state used instead of a zero seed, which xorshift can never leave.
+const ZERO_SEED_REPLACEMENT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// A small, fast, deterministic pseudo-random generator (xorshift64*).
+///
+/// Not suitable for anything security related; it only needs to produce the same bytes everywhere.
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    /// Create a generator from `seed`. A zero seed is replaced, since xorshift never leaves zero.
+    pub fn new(seed: u64) -> Self {
+        XorShift { state: if seed == 0 { ZERO_SEED_REPLACEMENT } else { seed } }
+    }
+
+    /// Return the next pseudo-random [u64].
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Return `len` pseudo-random bytes.
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len + 8);
+        while bytes.len() < len {
+            bytes.extend_from_slice(&self.next_u64().to_le_bytes());
+        }
+        bytes.truncate(len);
+        bytes
+    }
+}
+
+
+/// Generate a fresh seed for when the user didn't pick one.
+///
+/// The seed is recorded in reports so the sample can be reproduced later.
+pub fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    hasher.finish()
+}
+
+/// Pick `count` targets at random using `seed`.
+///
+/// The chosen targets keep their original order. If there are no more than `count` targets, all of them are returned.
+pub fn sample_targets(targets: Vec<PathBuf>, count: usize, seed: u64) -> Vec<PathBuf> {
+    if targets.len() <= count {
+        return targets;
+    }
+
+    let mut rng = XorShift::new(seed);
+    let mut indices: Vec<usize> = (0..targets.len()).collect();
+    for i in 0..count {
+        let j = i + ((rng.next_u64() % ((targets.len() - i) as u64)) as usize);
+        indices.swap(i, j);
+    }
+    let mut chosen = indices[..count].to_vec();
+    chosen.sort_unstable();
+
+    let mut targets: Vec<Option<PathBuf>> = targets.into_iter().map(Some).collect();
+    chosen
+        .into_iter()
+        .filter_map(|i| targets[i].take())
+        .collect()
+}
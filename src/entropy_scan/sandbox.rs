@@ -0,0 +1,127 @@
+//! Contains the logic for confining a scan before it parses untrusted files.
+//!
+//! The [confine] function limits the process to reading beneath the scan roots, so a parser exploited by a crafted archive or executable can't write, delete, or run anything. Files and pipes already open, such as output files and standard output, keep working.
+//!
+//! On Linux this uses Landlock (kernel 5.13 or later) after setting `no_new_privs`, so setuid programs can't be used to escape. Other platforms aren't supported yet.
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::os::raw::{ c_int, c_long };
+
+/// The `landlock_create_ruleset` system call number, the same on every Linux architecture.
+#[cfg(target_os = "linux")]
+const SYS_LANDLOCK_CREATE_RULESET: c_long = 444;
+
+/// The `landlock_add_rule` system call number.
+#[cfg(target_os = "linux")]
+const SYS_LANDLOCK_ADD_RULE: c_long = 445;
+
+/// The `landlock_restrict_self` system call number.
+#[cfg(target_os = "linux")]
+const SYS_LANDLOCK_RESTRICT_SELF: c_long = 446;
+
+/// The `landlock_create_ruleset` flag that asks for the supported Landlock ABI version.
+#[cfg(target_os = "linux")]
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+
+/// The Landlock rule type granting access beneath a directory.
+#[cfg(target_os = "linux")]
+const LANDLOCK_RULE_PATH_BENEATH: c_int = 1;
+
+/// The Landlock filesystem right to read files.
+#[cfg(target_os = "linux")]
+const ACCESS_READ_FILE: u64 = 1 << 2;
+
+/// The Landlock filesystem right to list directories. Only directories can be granted it.
+#[cfg(target_os = "linux")]
+const ACCESS_READ_DIR: u64 = 1 << 3;
+
+/// Every filesystem right of the first Landlock ABI, from executing files to making symlinks. All of them are denied unless granted.
+#[cfg(target_os = "linux")]
+const HANDLED_ACCESS: u64 = (1 << 13) - 1;
+
+/// The `prctl` option that stops the process and its children gaining privileges.
+#[cfg(target_os = "linux")]
+const PR_SET_NO_NEW_PRIVS: c_int = 38;
+
+/// The `landlock_ruleset_attr` structure of the first Landlock ABI.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+/// The `landlock_path_beneath_attr` structure.
+#[cfg(target_os = "linux")]
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn syscall(number: c_long, ...) -> c_long;
+    fn prctl(option: c_int, ...) -> c_int;
+}
+
+/// Allow nothing but reading beneath `roots` from now on.
+///
+/// Returns an error message if the kernel doesn't support Landlock or the rules can't be applied. The process is left unconfined in that case.
+#[cfg(target_os = "linux")]
+pub fn confine(roots: &[PathBuf]) -> Result<(), String> {
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd };
+
+    let error = |step: &str| format!("Couldn't {step}: {}", io::Error::last_os_error());
+    // SAFETY: asking for the ABI version takes no pointer.
+    let version = unsafe { syscall(SYS_LANDLOCK_CREATE_RULESET, 0usize, 0usize, LANDLOCK_CREATE_RULESET_VERSION) };
+    if version < 1 {
+        return Err("This kernel doesn't support Landlock sandboxing (Linux 5.13 or later is needed)".to_string());
+    }
+
+    let attr = RulesetAttr { handled_access_fs: HANDLED_ACCESS };
+    // SAFETY: `attr` outlives the call and its size is passed alongside it.
+    let ruleset = unsafe { syscall(SYS_LANDLOCK_CREATE_RULESET, &attr as *const RulesetAttr, size_of::<RulesetAttr>(), 0u32) };
+    if ruleset < 0 {
+        return Err(error("create the sandbox"));
+    }
+    // SAFETY: the kernel just returned this descriptor and nothing else owns it.
+    let ruleset = unsafe { OwnedFd::from_raw_fd(ruleset as c_int) };
+
+    for root in roots {
+        let parent = File::open(root).map_err(|e| format!("Couldn't open {}: {e}", root.to_string_lossy()))?;
+        let rule = PathBeneathAttr {
+            allowed_access: match root.is_dir() {
+                true => ACCESS_READ_FILE | ACCESS_READ_DIR,
+                false => ACCESS_READ_FILE,
+            },
+            parent_fd: parent.as_raw_fd(),
+        };
+        // SAFETY: `rule` outlives the call.
+        let added = unsafe {
+            syscall(SYS_LANDLOCK_ADD_RULE, ruleset.as_raw_fd(), LANDLOCK_RULE_PATH_BENEATH, &rule as *const PathBeneathAttr, 0u32)
+        };
+        if added < 0 {
+            return Err(error(&format!("allow reading {}", root.to_string_lossy())));
+        }
+    }
+
+    // SAFETY: setting no_new_privs takes no pointer.
+    if unsafe { prctl(PR_SET_NO_NEW_PRIVS, 1usize, 0usize, 0usize, 0usize) } != 0 {
+        return Err(error("set no_new_privs"));
+    }
+    // SAFETY: `ruleset` is a valid Landlock ruleset descriptor.
+    if unsafe { syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset.as_raw_fd(), 0u32) } != 0 {
+        return Err(error("enter the sandbox"));
+    }
+    Ok(())
+}
+
+/// Allow nothing but reading beneath `roots` from now on.
+///
+/// Always returns an error message, as sandboxing is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn confine(_roots: &[PathBuf]) -> Result<(), String> {
+    Err("Sandboxing is only supported on Linux".to_string())
+}
@@ -0,0 +1,189 @@
+//! Contains the persistent cache that lets repeated scans of the same tree skip files that haven't changed.
+//!
+//! A [ScanCache] remembers each file's [FileEntropy] along with its [Stamp] and the options it was measured with. A file whose stamp still matches is not read again.
+//!
+//! The stamp includes the change time, which the kernel updates on every write and user space can't set back, unlike the modification time. A file encrypted in place and given back its old size and modification time with `utime`, as ransomware does to hide, is measured again. Where there is no change time, as on Windows, nothing is cached.
+//!
+//! The cache lives in [default_cache_path], under the user's cache directory, as one JSON object per line.
+use std::collections::HashMap;
+use std::env;
+use std::fs::{ self, File, Metadata };
+use std::io::{ BufRead, BufReader, BufWriter, Write };
+use std::path::{ Path, PathBuf };
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use serde::{ Deserialize, Serialize };
+
+use super::options::ScanOptions;
+use super::structs::FileEntropy;
+
+/// The cache file's name inside the cache directory.
+const CACHE_FILE: &str = "scan-cache.jsonl";
+
+/// Holds what is remembered about one file.
+///
+/// The `path` field holds the file's path, which must be valid UTF-8 to be cached.
+///
+/// The `options` field holds a fingerprint of the [ScanOptions] the file was measured with, see [fingerprint].
+///
+/// The `stamp` field holds the file's [Stamp] when it was measured.
+///
+/// The `entropy` field holds what was measured.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    path: String,
+    options: String,
+    stamp: Stamp,
+    entropy: FileEntropy,
+}
+
+/// Holds what tells whether a file may have changed since it was measured.
+///
+/// The `size` field holds its size in bytes.
+///
+/// The `modified` and `changed` fields hold its modification and change times in nanoseconds since the Unix epoch.
+///
+/// The `device` and `inode` fields hold the device and inode numbers, so a different file moved into its place doesn't match.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    modified: u128,
+    changed: i128,
+    device: u64,
+    inode: u64,
+}
+
+/// Holds the entropies of files measured by earlier scans, and those measured since the cache was loaded.
+///
+/// Lookups and new entries can come from several scanning threads at once.
+#[derive(Debug)]
+pub struct ScanCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+/// Find where the cache is kept: `entropyscan/scan-cache.jsonl` under `$XDG_CACHE_HOME`, `~/.cache`, or `%LOCALAPPDATA%`.
+///
+/// Returns [None] if none of them is set.
+pub fn default_cache_path() -> Option<PathBuf> {
+    let nonempty = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    let base = nonempty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| nonempty("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| nonempty("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("entropyscan").join(CACHE_FILE))
+}
+
+/// Summarise the [ScanOptions] that change what is measured, so entries measured differently aren't reused.
+fn fingerprint(options: &ScanOptions) -> String {
+    format!(
+        "{:?} periodicity={} xor={} sections={} deep={} histogram={} timings={}",
+        options.symbol_width,
+        options.periodicity,
+        options.try_xor,
+        options.sections,
+        options.deep,
+        options.histogram,
+        options.timings
+    )
+}
+
+/// Read a file's [Stamp] from its `metadata`.
+///
+/// Returns [None] if it has no modification time.
+#[cfg(unix)]
+fn stamp(metadata: &Metadata) -> Option<Stamp> {
+    use std::os::unix::fs::MetadataExt;
+
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some(Stamp {
+        size: metadata.len(),
+        modified,
+        changed: (metadata.ctime() as i128) * 1_000_000_000 + (metadata.ctime_nsec() as i128),
+        device: metadata.dev(),
+        inode: metadata.ino(),
+    })
+}
+
+/// Read a file's [Stamp] from its `metadata`.
+///
+/// Always returns [None]: without a change time a rewritten file can't be told apart from an unchanged one, so nothing is cached.
+#[cfg(not(unix))]
+fn stamp(_metadata: &Metadata) -> Option<Stamp> {
+    None
+}
+
+impl ScanCache {
+    /// Load the cache kept at `path`, starting empty if it doesn't exist yet or `refresh` is set.
+    ///
+    /// Lines that can't be read, such as ones written by an older version, are dropped.
+    pub fn load(path: &Path, refresh: bool) -> ScanCache {
+        let mut entries = HashMap::new();
+        if !refresh {
+            if let Ok(file) = File::open(path) {
+                for line in BufReader::new(file).lines().map_while(Result::ok) {
+                    if let Ok(entry) = serde_json::from_str::<Entry>(&line) {
+                        entries.insert(entry.path.clone(), entry);
+                    }
+                }
+            }
+        }
+        ScanCache { path: path.to_path_buf(), entries: Mutex::new(entries) }
+    }
+
+    /// Find what was measured for the file at `path` with the same `options`, if its [Stamp] in `metadata` hasn't changed.
+    pub fn get(&self, path: &Path, metadata: &Metadata, options: &ScanOptions) -> Option<FileEntropy> {
+        let key = path.to_str()?;
+        let stamp = stamp(metadata)?;
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|entry| entry.stamp == stamp)
+            .filter(|entry| entry.options == fingerprint(options))
+            .map(|entry| entry.entropy.clone())
+    }
+
+    /// Remember what was measured for the file at `path`, whose `metadata` was read before it was.
+    pub fn insert(&self, path: &Path, metadata: &Metadata, options: &ScanOptions, entropy: &FileEntropy) {
+        let (Some(key), Some(stamp)) = (path.to_str(), stamp(metadata)) else {
+            return;
+        };
+        let entry = Entry {
+            path: key.to_string(),
+            options: fingerprint(options),
+            stamp,
+            entropy: entropy.clone(),
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(entry.path.clone(), entry);
+        }
+    }
+
+    /// Write the cache back to where it was loaded from, creating its directory if needed.
+    ///
+    /// The new cache is written beside the old one and renamed over it, so an interrupted save leaves the old cache intact. Returns an error message if it can't be written.
+    pub fn save(&self) -> Result<(), String> {
+        let error = |e: std::io::Error| format!("Couldn't write the scan cache {}: {e}", self.path.to_string_lossy());
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        // Scans running side by side each write their own copy, the last to finish winning.
+        let partial = self.path.with_extension(format!("jsonl.{}.partial", std::process::id()));
+        let mut out = BufWriter::new(File::create(&partial).map_err(error)?);
+        let entries = self.entries.lock().map_err(|_| "The scan cache was poisoned".to_string())?;
+        for entry in entries.values() {
+            serde_json::to_writer(&mut out, entry).map_err(|e| error(e.into()))?;
+            writeln!(out).map_err(error)?;
+        }
+        out.flush().map_err(error)?;
+        fs::rename(&partial, &self.path).map_err(error)
+    }
+}
@@ -0,0 +1,319 @@
+//! Contains the logic for measuring the entropy of each section of an executable.
+//!
+//! The [file_sections] function recognises PE, ELF, and Mach-O executables and measures each of their sections or segments, plus any overlay appended after the last one, on its own.
+//!
+//! The headers and tables are parsed with [goblin], reading only them rather than the whole executable. PE files are split by their section table. ELF files are split by their section headers, or by their program headers when the section headers were stripped, as packers do. Mach-O files are split by their segments, one slice of a universal binary after another.
+//!
+//! Packers such as UPX and Themida leave a whole-file average that looks ordinary, but their packed sections stand out at close to 8.0, often next to an empty section the code is unpacked into.
+use std::fs::File;
+use std::io::{ self, Read, Seek, SeekFrom };
+use std::path::Path;
+
+use goblin::container::Ctx;
+use goblin::elf::{ Elf, ProgramHeader, SectionHeader };
+use goblin::elf::header::ELFMAG;
+use goblin::elf::program_header::pt_to_str;
+use goblin::elf::section_header::SHT_NOBITS;
+use goblin::mach::{ parse_magic_and_ctx, peek, MachO };
+use goblin::mach::cputype::get_arch_name_from_types;
+use goblin::mach::fat::{ FatArch, FatHeader, FAT_MAGIC, SIZEOF_FAT_ARCH, SIZEOF_FAT_HEADER };
+use goblin::mach::header::{ SIZEOF_HEADER_32, SIZEOF_HEADER_64 };
+use goblin::pe::header::{ CoffHeader, DosHeader, PE_POINTER_OFFSET, SIZEOF_COFF_HEADER, SIZEOF_PE_MAGIC };
+use goblin::pe::section_table::{ SectionTable, SIZEOF_SECTION_TABLE };
+use goblin::strtab::Strtab;
+
+use super::for_each_block;
+use super::forensic::open_file;
+use super::histogram::ByteHistogram;
+use super::structs::Section;
+
+/// The block size sections are streamed in.
+const SECTION_BLOCK_SIZE: usize = 64 * 1024;
+
+/// The most sections read from a PE section table, as allowed by the Windows loader, so a corrupt header can't exhaust memory.
+const MAX_PE_SECTIONS: usize = 96;
+
+/// The most section or program headers read from an ELF file, and segments from a Mach-O file, so a corrupt header can't exhaust memory.
+const MAX_ENTRIES: usize = 1024;
+
+/// The most bytes of Mach-O load commands or an ELF section name table read, and the furthest a PE header may start into the file.
+const MAX_TABLE_SIZE: usize = 1024 * 1024;
+
+/// The size of the DOS header a PE file starts with, the last field of which points to the PE header.
+const DOS_HEADER_SIZE: usize = 64;
+
+/// Read up to `len` bytes at `offset`. Fewer bytes are returned at the end of the file.
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(len);
+    file.seek(SeekFrom::Start(offset))?;
+    file.by_ref()
+        .take(len as u64)
+        .read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// The error for a header or table [goblin] couldn't parse.
+fn malformed(e: goblin::error::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("malformed executable: {e}"))
+}
+
+/// Read a NUL-terminated name from `bytes`, which may fill them completely.
+fn name_from(bytes: &[u8]) -> String {
+    let name: Vec<u8> = bytes
+        .iter()
+        .copied()
+        .take_while(|b| *b != 0)
+        .collect();
+    String::from_utf8_lossy(&name).to_string()
+}
+
+/// Calculate the entropy of the `size` bytes at `offset`, stopping early at the end of the file.
+///
+/// Returns the entropy and the number of bytes actually read.
+fn range_entropy(file: &mut File, offset: u64, size: u64) -> io::Result<(f64, u64)> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut histogram = ByteHistogram::default();
+    for_each_block(file.by_ref().take(size), SECTION_BLOCK_SIZE, |block| histogram.update(block))?;
+    Ok((histogram.entropy(), histogram.total()))
+}
+
+/// A section or segment, before its contents are read.
+struct Entry {
+    name: String,
+    offset: u64,
+    size: u64,
+}
+
+/// The sections of an executable and where its own headers end.
+///
+/// The `end` field holds the first byte past the headers and tables the format describes itself with, so they aren't mistaken for an overlay.
+struct Layout {
+    entries: Vec<Entry>,
+    end: u64,
+}
+
+/// Parse the section table of a PE executable.
+///
+/// Only the headers and the section table are read. Returns [None] if the file is not a PE executable.
+fn parse_pe(file: &mut File) -> io::Result<Option<Layout>> {
+    let dos = read_at(file, 0, DOS_HEADER_SIZE)?;
+    if dos.len() < DOS_HEADER_SIZE || !dos.starts_with(b"MZ") {
+        return Ok(None);
+    }
+    let pointer = PE_POINTER_OFFSET as usize;
+    let pe_offset = u32::from_le_bytes(dos[pointer..pointer + 4].try_into().unwrap()) as usize;
+    if pe_offset > MAX_TABLE_SIZE {
+        return Ok(None);
+    }
+    let headers = read_at(file, 0, pe_offset + SIZEOF_PE_MAGIC + SIZEOF_COFF_HEADER)?;
+    // Plain DOS executables have no PE header for the DOS header to point to.
+    if DosHeader::parse(&headers).is_err() {
+        return Ok(None);
+    }
+    let coff = CoffHeader::parse(&headers, &mut (pe_offset + SIZEOF_PE_MAGIC)).map_err(malformed)?;
+    let count = (coff.number_of_sections as usize).min(MAX_PE_SECTIONS);
+
+    let table_offset = (pe_offset + SIZEOF_PE_MAGIC + SIZEOF_COFF_HEADER) as u64 + coff.size_of_optional_header as u64;
+    let table = read_at(file, table_offset, count * SIZEOF_SECTION_TABLE)?;
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 0;
+    while at + SIZEOF_SECTION_TABLE <= table.len() {
+        // Executables have no COFF string table, so long names such as `/4` are kept as written.
+        let section = SectionTable::parse(&table, &mut at, table.len()).map_err(malformed)?;
+        entries.push(Entry {
+            name: name_from(&section.name),
+            offset: section.pointer_to_raw_data as u64,
+            size: section.size_of_raw_data as u64,
+        });
+    }
+    Ok(Some(Layout { entries, end: table_offset + (table.len() as u64) }))
+}
+
+/// Name an ELF program header type, e.g. `LOAD`, falling back to its hex value.
+fn elf_segment_type(kind: u32) -> String {
+    match pt_to_str(kind).strip_prefix("PT_") {
+        Some(name) => name.to_string(),
+        None => format!("0x{kind:x}"),
+    }
+}
+
+/// Parse the section headers of an ELF file, falling back to its program headers when there are none.
+///
+/// Sections are named after the section name table, e.g. `.text`. Segments are named after their type and index, e.g. `LOAD[2]`. Only the headers and tables are read. Returns [None] if the file is not an ELF file.
+fn parse_elf(file: &mut File) -> io::Result<Option<Layout>> {
+    let ident = read_at(file, 0, 64)?;
+    if !ident.starts_with(ELFMAG) {
+        return Ok(None);
+    }
+    let header = Elf::parse_header(&ident).map_err(malformed)?;
+    let ctx = Ctx::new(header.container().map_err(malformed)?, header.endianness().map_err(malformed)?);
+    let phnum = (header.e_phnum as usize).min(MAX_ENTRIES);
+    let shnum = (header.e_shnum as usize).min(MAX_ENTRIES);
+    let end = header.e_phoff
+        .saturating_add((header.e_phentsize as usize * phnum) as u64)
+        .max(header.e_shoff.saturating_add((header.e_shentsize as usize * shnum) as u64));
+
+    let section_size = SectionHeader::size(ctx);
+    if shnum > 0 && header.e_shentsize as usize == section_size {
+        let table = read_at(file, header.e_shoff, section_size * shnum)?;
+        let headers = match table.len() / section_size {
+            0 => Vec::new(),
+            count => SectionHeader::parse_from(&table, 0, count, ctx).map_err(malformed)?,
+        };
+        let names = match headers.get(header.e_shstrndx as usize) {
+            Some(names) => read_at(file, names.sh_offset, (names.sh_size as usize).min(MAX_TABLE_SIZE))?,
+            None => Vec::new(),
+        };
+        let names = Strtab::parse(&names, 0, names.len(), 0).map_err(malformed)?;
+        let entries = headers
+            .iter()
+            .enumerate()
+            // The first section header is always empty.
+            .skip(1)
+            .map(|(index, section)| Entry {
+                name: match names.get_at(section.sh_name) {
+                    Some(name) if !name.is_empty() => name.to_string(),
+                    _ => format!("section[{index}]"),
+                },
+                offset: section.sh_offset,
+                size: match section.sh_type == SHT_NOBITS {
+                    true => 0,
+                    false => section.sh_size,
+                },
+            })
+            .collect();
+        return Ok(Some(Layout { entries, end }));
+    }
+
+    let segment_size = ProgramHeader::size(ctx);
+    if header.e_phentsize as usize != segment_size {
+        return Ok(Some(Layout { entries: Vec::new(), end }));
+    }
+    let table = read_at(file, header.e_phoff, segment_size * phnum)?;
+    let entries = ProgramHeader::parse(&table, 0, table.len() / segment_size, ctx)
+        .map_err(malformed)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, segment)| Entry {
+            name: format!("{}[{index}]", elf_segment_type(segment.p_type)),
+            offset: segment.p_offset,
+            size: segment.p_filesz,
+        })
+        .collect();
+    Ok(Some(Layout { entries, end }))
+}
+
+/// Parse the segments of the Mach-O image at `base`, naming each after its segment name with `prefix` in front.
+///
+/// Only the header and load commands are read. Returns [None] if there is no Mach-O image at `base`.
+fn parse_mach_o_image(file: &mut File, base: u64, prefix: &str) -> io::Result<Option<Layout>> {
+    let image = read_at(file, base, SIZEOF_HEADER_64 + MAX_TABLE_SIZE)?;
+    let ctx = match parse_magic_and_ctx(&image, 0) {
+        Ok((_, Some(ctx))) => ctx,
+        _ => {
+            return Ok(None);
+        }
+    };
+    // The load commands are all there is to parse, so lossy parsing skips the symbols and libraries they point to past them.
+    let mach_o = MachO::parse_lossy(&image, 0).map_err(malformed)?;
+    let header_size = match ctx.is_big() {
+        true => SIZEOF_HEADER_64,
+        false => SIZEOF_HEADER_32,
+    };
+    let entries = mach_o.segments
+        .iter()
+        .take(MAX_ENTRIES)
+        .map(|segment| Entry {
+            name: format!("{prefix}{}", name_from(&segment.segname)),
+            offset: base + segment.fileoff,
+            size: segment.filesize,
+        })
+        .collect();
+    Ok(Some(Layout { entries, end: base + (header_size as u64) + (mach_o.header.sizeofcmds as u64) }))
+}
+
+/// Name the CPU of a slice of a universal binary, e.g. `arm64`, falling back to the hex value of its type.
+fn mach_o_cpu_type(arch: &FatArch) -> String {
+    get_arch_name_from_types(arch.cputype(), arch.cpusubtype())
+        .map_or_else(|| format!("0x{:x}", arch.cputype()), str::to_string)
+}
+
+/// Parse the segments of a Mach-O file, or of every slice of a universal binary.
+///
+/// Segments of a universal binary are prefixed with their slice's CPU type, e.g. `arm64:__TEXT`. Returns [None] if the file is not a Mach-O file.
+fn parse_mach_o(file: &mut File) -> io::Result<Option<Layout>> {
+    let header = read_at(file, 0, SIZEOF_FAT_HEADER)?;
+    if header.len() < SIZEOF_FAT_HEADER {
+        return Ok(None);
+    }
+    if peek(&header, 0).map_err(malformed)? != FAT_MAGIC {
+        return parse_mach_o_image(file, 0, "");
+    }
+    // Java class files share the universal magic, but their version puts the "slice count" at 45 or more.
+    let count = FatHeader::parse(&header).map_err(malformed)?.nfat_arch as usize;
+    if count == 0 || count >= 45 {
+        return Ok(None);
+    }
+
+    let table = read_at(file, SIZEOF_FAT_HEADER as u64, count * SIZEOF_FAT_ARCH)?;
+    let mut layout = Layout { entries: Vec::new(), end: (SIZEOF_FAT_HEADER + table.len()) as u64 };
+    for slice in table.chunks_exact(SIZEOF_FAT_ARCH) {
+        let arch = FatArch::parse(slice, 0).map_err(malformed)?;
+        let prefix = format!("{}:", mach_o_cpu_type(&arch));
+        if let Some(image) = parse_mach_o_image(file, arch.offset as u64, &prefix)? {
+            layout.entries.extend(image.entries);
+            layout.end = layout.end.max(image.end);
+        }
+    }
+    Ok(Some(layout))
+}
+
+/// Measure the entropy of each section or segment of the executable at `path`.
+///
+/// Bytes past the end of the last section, and of the executable's own headers, are reported as an `overlay` section. Returns [None] if the file is not a recognised executable, or an error message if it can't be read.
+pub fn file_sections(path: &Path) -> Result<Option<Vec<Section>>, String> {
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let mut file = open_file(path).map_err(error)?;
+    let length = file.metadata().map_err(error)?.len();
+
+    let mut layout = None;
+    for parse in [parse_pe, parse_elf, parse_mach_o] {
+        layout = parse(&mut file).map_err(error)?;
+        if layout.is_some() {
+            break;
+        }
+    }
+    let layout = match layout {
+        Some(layout) => layout,
+        None => {
+            return Ok(None);
+        }
+    };
+
+    let mut sections = Vec::with_capacity(layout.entries.len() + 1);
+    let mut end = layout.end;
+    for entry in layout.entries {
+        // Sections without raw data, such as UPX0 or .bss, take no space in the file.
+        let (entropy, size) = match entry.size {
+            0 => (0.0, 0),
+            size => range_entropy(&mut file, entry.offset, size).map_err(error)?,
+        };
+        end = end.max(entry.offset.saturating_add(size));
+        sections.push(Section {
+            name: entry.name,
+            offset: entry.offset,
+            size,
+            entropy,
+        });
+    }
+    if length > end {
+        let (entropy, size) = range_entropy(&mut file, end, length - end).map_err(error)?;
+        sections.push(Section {
+            name: "overlay".to_string(),
+            offset: end,
+            size,
+            entropy,
+        });
+    }
+    Ok(Some(sections))
+}
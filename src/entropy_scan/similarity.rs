@@ -0,0 +1,87 @@
+//! Contains functions to compare the block-entropy profiles of files.
+//!
+//! The [profile_similarity] function scores how alike two block-entropy profiles are.
+//!
+//! The [rank_by_similarity] function scores a [Vec] of targets against a sample profile and sorts them, most similar first.
+//!
+//! Profiles are built from fixed-size blocks, or from [content-defined chunks](super::cdc) so they stay aligned across files that differ by insertions.
+//!
+//! Profiles are resampled to a fixed number of points before being compared, so files of different sizes can still be scored against each other.
+use std::path::PathBuf;
+
+use super::block_profile;
+use super::cdc::chunk_profile;
+use super::structs::Similarity;
+
+/// The number of points a profile is resampled to before comparison.
+const PROFILE_POINTS: usize = 64;
+
+/// The maximum entropy of a byte, used to normalise profile distances.
+const MAX_ENTROPY: f64 = 8.0;
+
+/// Resample a profile to a fixed number of points.
+///
+/// Each point is the mean of the blocks that fall into it. Profiles shorter than `points` repeat their nearest block.
+fn resample(profile: &[f64], points: usize) -> Vec<f64> {
+    let len = profile.len();
+    (0..points)
+        .map(|i| {
+            let start = (i * len) / points;
+            let end = (((i + 1) * len) / points).max(start + 1);
+            let window = &profile[start..end.min(len)];
+            window.iter().sum::<f64>() / (window.len() as f64)
+        })
+        .collect()
+}
+
+/// Score how similar two block-entropy profiles are.
+///
+/// Returns a value between 0.0 (completely different) and 1.0 (identical) if both profiles are non-empty. Returns [None] otherwise.
+pub fn profile_similarity(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let a = resample(a, PROFILE_POINTS);
+    let b = resample(b, PROFILE_POINTS);
+    let distance: f64 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y).abs())
+        .sum::<f64>() / (PROFILE_POINTS as f64);
+    Some(1.0 - distance / MAX_ENTROPY)
+}
+
+/// Rank a slice of targets by the similarity of their block-entropy profile to `sample`.
+///
+/// With `content_defined`, profiles are built from content-defined chunks averaging `block_size` bytes instead of fixed blocks, and `sample` should be too. Targets that can't be read or are empty are skipped. Returns a [Vec] of [Similarity] structs sorted most similar first, and the number of targets that couldn't be read.
+pub fn rank_by_similarity(
+    sample: &[f64],
+    targets: &[PathBuf],
+    block_size: usize,
+    content_defined: bool
+) -> (Vec<Similarity>, usize) {
+    let mut failed = 0;
+    let mut ranked: Vec<Similarity> = targets
+        .iter()
+        .filter_map(|target| {
+            let profile = match content_defined {
+                true => chunk_profile(target, block_size),
+                false => block_profile(target, block_size),
+            };
+            let profile = profile
+                .map_err(|_| {
+                    failed += 1;
+                })
+                .ok()?;
+            let similarity = profile_similarity(sample, &profile)?;
+            Some(Similarity {
+                path: target.to_owned(),
+                blocks: profile.len(),
+                similarity,
+            })
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap());
+    (ranked, failed)
+}
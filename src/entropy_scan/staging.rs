@@ -0,0 +1,117 @@
+//! Contains the logic for recognising exfiltration staging areas: the trash, temporary directories, shared memory, downloads, and cloud-sync conflict copies.
+//!
+//! The [staging_label] function names the kind of staging area a path sits in, and [prioritize] moves files in staging areas to the top of a scan, archives first.
+//!
+//! The locations scanned by `--staging-locations` are listed in [super::presets::STAGING_LOCATIONS].
+use std::cmp::Ordering;
+use std::env;
+use std::io::Read;
+use std::path::{ Component, Path, PathBuf };
+
+use super::forensic::open_file;
+use super::magic::sniff;
+use super::structs::FileEntropy;
+
+/// The number of bytes read from each staged file to recognise an archive. Covers the `ustar` signature at offset 257.
+const SNIFF_WINDOW: u64 = 512;
+
+/// Signatures of the archive and compression formats data is usually packed into before it leaves.
+const ARCHIVE_NAMES: &[&str] = &["ZIP", "gzip", "tar", "7-Zip", "RAR", "bzip2", "xz", "zstd"];
+
+/// Directories that hold deleted files, compared case-insensitively.
+const TRASH_NAMES: &[&str] = &["$recycle.bin", "recycler", ".trash", ".trashes"];
+
+/// Directories that hold cloud-sync caches and old versions, compared case-insensitively.
+const SYNC_CACHE_NAMES: &[&str] = &[".dropbox.cache", ".stversions"];
+
+/// Markers in the names of cloud-sync conflict copies, e.g. `report (conflicted copy 2024-01-01).zip`, compared case-insensitively.
+const SYNC_CONFLICT_MARKERS: &[&str] = &["conflicted copy", ".sync-conflict-"];
+
+/// Temporary directories, beyond those named by `TMPDIR`, `TEMP`, and `TMP`.
+const TEMP_DIRS: &[&str] = &["/tmp", "/var/tmp", "/private/tmp", "/private/var/tmp"];
+
+/// Shared-memory filesystems, whose files never touch the disk.
+const SHARED_MEMORY_DIRS: &[&str] = &["/dev/shm", "/run/shm"];
+
+/// The temporary directories named by the environment.
+fn temp_env_dirs() -> Vec<PathBuf> {
+    ["TMPDIR", "TEMP", "TMP"]
+        .iter()
+        .filter_map(env::var_os)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Name the kind of staging area `path` sits in: `trash`, `temp`, `shared memory`, `downloads`, or `sync conflict`.
+///
+/// Returns [None] if `path` is not in a staging area.
+pub fn staging_label(path: &Path) -> Option<&'static str> {
+    let names: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let dirs = &names[..names.len().saturating_sub(1)];
+
+    if dirs.iter().any(|name| TRASH_NAMES.contains(&name.as_str())) || dirs.windows(2).any(|pair| pair == ["share", "trash"]) {
+        return Some("trash");
+    }
+    if SHARED_MEMORY_DIRS.iter().any(|dir| path.starts_with(dir)) {
+        return Some("shared memory");
+    }
+    if
+        TEMP_DIRS.iter().any(|dir| path.starts_with(dir)) ||
+        temp_env_dirs().iter().any(|dir| path.starts_with(dir)) ||
+        dirs.windows(3).any(|triple| triple == ["appdata", "local", "temp"])
+    {
+        return Some("temp");
+    }
+    if
+        dirs.iter().any(|name| SYNC_CACHE_NAMES.contains(&name.as_str())) ||
+        SYNC_CONFLICT_MARKERS.iter().any(|marker| file_name.contains(marker))
+    {
+        return Some("sync conflict");
+    }
+    match dirs.iter().any(|name| name == "downloads") {
+        true => Some("downloads"),
+        false => None,
+    }
+}
+
+/// Check whether the file at `path` starts with an archive or compression signature.
+fn is_archive(path: &Path) -> bool {
+    let mut head = Vec::new();
+    let read = open_file(path).and_then(|file| file.take(SNIFF_WINDOW).read_to_end(&mut head));
+    read.is_ok() && sniff(&head).is_some_and(|magic| ARCHIVE_NAMES.contains(&magic.name))
+}
+
+/// Move the files in staging areas to the top of `entropies`, highest entropy first, with archives ahead of everything else.
+///
+/// Files outside staging areas keep their order below them.
+pub fn prioritize(entropies: Vec<FileEntropy>) -> Vec<FileEntropy> {
+    // 0 for staged archives, 1 for other staged files, 2 for the rest.
+    let mut ranked: Vec<(u8, FileEntropy)> = entropies
+        .into_iter()
+        .map(|entropy| {
+            let rank = match entropy.staging.is_some() {
+                true => (!is_archive(&entropy.path)) as u8,
+                false => 2,
+            };
+            (rank, entropy)
+        })
+        .collect();
+    ranked.sort_by(|(a_rank, a), (b_rank, b)| {
+        a_rank.cmp(b_rank).then_with(|| match *a_rank < 2 {
+            true => b.entropy.partial_cmp(&a.entropy).unwrap_or(Ordering::Equal),
+            false => Ordering::Equal,
+        })
+    });
+    ranked
+        .into_iter()
+        .map(|(_, entropy)| entropy)
+        .collect()
+}
@@ -6,8 +6,17 @@
 //!
 //! The [Iqr] struct holds the interquartile range of a [Vec] of [FileEntropy] structs.
 //!
+//! The [ks_distance] and [ks_p_value] functions compare two sets of entropies with the two-sample [Kolmogorov-Smirnov test](https://en.wikipedia.org/wiki/Kolmogorov%E2%80%93Smirnov_test), to tell whether a corpus changed between scans.
+//!
+//! The [created_since] function is used to pick out the files created after a given time.
+//!
 //! The [sort_entropies] function is used to sort a [Vec] of [FileEntropy] structs by entropy.
-use crate::FileEntropy;
+use super::structs::FileEntropy;
+
+/// The fewest files an [interquartile range](interquartile_range) is calculated for.
+///
+/// This is set to 4, one file per quartile.
+pub const IQR_MIN_FILES: usize = 4;
 
 /// Holds the [interquartile range](https://en.wikipedia.org/wiki/Interquartile_range) of a [Vec] of [FileEntropy] structs.
 ///
@@ -25,37 +34,28 @@ pub struct Iqr {
 
 /// Calculate the [interquartile range](https://en.wikipedia.org/wiki/Interquartile_range) of a [Vec] of [FileEntropy] structs.
 ///
-/// Returns the [Iqr] struct if the [Vec] is not empty. Returns [None] if the [Vec] is empty.
+/// Returns the [Iqr] struct if the [Vec] has at least [IQR_MIN_FILES] files. Returns [None] otherwise, as fewer files have no quartiles to speak of.
 pub fn interquartile_range(data: &[FileEntropy]) -> Option<Iqr> {
-    match data.is_empty() {
-        true => None,
-        false => {
-            if data.len() == 1 {
-                return Some(Iqr {
-                    q1: data[0].entropy,
-                    q3: data[0].entropy,
-                    range: 0.0,
-                });
-            }
+    if data.len() < IQR_MIN_FILES {
+        return None;
+    }
 
-            let sorted_data = sort_entropies(data);
-            let len = sorted_data.len();
+    let sorted_data = sort_entropies(data);
+    let len = sorted_data.len();
 
-            let q1_idx = match len % 2 {
-                0 => len / 4,
-                _ => (len + 1) / 4,
-            };
-            let q3_idx = 3 * q1_idx;
-
-            let q1 = sorted_data[q1_idx - 1].entropy;
-            let q3 = sorted_data[q3_idx - 1].entropy;
-            Some(Iqr {
-                q1,
-                q3,
-                range: q3 - q1,
-            })
-        }
-    }
+    let q1_idx = match len % 2 {
+        0 => len / 4,
+        _ => (len + 1) / 4,
+    };
+    let q3_idx = 3 * q1_idx;
+
+    let q1 = sorted_data[q1_idx - 1].entropy;
+    let q3 = sorted_data[q3_idx - 1].entropy;
+    Some(Iqr {
+        q1,
+        q3,
+        range: q3 - q1,
+    })
 }
 
 /// Calculate the mean of a [Vec] of [FileEntropy] structs.
@@ -84,7 +84,7 @@ pub fn median(data: &[FileEntropy]) -> Option<f64> {
             let sorted_data = sort_entropies(data);
             let len = sorted_data.len();
             let mid = len / 2;
-            if len % 2 == 0 {
+            if len.is_multiple_of(2) {
                 let a = sorted_data[mid - 1].entropy;
                 let b = sorted_data[mid].entropy;
                 Some((a + b) / 2.0)
@@ -115,24 +115,77 @@ pub fn variance(data: &[FileEntropy]) -> Option<f64> {
 
 /// Calculate the outliers based on the [IQR](interquartile_range) of a [Vec] of [FileEntropy] structs.
 ///
-/// Returns a [Vec] of [FileEntropy] structs if the [Vec] is not empty. Returns [None] if the [Vec] is empty.
+/// Returns a [Vec] of [FileEntropy] structs if the [Vec] has an interquartile range. Returns [None] if it has too few files for one.
 pub fn entropy_outliers(data: &[FileEntropy]) -> Option<Vec<FileEntropy>> {
-    match data.is_empty() {
-        true => None,
-        false => {
-            let iqr = interquartile_range(data).unwrap();
+    let iqr = interquartile_range(data)?;
 
-            let outliers = data
-                .iter()
-                .filter(
-                    |e|
-                        e.entropy < iqr.q1 - 1.5 * iqr.range || e.entropy > iqr.q3 + 1.5 * iqr.range
-                )
-                .map(|e| e.to_owned())
-                .collect();
-            Some(outliers)
+    let outliers = data
+        .iter()
+        .filter(
+            |e|
+                e.entropy < iqr.q1 - 1.5 * iqr.range || e.entropy > iqr.q3 + 1.5 * iqr.range
+        )
+        .map(|e| e.to_owned())
+        .collect();
+    Some(outliers)
+}
+
+/// Calculate the two-sample Kolmogorov-Smirnov distance between the entropies of `before` and `after`: the largest gap between their cumulative distributions, from 0 for identical distributions to 1 for ones that don't overlap.
+///
+/// Returns the distance as a [f64] if neither slice is empty. Returns [None] otherwise.
+pub fn ks_distance(before: &[FileEntropy], after: &[FileEntropy]) -> Option<f64> {
+    if before.is_empty() || after.is_empty() {
+        return None;
+    }
+    let before = sort_entropies(before);
+    let after = sort_entropies(after);
+    let (n, m) = (before.len() as f64, after.len() as f64);
+    let (mut i, mut j) = (0, 0);
+    let mut distance: f64 = 0.0;
+    while i < before.len() && j < after.len() {
+        let x = before[i].entropy.min(after[j].entropy);
+        while i < before.len() && before[i].entropy <= x {
+            i += 1;
+        }
+        while j < after.len() && after[j].entropy <= x {
+            j += 1;
         }
+        distance = distance.max(((i as f64) / n - (j as f64) / m).abs());
+    }
+    Some(distance)
+}
+
+/// Calculate the approximate p-value of a Kolmogorov-Smirnov `distance` between samples of `n` and `m` files: the chance of a distance at least this large if both samples came from the same distribution.
+///
+/// Uses the asymptotic Kolmogorov distribution with a small-sample correction, so it is a guide rather than exact for a handful of files.
+pub fn ks_p_value(distance: f64, n: usize, m: usize) -> f64 {
+    if n == 0 || m == 0 {
+        return 1.0;
     }
+    let effective = ((n * m) as f64 / (n + m) as f64).sqrt();
+    let lambda = (effective + 0.12 + 0.11 / effective) * distance;
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..=100 {
+        let term = sign * 2.0 * (-2.0 * ((k * k) as f64) * lambda * lambda).exp();
+        sum += term;
+        if term.abs() <= 1e-10 * sum.abs() {
+            return sum.clamp(0.0, 1.0);
+        }
+        sign = -sign;
+    }
+    // The series only fails to converge for tiny distances, which are never significant.
+    1.0
+}
+
+/// Filter a [Vec] of [FileEntropy] structs down to the files created at or after `since`, in seconds since the Unix epoch.
+///
+/// Files without a known creation time are never included.
+pub fn created_since(data: &[FileEntropy], since: u64) -> Vec<FileEntropy> {
+    data.iter()
+        .filter(|e| e.created.is_some_and(|created| created >= since))
+        .map(|e| e.to_owned())
+        .collect()
 }
 
 /// Sort a [Vec] of [FileEntropy] structs by entropy.
@@ -143,3 +196,37 @@ fn sort_entropies(data: &[FileEntropy]) -> Vec<FileEntropy> {
     sorted_data.sort_by(|a, b| a.entropy.partial_cmp(&b.entropy).unwrap());
     sorted_data
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ entropy_outliers, interquartile_range, FileEntropy };
+
+    /// Build one [FileEntropy] per entropy in `entropies`.
+    fn files(entropies: &[f64]) -> Vec<FileEntropy> {
+        entropies
+            .iter()
+            .enumerate()
+            .map(|(i, entropy)| {
+                serde_json::from_value(serde_json::json!({ "path": format!("{i}"), "entropy": entropy, "size": 1 })).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn too_few_files_have_no_interquartile_range() {
+        for entropies in [&[][..], &[4.0], &[1.0, 7.0], &[1.0, 4.0, 7.0]] {
+            assert!(interquartile_range(&files(entropies)).is_none(), "{} files", entropies.len());
+            assert!(entropy_outliers(&files(entropies)).is_none(), "{} files", entropies.len());
+        }
+    }
+
+    #[test]
+    fn interquartile_range_spans_the_middle_files() {
+        let iqr = interquartile_range(&files(&[7.0, 1.0, 3.0, 5.0])).unwrap();
+        assert_eq!(iqr.range, 4.0);
+        let data = files(&[4.0, 4.0, 4.0, 4.0, 8.0, 4.0, 4.0, 4.0]);
+        let outliers = entropy_outliers(&data).unwrap();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].entropy, 8.0);
+    }
+}
@@ -4,42 +4,275 @@
 //!
 //! The `Stats` struct holds the stats for a given target.
 //!
-//! Both structs implement the `Tabled` and `Serialize` traits to be able to print them in a table and JSON format, respectively.
+//! The `Periodicity` struct holds the result of looking for repeating structure in a file.
+//!
+//! The `XorCandidate` struct holds a likely XOR key found by the XOR heuristic.
+//!
+//! The `ScanMeta` struct holds the details that identify a scan in a report.
+//!
+//! The `Similarity` struct holds how closely a file's block-entropy profile matches a sample.
+//!
+//! The `Partition` struct holds the entropy of a partition of a disk or disk image.
+//!
+//! The `RegionMap` and `Region` structs hold a file's region-level entropy map.
+//!
+//! The `Aggregate` struct holds the combined results of a group of files, such as an app bundle.
+//!
+//! The `BaselineDiff` and `FileChange` structs hold how a tree changed since a baseline scan.
+//!
+//! The `JobSummary` and `JobResult` structs hold the outcome of a batch of scans run from a job file.
+//!
+//! All structs implement the `Tabled` and `Serialize` traits to be able to print them in a table and JSON format, respectively.
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::path::{ Path, PathBuf };
 
-use serde::Serialize;
+use serde::{ Deserialize, Serialize, Serializer };
 use tabled::Tabled;
 
+/// Serialize a path as a string, replacing any invalid UTF-8 so non-UTF-8 file names can't abort a report.
+fn serialize_path_lossy<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
 /// Holds info about a given target file.
 ///
 /// The `path` field holds the path to the file.
 ///
 /// The `entropy` field holds the entropy of the file.
 ///
+/// The `size` field holds the size of the file in bytes.
+///
+/// The `low_confidence` field is set when the file is too small for its entropy to be meaningful. Empty files have an entropy of 0.0 and are always low confidence.
+///
+/// The `printable_ratio` field holds the fraction of bytes that are printable ASCII or common whitespace.
+///
+/// The `null_ratio` field holds the fraction of bytes that are 0x00.
+///
+/// The `longest_zero_run` field holds the length of the longest run of 0x00 bytes.
+///
+/// The `container` field names the encrypted container the file is, e.g. `LUKS2 container`, when its signature is recognised.
+///
+/// The `package` and `package_status` fields hold the system package owning the file and whether the file still matches it (`ok`, `modified`, `unowned`, or `unverified`), when package verification was requested.
+///
+/// The `anomaly_score` field holds how far the file is from a baseline scan, when one was given. See [super::anomaly::AnomalyModel::score].
+///
+/// The `staging` field names the exfiltration staging area the file sits in, e.g. `trash` or `temp`, when staging locations were requested. See [super::staging::staging_label].
+///
+/// The `mount_point` field holds the mount point of the filesystem the file is on, when every local filesystem was scanned.
+///
+/// The `link_target` field holds where the file's path points, when the path is a symbolic link.
+///
+/// The `periodicity` field holds the result of the periodicity analysis, when it was requested.
+///
+/// The `xor` field holds a likely XOR key for the file's contents, when the XOR heuristic was requested and found one.
+///
+/// The `sections` field holds the entropy of each section of the file, when it is a recognised executable and section analysis was requested, or `--deep` routed it there.
+///
+/// The `regions` field holds the file's region map, when `--deep` recognised it as a paging or hibernation file. See [super::regions::region_map].
+///
+/// The `archive_members` field holds the members of the file, when it is an archive whose members were scanned, in JSON reports only. Each is a [FileEntropy] of its own, with members of its own if it is a nested archive that was opened. See [super::archive::nest_members].
+///
+/// The `histogram` field holds how often each of the 256 byte values occurs in the file, indexed by value, when it was requested. It is only written to JSON.
+///
+/// The `duration_ms` field holds how long the file took to open and read, in milliseconds, when timings were requested.
+///
+/// The `created` field holds the file's creation (birth) time in seconds since the Unix epoch, where the platform supports it.
+///
 /// The `FileEntropy` struct implements the `Tabled` trait to be able to print it in a table format.
 ///
-/// The `FileEntropy` struct also implements the `Serialize` trait to be able to print it in JSON format.
+/// The `FileEntropy` struct also implements the `Serialize` and `Deserialize` traits to be able to print it in JSON format and read it back from a report.
 ///
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FileEntropy {
+    #[serde(serialize_with = "serialize_path_lossy")]
     pub path: PathBuf,
     pub entropy: f64,
+    pub size: u64,
+    #[serde(default)]
+    pub low_confidence: bool,
+    #[serde(default)]
+    pub printable_ratio: f64,
+    #[serde(default)]
+    pub null_ratio: f64,
+    #[serde(default)]
+    pub longest_zero_run: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anomaly_score: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staging: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount_point: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub periodicity: Option<Periodicity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xor: Option<XorCandidate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sections: Option<Vec<Section>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regions: Option<Vec<Region>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_members: Option<Vec<FileEntropy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Vec<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
 }
 
 impl Tabled for FileEntropy {
-    const LENGTH: usize = 2;
+    const LENGTH: usize = 6;
 
     fn headers() -> Vec<Cow<'static, str>> {
-        vec![Cow::from("PATH"), Cow::from("ENTROPY")]
+        vec![
+            Cow::from("PATH"),
+            Cow::from("ENTROPY"),
+            Cow::from("SIZE"),
+            Cow::from("PRINTABLE"),
+            Cow::from("NULLS"),
+            Cow::from("ZERO RUN")
+        ]
     }
     fn fields(&self) -> Vec<Cow<'_, str>> {
-        vec![Cow::from(self.path.to_str().unwrap()), Cow::from(format!("{:.3}", self.entropy))]
+        vec![
+            self.path.to_string_lossy(),
+            Cow::from(format!("{:.3}", self.entropy)),
+            Cow::from(self.size.to_string()),
+            Cow::from(format!("{:.3}", self.printable_ratio)),
+            Cow::from(format!("{:.3}", self.null_ratio)),
+            Cow::from(self.longest_zero_run.to_string())
+        ]
     }
 }
 
+/// Holds a target that wasn't scanned and why.
+///
+/// The `path` field holds the path to the target.
+///
+/// The `reason` field says why it wasn't scanned, e.g. `Couldn't read file!`.
+///
+/// The `skipped` field is set when the target was left out on purpose, such as for being larger than `--max-file-size` or a cloud-sync placeholder, rather than because it couldn't be read.
+///
+#[derive(Debug, Clone)]
+pub struct Unscanned {
+    pub path: PathBuf,
+    pub reason: String,
+    pub skipped: bool,
+}
+
+/// Holds the result of looking for periodic structure in a file.
+///
+/// The `period` field holds the distance in bytes at which the file repeats itself, if it does.
+///
+/// The `strength` field holds how many times more often than chance bytes repeat at the strongest distance.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Periodicity {
+    pub period: Option<usize>,
+    pub strength: f64,
+}
+
+/// Holds a likely XOR key for a file's contents.
+///
+/// The `key` field holds the key byte.
+///
+/// The `rolling` field is set when the key increments by one with each byte.
+///
+/// The `evidence` field describes why the key was picked, e.g. a decoded file signature.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XorCandidate {
+    pub key: u8,
+    pub rolling: bool,
+    pub evidence: String,
+}
+
+/// Holds the entropy of one section or segment of an executable.
+///
+/// The `name` field holds the section or segment name, e.g. `.text`, `LOAD[2]`, or `__TEXT`, or `overlay` for data appended after the last section.
+///
+/// The `offset` and `size` fields hold where the section's data starts in the file and how many bytes of it the file holds.
+///
+/// The `entropy` field holds the entropy of the section's data.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+    pub entropy: f64,
+}
+
+/// Holds a finding as it is streamed, one line of JSON at a time.
+///
+/// The `scan_id` field holds the unique ID of the scan that found it, so findings from overlapping scans can be told apart.
+///
+/// The `entropy` field holds the finding itself, whose fields sit beside `scan_id`.
+///
+/// The `Finding` struct implements the `Serialize` trait to be able to write it as a line of JSON.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding<'a> {
+    pub scan_id: &'a str,
+    #[serde(flatten)]
+    pub entropy: &'a FileEntropy,
+}
+
+/// Holds the details that identify a scan in a report.
+///
+/// The `scan_id` field holds the unique ID of the scan.
+///
+/// The `seed` field holds the seed used to pick a random sample of targets, if one was taken.
+///
+/// The `symbol_width` field holds the symbol width in bits entropy was measured over, when it isn't the default of 8.
+///
+/// The `duration_ms` field holds how long the whole scan took, target collection included, in milliseconds, when timings were requested.
+///
+/// The `provenance` field holds the binary and arguments that produced the report, for forensic scans.
+///
+/// The `ScanMeta` struct implements the `Serialize` trait so it can head a JSON report.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanMeta {
+    pub scan_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol_width: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Holds what produced a report, as evidence of its integrity.
+///
+/// The `binary` field holds the path of the running binary.
+///
+/// The `sha256` field holds the SHA-256 digest of the binary, as lowercase hex.
+///
+/// The `arguments` field holds the command line, binary name first.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub binary: PathBuf,
+    pub sha256: String,
+    pub arguments: Vec<String>,
+}
+
 /// Holds the stats for a given target.
 ///
+/// The `target` field holds the file or path that was scanned. Several targets are joined like the entries of `PATH`, e.g. `/bin:/sbin`.
+///
 /// The `total` field holds the total number of files scanned.
 ///
 /// The `mean` field holds the mean entropy of the files.
@@ -48,7 +281,7 @@ impl Tabled for FileEntropy {
 ///
 /// The `variance` field holds the variance of the files.
 ///
-/// The `iqr` field holds the interquartile range of the files.
+/// The `iqr` field holds the interquartile range of the files, or [None] if there are too few files for one.
 ///
 /// The `Stats` struct implements the `Tabled` trait to be able to print it in a table format.
 ///
@@ -56,12 +289,13 @@ impl Tabled for FileEntropy {
 ///
 #[derive(Debug, Clone, Serialize)]
 pub struct Stats {
+    #[serde(serialize_with = "serialize_path_lossy")]
     pub target: PathBuf,
     pub total: usize,
     pub mean: f64,
     pub median: f64,
     pub variance: f64,
-    pub iqr: f64,
+    pub iqr: Option<f64>,
 }
 
 impl Tabled for Stats {
@@ -80,12 +314,554 @@ impl Tabled for Stats {
 
     fn fields(&self) -> Vec<Cow<'_, str>> {
         vec![
-            Cow::from(self.target.to_str().unwrap()),
+            self.target.to_string_lossy(),
             Cow::from(self.total.to_string()),
             Cow::from(format!("{:.3}", self.mean)),
             Cow::from(format!("{:.3}", self.median)),
             Cow::from(format!("{:.3}", self.variance)),
-            Cow::from(format!("{:.3}", self.iqr))
+            Cow::from(self.iqr.map_or("n/a".to_string(), |iqr| format!("{iqr:.3}")))
+        ]
+    }
+}
+
+/// Holds how one statistic changed between two scans.
+///
+/// The `metric` field holds the statistic's name, e.g. `mean`.
+///
+/// The `before` and `after` fields hold its value in each scan, and the `change` field holds `after - before`.
+///
+/// The `count` field is set for statistics that count files, which are shown without decimals.
+///
+/// The `StatChange` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `StatChange` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct StatChange {
+    pub metric: &'static str,
+    pub before: f64,
+    pub after: f64,
+    pub change: f64,
+    #[serde(skip)]
+    pub count: bool,
+}
+
+impl Tabled for StatChange {
+    const LENGTH: usize = 4;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![Cow::from("METRIC"), Cow::from("BEFORE"), Cow::from("AFTER"), Cow::from("CHANGE")]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let number = |value: f64| match self.count {
+            true => format!("{value}"),
+            false => format!("{value:.3}"),
+        };
+        vec![
+            Cow::from(self.metric),
+            Cow::from(number(self.before)),
+            Cow::from(number(self.after)),
+            Cow::from(match self.change >= 0.0 {
+                true => format!("+{}", number(self.change)),
+                false => number(self.change),
+            })
+        ]
+    }
+}
+
+/// Holds the comparison of two scan reports.
+///
+/// The `before` and `after` fields hold the paths of the reports compared.
+///
+/// The `changes` field holds how the file count, mean, median, variance, IQR, and outlier count changed. The IQR and outlier count are left out when either report has too few files for quartiles.
+///
+/// The `ks_distance` and `p_value` fields hold the Kolmogorov-Smirnov distance between the two entropy distributions and its p-value.
+///
+/// The `changed` field is set when the p-value is below the significance level asked for, i.e. the corpus changed materially.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub before: PathBuf,
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub after: PathBuf,
+    pub changes: Vec<StatChange>,
+    pub ks_distance: f64,
+    pub p_value: f64,
+    pub changed: bool,
+}
+
+/// Holds how one file differs from a baseline scan.
+///
+/// The `path` field holds the path to the file.
+///
+/// The `change` field holds `new` for a file missing from the baseline, `removed` for one that is no longer there, `wiped` for one that held data but is now empty or a single repeated byte, or `changed` for one whose entropy moved by more than the delta asked for.
+///
+/// The `before` and `after` fields hold the file's entropy in the baseline and now, where it was there.
+///
+/// The `FileChange` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `FileChange` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    pub change: &'static str,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+}
+
+impl Tabled for FileChange {
+    const LENGTH: usize = 4;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![Cow::from("PATH"), Cow::from("CHANGE"), Cow::from("BEFORE"), Cow::from("AFTER")]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let entropy = |value: Option<f64>| match value {
+            Some(value) => Cow::from(format!("{value:.3}")),
+            None => Cow::from("-"),
+        };
+        vec![self.path.to_string_lossy(), Cow::from(self.change), entropy(self.before), entropy(self.after)]
+    }
+}
+
+/// Holds how a tree changed since a baseline scan.
+///
+/// The `baseline` field holds the path of the baseline report.
+///
+/// The `delta` field holds how far a file's entropy had to move to be reported as changed.
+///
+/// The `new`, `removed`, `changed`, `wiped`, and `unchanged` fields count the files of each kind.
+///
+/// The `changes` field holds every new, removed, changed, and wiped file, sorted by path.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineDiff {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub baseline: PathBuf,
+    pub delta: f64,
+    pub new: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub wiped: usize,
+    pub unchanged: usize,
+    pub changes: Vec<FileChange>,
+}
+
+/// Holds the outcome of one target of a batch job.
+///
+/// The `name` field holds the target's name from the job file, or its paths if it has none.
+///
+/// The `status` field holds how its scan ended: `clean`, `findings`, `errors` when some files couldn't be read, or `failed` when the scan couldn't run.
+///
+/// The `reported` field holds the number of files in its report, when the report could be read. Grouped reports count groups.
+///
+/// The `duration_ms` field holds how long its scan took, in milliseconds.
+///
+/// The `JobResult` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `JobResult` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct JobResult {
+    pub name: String,
+    pub status: &'static str,
+    pub reported: Option<usize>,
+    pub duration_ms: u64,
+}
+
+impl Tabled for JobResult {
+    const LENGTH: usize = 4;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![Cow::from("TARGET"), Cow::from("STATUS"), Cow::from("REPORTED"), Cow::from("DURATION (ms)")]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            Cow::from(self.name.as_str()),
+            Cow::from(self.status),
+            Cow::from(self.reported.map(|count| count.to_string()).unwrap_or_else(|| "-".to_string())),
+            Cow::from(self.duration_ms.to_string())
+        ]
+    }
+}
+
+/// Holds the outcome of a batch job.
+///
+/// The `job` field holds the path of the job file.
+///
+/// The `results` field holds the outcome of each target, in the order the job file lists them.
+///
+/// The `suppressed` field holds why notifications were held back, when the job ran during one of its suppression windows.
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSummary {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub job: PathBuf,
+    pub results: Vec<JobResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppressed: Option<String>,
+}
+
+impl JobSummary {
+    /// Count the targets whose scan ended with `status`.
+    pub fn count(&self, status: &str) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.status == status)
+            .count()
+    }
+}
+
+/// Holds how similar a file is to a sample.
+///
+/// The `path` field holds the path to the file.
+///
+/// The `blocks` field holds the number of blocks in the file's entropy profile.
+///
+/// The `similarity` field holds the profile similarity, from 0.0 (completely different) to 1.0 (identical).
+///
+/// The `Similarity` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `Similarity` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct Similarity {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    pub blocks: usize,
+    pub similarity: f64,
+}
+
+impl Tabled for Similarity {
+    const LENGTH: usize = 3;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![Cow::from("PATH"), Cow::from("BLOCKS"), Cow::from("SIMILARITY")]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            self.path.to_string_lossy(),
+            Cow::from(self.blocks.to_string()),
+            Cow::from(format!("{:.3}", self.similarity))
+        ]
+    }
+}
+
+/// Holds the entropy of a partition of a disk or disk image.
+///
+/// The `index` field holds the partition's position in its table, starting at 1.
+///
+/// The `scheme` field holds the partition table the partition came from, `MBR` or `GPT`.
+///
+/// The `start` and `size` fields hold the partition's offset and length in bytes.
+///
+/// The `kind` field holds the partition type from the table. It is serialized as `type`.
+///
+/// The `label` field holds the GPT partition name, if it has one.
+///
+/// The `signature` field holds the volume signature found at the start of the partition, e.g. `NTFS` or `LUKS`.
+///
+/// The `entropy` and `min_entropy` fields hold the mean and lowest entropy of the partition's blocks.
+///
+/// The `likely_encrypted` field is set when every block looks random or the signature is an encrypted container.
+///
+/// The `blocks` field holds the entropy of each block of the partition. It is not shown in tables.
+///
+/// The `Partition` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `Partition` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct Partition {
+    pub index: usize,
+    pub scheme: &'static str,
+    pub start: u64,
+    pub size: u64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub label: Option<String>,
+    pub signature: Option<&'static str>,
+    pub entropy: f64,
+    pub min_entropy: f64,
+    pub likely_encrypted: bool,
+    pub blocks: Vec<f64>,
+}
+
+impl Tabled for Partition {
+    const LENGTH: usize = 9;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![
+            Cow::from("#"),
+            Cow::from("TYPE"),
+            Cow::from("LABEL"),
+            Cow::from("START"),
+            Cow::from("SIZE"),
+            Cow::from("SIGNATURE"),
+            Cow::from("ENTROPY"),
+            Cow::from("MIN ENTROPY"),
+            Cow::from("ENCRYPTED?")
+        ]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            Cow::from(self.index.to_string()),
+            Cow::from(self.kind.as_str()),
+            Cow::from(self.label.as_deref().unwrap_or_default()),
+            Cow::from(self.start.to_string()),
+            Cow::from(self.size.to_string()),
+            Cow::from(self.signature.unwrap_or_default()),
+            Cow::from(format!("{:.3}", self.entropy)),
+            Cow::from(format!("{:.3}", self.min_entropy)),
+            Cow::from(if self.likely_encrypted { "likely" } else { "" })
+        ]
+    }
+}
+
+/// Holds the region-level entropy map of a file.
+///
+/// The `path` field holds the path to the file.
+///
+/// The `kind` field names the paging or hibernation file the file is, judging by its name, e.g. `Windows page file`.
+///
+/// The `signature` field holds the header signature found at the start of the file, e.g. `Windows hibernation file`.
+///
+/// The `size` field holds the number of bytes read.
+///
+/// The `regions` field holds the file's regions in order. It is shown in its own table.
+///
+/// The `RegionMap` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `RegionMap` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct RegionMap {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    pub kind: Option<&'static str>,
+    pub signature: Option<&'static str>,
+    pub size: u64,
+    pub regions: Vec<Region>,
+}
+
+impl Tabled for RegionMap {
+    const LENGTH: usize = 5;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![
+            Cow::from("PATH"),
+            Cow::from("KIND"),
+            Cow::from("SIGNATURE"),
+            Cow::from("SIZE"),
+            Cow::from("REGIONS")
+        ]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            self.path.to_string_lossy(),
+            Cow::from(self.kind.unwrap_or_default()),
+            Cow::from(self.signature.unwrap_or_default()),
+            Cow::from(self.size.to_string()),
+            Cow::from(self.regions.len().to_string())
+        ]
+    }
+}
+
+/// Holds a run of neighbouring blocks of a file with the same class.
+///
+/// The `start` and `length` fields hold the region's offset and length in bytes.
+///
+/// The `class` field holds the class of the region's blocks: `zero`, `low`, `mixed`, or `high`.
+///
+/// The `entropy` field holds the mean entropy of the region's blocks.
+///
+/// The `Region` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `Region` struct also implements the `Serialize` and `Deserialize` traits to be able to print it in JSON format and read it back as part of a [FileEntropy].
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Region {
+    pub start: u64,
+    pub length: u64,
+    pub class: String,
+    pub entropy: f64,
+}
+
+impl Tabled for Region {
+    const LENGTH: usize = 4;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![Cow::from("START"), Cow::from("LENGTH"), Cow::from("CLASS"), Cow::from("ENTROPY")]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            Cow::from(self.start.to_string()),
+            Cow::from(self.length.to_string()),
+            Cow::from(self.class.as_str()),
+            Cow::from(format!("{:.3}", self.entropy))
+        ]
+    }
+}
+
+/// Holds the entropy of a single window of a file.
+///
+/// The `offset` and `length` fields hold where the window starts and how many bytes it covers.
+///
+/// The `entropy` field holds the entropy of the bytes in the window.
+///
+/// The `Window` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `Window` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct Window {
+    pub offset: u64,
+    pub length: u64,
+    pub entropy: f64,
+}
+
+impl Tabled for Window {
+    const LENGTH: usize = 3;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![Cow::from("OFFSET"), Cow::from("LENGTH"), Cow::from("ENTROPY")]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            Cow::from(self.offset.to_string()),
+            Cow::from(self.length.to_string()),
+            Cow::from(format!("{:.3}", self.entropy))
+        ]
+    }
+}
+
+/// Holds the entropy of data found through an NTFS volume's master file table.
+///
+/// The `record` field holds the number of the file record the data belongs to.
+///
+/// The `name` field holds the file's name, `name:stream` for a named data stream, or `#record` for a record without a name.
+///
+/// The `kind` field holds where the data is: `resident` for data stored inside the file record, `slack` for the unused tail of the file's last cluster, or `unallocated` for a run of clusters no file owns, named `clusters first-last`.
+///
+/// The `offset` and `length` fields hold where the data is in the image and how many bytes it covers.
+///
+/// The `entropy` field holds the entropy of the data.
+///
+/// The `MftData` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `MftData` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct MftData {
+    pub record: u64,
+    pub name: String,
+    pub kind: &'static str,
+    pub offset: u64,
+    pub length: u64,
+    pub entropy: f64,
+}
+
+impl Tabled for MftData {
+    const LENGTH: usize = 6;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![
+            Cow::from("RECORD"),
+            Cow::from("NAME"),
+            Cow::from("KIND"),
+            Cow::from("OFFSET"),
+            Cow::from("LENGTH"),
+            Cow::from("ENTROPY")
+        ]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            Cow::from(self.record.to_string()),
+            Cow::from(self.name.as_str()),
+            Cow::from(self.kind),
+            Cow::from(self.offset.to_string()),
+            Cow::from(self.length.to_string()),
+            Cow::from(format!("{:.3}", self.entropy))
+        ]
+    }
+}
+
+/// Holds the combined results of a group of files, such as an app bundle.
+///
+/// The `path` field holds the path to the group, e.g. the bundle directory, or to the file for a group of one.
+///
+/// The `kind` field holds what the group is, e.g. `app bundle` or `file`.
+///
+/// The `files` and `size` fields hold the number of files in the group and their total size in bytes.
+///
+/// The `max_entropy` and `mean_entropy` fields hold the highest and mean entropy of the group's files.
+///
+/// The `mach_o` field holds the number of Mach-O binaries in the group.
+///
+/// The `signed` field holds whether an app bundle carries a code signature. It is [None] for other groups.
+///
+/// The `Aggregate` struct implements the `Tabled` trait to be able to print it in a table format.
+///
+/// The `Aggregate` struct also implements the `Serialize` trait to be able to print it in JSON format.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct Aggregate {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    pub kind: &'static str,
+    pub files: usize,
+    pub size: u64,
+    pub max_entropy: f64,
+    pub mean_entropy: f64,
+    pub mach_o: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed: Option<bool>,
+}
+
+impl Tabled for Aggregate {
+    const LENGTH: usize = 8;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        vec![
+            Cow::from("PATH"),
+            Cow::from("KIND"),
+            Cow::from("FILES"),
+            Cow::from("SIZE"),
+            Cow::from("MAX ENTROPY"),
+            Cow::from("MEAN ENTROPY"),
+            Cow::from("MACH-O"),
+            Cow::from("SIGNED")
+        ]
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            self.path.to_string_lossy(),
+            Cow::from(self.kind),
+            Cow::from(self.files.to_string()),
+            Cow::from(self.size.to_string()),
+            Cow::from(format!("{:.3}", self.max_entropy)),
+            Cow::from(format!("{:.3}", self.mean_entropy)),
+            Cow::from(self.mach_o.to_string()),
+            Cow::from(
+                match self.signed {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "",
+                }
+            )
         ]
     }
 }
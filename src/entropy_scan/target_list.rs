@@ -0,0 +1,48 @@
+//! Contains the logic for reading lists of targets chosen by other tools.
+//!
+//! The [read_target_list] function parses newline- or NUL-delimited paths, such as the output of `find -print0`, so the filesystem isn't walked again to find them.
+use std::io::{ self, Read };
+use std::path::PathBuf;
+
+use super::filters::TargetFilter;
+
+/// Turn one entry of a target list into a path, keeping non-UTF-8 names intact where the platform allows.
+#[cfg(unix)]
+fn entry_path(entry: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(entry))
+}
+
+/// Turn one entry of a target list into a path.
+#[cfg(not(unix))]
+fn entry_path(entry: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(entry).as_ref())
+}
+
+/// Read the paths `reader` lists, one per line, or separated by NUL bytes if `null` is set.
+///
+/// Empty entries and directories are left out, since the list already names the files wanted. Other paths are kept if `filter` accepts them, including ones that don't exist, so they are reported when scanned.
+///
+/// Returns the paths in the order listed, or an error if `reader` fails.
+pub fn read_target_list<R: Read>(mut reader: R, null: bool, filter: &TargetFilter) -> io::Result<Vec<PathBuf>> {
+    let mut list = Vec::new();
+    reader.read_to_end(&mut list)?;
+    let separator = match null {
+        true => 0,
+        false => b'\n',
+    };
+    let targets = list
+        .split(|b| *b == separator)
+        .map(|entry| match null {
+            true => entry,
+            false => entry.strip_suffix(b"\r").unwrap_or(entry),
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(entry_path)
+        .filter(|path| !path.is_dir())
+        .filter(|path| filter.accepts(path))
+        .collect();
+    Ok(targets)
+}
@@ -0,0 +1,83 @@
+//! Contains the logic for holding a scan back while the machine is busy or running on battery, for the `--max-load` and `--pause-on-battery` options.
+//!
+//! The [throttle] function blocks a worker until the one-minute [load_average] is at or below the limit and, if asked, the machine is no longer [on_battery], checking again every [POLL_INTERVAL].
+//!
+//! Both are read from `/proc` and `/sys` on Linux. Elsewhere they can't be read, so scans are never held back.
+use std::thread;
+use std::time::Duration;
+
+/// How long a paused worker waits before checking the load and battery again.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The Linux file holding the load averages.
+#[cfg(target_os = "linux")]
+const LOADAVG_FILE: &str = "/proc/loadavg";
+
+/// The Linux directory listing batteries and mains adapters.
+#[cfg(target_os = "linux")]
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Read the one-minute load average from text in the `/proc/loadavg` format, e.g. `0.52 0.58 0.59 1/467 12345`.
+pub fn parse_load_average(text: &str) -> Option<f64> {
+    text.split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Read the one-minute load average of this host, or [None] if it can't be read.
+#[cfg(target_os = "linux")]
+pub fn load_average() -> Option<f64> {
+    parse_load_average(&std::fs::read_to_string(LOADAVG_FILE).ok()?)
+}
+
+/// Read the one-minute load average of this host. Only Linux's can be read, so this is always [None].
+#[cfg(not(target_os = "linux"))]
+pub fn load_average() -> Option<f64> {
+    None
+}
+
+/// Check whether this host is running on battery: whether any of its batteries is discharging.
+#[cfg(target_os = "linux")]
+pub fn on_battery() -> bool {
+    use std::fs;
+
+    let Ok(supplies) = fs::read_dir(POWER_SUPPLY_DIR) else {
+        return false;
+    };
+    supplies
+        .filter_map(|supply| supply.ok())
+        .any(|supply| {
+            let read = |name: &str| fs::read_to_string(supply.path().join(name)).unwrap_or_default();
+            read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+        })
+}
+
+/// Check whether this host is running on battery. Only Linux's power supplies can be read, so this is always `false`.
+#[cfg(not(target_os = "linux"))]
+pub fn on_battery() -> bool {
+    false
+}
+
+/// Block until the load average is at or below `max_load`, if given, and, if `pause_on_battery` is set, the host is on mains power.
+pub fn throttle(max_load: Option<f64>, pause_on_battery: bool) {
+    let busy = || {
+        max_load.is_some_and(|max_load| load_average().is_some_and(|load| load > max_load)) ||
+            (pause_on_battery && on_battery())
+    };
+    while busy() {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_load_average;
+
+    #[test]
+    fn load_average_is_the_first_field() {
+        assert_eq!(parse_load_average("2.50 1.75 0.90 3/512 4242\n"), Some(2.5));
+        assert_eq!(parse_load_average(""), None);
+        assert_eq!(parse_load_average("busy"), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Contains helpers to parse and format human-friendly units.
+//!
+//! The [parse_duration] function turns strings like `48h` or `7d` into a [Duration].
+//!
+//! The [parse_date] function reads a UTC date such as `2024-01-01`, and [parse_time_bound] reads either a date or a duration before now, such as `24h`. The [parse_daily_window] function reads a time-of-day range such as `01:00-03:30`.
+//!
+//! The [parse_size] function reads a file size such as `10K` or `2G`. The [parse_min_size] function reads a minimum file size, including `auto`, and [parse_max_size] a maximum one, including `unlimited`.
+//!
+//! The [format_size], [format_count], and [format_duration] functions render numbers for humans, e.g. `1.4 GiB`, `1,234,567`, and `3m12s`.
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+use super::LOW_CONFIDENCE_SIZE;
+
+/// Parse a duration such as `90s`, `30m`, `48h`, `7d`, or `2w`.
+///
+/// A bare number is treated as seconds. Returns the [Duration] or an error message suitable for `clap`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration: {value}"))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!("Invalid duration unit: {unit}"));
+        }
+    };
+    number
+        .checked_mul(multiplier)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("Duration too long: {value}"))
+}
+
+/// Count the days from 1970-01-01 to the given date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Count the days in the given month of the proleptic Gregorian calendar.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parse a UTC date such as `2024-01-01`, optionally with a time such as `2024-01-01T08:30:00Z`.
+///
+/// Returns the [SystemTime] or an error message suitable for `clap`.
+pub fn parse_date(value: &str) -> Result<SystemTime, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid date: {value} (expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS)");
+    let (date, time) = value
+        .trim_end_matches('Z')
+        .split_once(['T', ' '])
+        .unwrap_or((value, "00:00:00"));
+    let numbers = |text: &str, separator: char| -> Option<Vec<i64>> {
+        text.split(separator)
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+    let (Some(date), Some(time)) = (numbers(date, '-'), numbers(time, ':')) else {
+        return Err(invalid());
+    };
+    let (&[year, month, day], &[hour, minute, second]) = (date.as_slice(), time.as_slice()) else {
+        return Err(invalid());
+    };
+    if
+        !(1..=9999).contains(&year) ||
+        !(1..=12).contains(&month) ||
+        !(1..=days_in_month(year, month)).contains(&day) ||
+        !(0..=23).contains(&hour) ||
+        !(0..=59).contains(&minute) ||
+        !(0..=60).contains(&second)
+    {
+        return Err(invalid());
+    }
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    let seconds = u64::try_from(seconds).map_err(|_| format!("Invalid date: {value} (before 1970)"))?;
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Parse a point in time: a UTC date as read by [parse_date], or a duration before now as read by [parse_duration], e.g. `24h`.
+///
+/// Returns the [SystemTime] or an error message suitable for `clap`.
+pub fn parse_time_bound(value: &str) -> Result<SystemTime, String> {
+    if value.contains('-') {
+        return parse_date(value);
+    }
+    let ago = parse_duration(value)?;
+    SystemTime::now()
+        .checked_sub(ago)
+        .ok_or_else(|| format!("Invalid time: {value} (too long ago)"))
+}
+
+/// Parse a daily window of UTC time such as `01:00-03:30`, which may wrap past midnight, e.g. `22:00-02:00`.
+///
+/// Returns the start and end as seconds since midnight, or an error message.
+pub fn parse_daily_window(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid daily window: {value} (expected HH:MM-HH:MM)");
+    let time = |text: &str| -> Option<u32> {
+        let (hour, minute) = text.trim().split_once(':')?;
+        let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+        (hour < 24 && minute < 60).then_some(hour * 3600 + minute * 60)
+    };
+    let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+    match (time(start), time(end)) {
+        (Some(start), Some(end)) => Ok((start, end)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse a minimum file size in bytes, with units like [parse_size].
+///
+/// `auto` picks the smallest size with a meaningful entropy, see [LOW_CONFIDENCE_SIZE]. Returns the size or an error message suitable for `clap`.
+pub fn parse_min_size(value: &str) -> Result<u64, String> {
+    match value.trim() {
+        "auto" => Ok(LOW_CONFIDENCE_SIZE),
+        value => parse_size(value),
+    }
+}
+
+/// Parse a maximum file size in bytes, with units like [parse_size].
+///
+/// `unlimited` is [u64::MAX], which no file exceeds. Returns the size or an error message suitable for `clap`.
+pub fn parse_max_size(value: &str) -> Result<u64, String> {
+    match value.trim() {
+        "unlimited" => Ok(u64::MAX),
+        value => parse_size(value),
+    }
+}
+
+/// Parse a file size such as `4096`, `64K`, `512M`, `2G`, or `1T`. Units are binary, so `1K` is 1024 bytes.
+///
+/// Returns the size or an error message suitable for `clap`.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size: {value}"))?;
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => {
+            return Err(format!("Invalid size unit: {unit}"));
+        }
+    };
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("Size too large: {value}"))
+}
+
+/// Format a size in bytes with binary units, e.g. `1.4 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+/// Format a duration with the largest units that fit, e.g. `0.4ms`, `12.3s`, `3m12s`, `2h05m`, or `3d04h`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0 => format!("{:.1}ms", duration.as_secs_f64() * 1000.0),
+        1..60 => format!("{:.1}s", duration.as_secs_f64()),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        3600..86400 => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+        _ => format!("{}d{:02}h", seconds / 86400, seconds % 86400 / 3600),
+    }
+}
+
+/// Format a count with thousands separators, e.g. `1,234,567`.
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{ Duration, UNIX_EPOCH };
+
+    use super::{ format_duration, parse_date, parse_duration };
+
+    #[test]
+    fn durations_too_long_to_hold_are_refused() {
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("999999999999999999w"), Err("Duration too long: 999999999999999999w".to_string()));
+        assert!(parse_duration("18446744073709551615").is_ok());
+        assert!(parse_duration("18446744073709551615m").is_err());
+    }
+
+    #[test]
+    fn dates_must_exist() {
+        assert_eq!(parse_date("1970-01-02"), Ok(UNIX_EPOCH + Duration::from_secs(86400)));
+        assert!(parse_date("2024-02-29").is_ok());
+        assert!(parse_date("2000-02-29").is_ok());
+        for date in ["2024-02-30", "2024-02-31", "2023-02-29", "1900-02-29", "2024-04-31", "2024-13-01", "2024-01-00"] {
+            assert!(parse_date(date).is_err(), "{date}");
+        }
+        assert!(parse_date("2024-01-01T24:00:00Z").is_err());
+        assert!(parse_date("2024-01-01T-1:00:00Z").is_err());
+    }
+
+    #[test]
+    fn durations_use_the_largest_units_that_fit() {
+        assert_eq!(format_duration(Duration::from_micros(400)), "0.4ms");
+        assert_eq!(format_duration(Duration::from_millis(12_345)), "12.3s");
+        assert_eq!(format_duration(Duration::from_secs(192)), "3m12s");
+        assert_eq!(format_duration(Duration::from_secs(2 * 3600 + 5 * 60 + 59)), "2h05m");
+        assert_eq!(format_duration(Duration::from_secs(3 * 86400 + 4 * 3600)), "3d04h");
+    }
+}
@@ -0,0 +1,79 @@
+//! Contains the logic for sliding-window entropy over a single file.
+//!
+//! The [sliding_entropy] function streams a file once, keeping a [ByteHistogram] of the bytes inside the current window, and reports the entropy of every window as a [Window].
+//!
+//! The [sliding_entropy_of] function does the same for any reader, such as bytes already in memory, so it needs no filesystem.
+//!
+//! Overlapping windows (a stride shorter than the window) pinpoint where an encrypted or compressed payload starts inside an otherwise low-entropy binary.
+use std::collections::VecDeque;
+use std::io::{ self, Read };
+use std::path::Path;
+
+use super::for_each_block;
+use super::forensic::open_file;
+use super::histogram::ByteHistogram;
+use super::structs::Window;
+
+/// The block size the file is read in. Windows don't have to line up with it.
+const READ_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Calculate the entropy of every `window`-byte window of the file at `path`, starting every `stride` bytes.
+///
+/// Only whole windows are reported, except that a file shorter than one window is reported as a single window over the whole file.
+///
+/// Returns a [Vec] of [Window]s, or an error message if the file can't be read or `window` or `stride` is zero.
+pub fn sliding_entropy(path: &Path, window: usize, stride: usize) -> Result<Vec<Window>, String> {
+    if window == 0 || stride == 0 {
+        return Err("Window and stride must be greater than zero".to_string());
+    }
+    let error = |e: io::Error| format!("Couldn't read {}: {e}", path.to_string_lossy());
+    let file = open_file(path).map_err(error)?;
+    sliding_entropy_of(file, window, stride).map_err(error)
+}
+
+/// Calculate the entropy of every `window`-byte window of everything `reader` yields, starting every `stride` bytes.
+///
+/// Windows are reported as by [sliding_entropy]. Returns an error if `reader` fails, or if `window` or `stride` is zero.
+pub fn sliding_entropy_of<R: Read>(reader: R, window: usize, stride: usize) -> io::Result<Vec<Window>> {
+    if window == 0 || stride == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "window and stride must be greater than zero"));
+    }
+
+    let mut histogram = ByteHistogram::default();
+    let mut buffer: VecDeque<u8> = VecDeque::with_capacity(window + 1);
+    let mut windows = Vec::new();
+    let mut total = 0u64;
+    // The offset the next window ends at.
+    let mut next_end = window as u64;
+    for_each_block(reader, READ_BLOCK_SIZE, |mut block| {
+        while !block.is_empty() {
+            let take = (next_end - total).min(block.len() as u64) as usize;
+            let (head, rest) = block.split_at(take);
+            histogram.update(head);
+            buffer.extend(head);
+            if buffer.len() > window {
+                let excess: Vec<u8> = buffer.drain(..buffer.len() - window).collect();
+                histogram.remove(&excess);
+            }
+            total += take as u64;
+            if total == next_end {
+                windows.push(Window {
+                    offset: total - (window as u64),
+                    length: window as u64,
+                    entropy: histogram.entropy(),
+                });
+                next_end += stride as u64;
+            }
+            block = rest;
+        }
+    })?;
+
+    if windows.is_empty() && total > 0 {
+        windows.push(Window {
+            offset: 0,
+            length: total,
+            entropy: histogram.entropy(),
+        });
+    }
+    Ok(windows)
+}
@@ -0,0 +1,105 @@
+//! Contains the logic for wiping file contents from memory once they have been measured.
+//!
+//! The [Wiped] buffer has a fixed capacity: it never reallocates, so no copy of its contents is ever left behind in freed memory. It is zeroed with [zeroize] when dropped, however that happens, spare capacity included.
+//!
+//! Scans run with `no_persist` in [super::options::ScanOptions] wipe every buffer that held file contents, for material under legal hold or classified handling rules.
+use std::io::{ self, Read };
+use std::ops::{ Deref, DerefMut };
+
+use zeroize::Zeroize;
+
+/// The size of the blocks [Wiped::read_from] reads in.
+const READ_BLOCK_SIZE: usize = 8192;
+
+/// Holds a byte buffer of fixed capacity that is wiped when dropped, if `enabled`.
+///
+/// It only dereferences to the bytes filled so far, as a slice, so it can't be grown past its capacity. Filling it beyond that is an error, rather than a reallocation that would free the old contents unwiped.
+///
+pub struct Wiped {
+    bytes: Vec<u8>,
+    enabled: bool,
+}
+
+impl Wiped {
+    /// Allocate an empty buffer for `capacity` bytes, wiping them when dropped if `enabled` is set.
+    pub fn with_capacity(capacity: usize, enabled: bool) -> Self {
+        Wiped { bytes: Vec::with_capacity(capacity), enabled }
+    }
+
+    /// Allocate a buffer of `length` zeros, wiping them when dropped if `enabled` is set.
+    pub fn zeroed(length: usize, enabled: bool) -> Self {
+        Wiped { bytes: vec![0; length], enabled }
+    }
+
+    /// The number of bytes that can still be added.
+    pub fn remaining(&self) -> usize {
+        self.bytes.capacity() - self.bytes.len()
+    }
+
+    /// Append `bytes`.
+    ///
+    /// Returns an error, adding nothing, if they don't fit in the [remaining](Wiped::remaining) capacity.
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if bytes.len() > self.remaining() {
+            return Err(overflow());
+        }
+        self.bytes.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Append everything `reader` yields.
+    ///
+    /// Returns an error if `reader` fails or yields more than the [remaining](Wiped::remaining) capacity. What was read is kept, to be wiped.
+    pub fn read_from<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut block = Wiped::zeroed(READ_BLOCK_SIZE, self.enabled);
+        loop {
+            let read = reader.read(&mut block)?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.extend_from_slice(&block[..read])?;
+        }
+    }
+
+    /// Hand the underlying [Vec] to `fill`, along with how many bytes it may hold in all.
+    ///
+    /// `fill` must not add more than that many, which would reallocate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fill` reallocated the buffer.
+    pub fn fill_with<T>(&mut self, fill: impl FnOnce(&mut Vec<u8>, usize) -> T) -> T {
+        let capacity = self.bytes.capacity();
+        let filled = fill(&mut self.bytes, capacity);
+        assert_eq!(self.bytes.capacity(), capacity, "a wiped buffer was reallocated");
+        filled
+    }
+}
+
+/// The error for filling a [Wiped] buffer past its capacity.
+fn overflow() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "more data than its size says")
+}
+
+impl Deref for Wiped {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for Wiped {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl Drop for Wiped {
+    fn drop(&mut self) {
+        if self.enabled {
+            // Zeroizes the spare capacity as well as the bytes in use.
+            self.bytes.zeroize();
+        }
+    }
+}
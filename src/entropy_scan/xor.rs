@@ -0,0 +1,113 @@
+//! Contains a heuristic decoder for data obfuscated with a simple XOR key.
+//!
+//! The [try_xor] function tries every single-byte key, and every single-byte key that increments with each byte ("rolling"), looking for a decoded file signature or a jump in readable text.
+use super::magic::sniff;
+use super::metrics::is_printable;
+use super::structs::XorCandidate;
+
+/// The number of bytes from the start of a file that are decoded.
+const XOR_WINDOW: usize = 4096;
+
+/// The printable fraction a decoding must reach to count as text.
+const MIN_PRINTABLE: f64 = 0.95;
+
+/// The fraction of letters, digits, and spaces a decoding must reach to count as text.
+const MIN_TEXT: f64 = 0.75;
+
+/// How much the fraction of letters, digits, and spaces must rise over the original for a decoding to count.
+const MIN_TEXT_GAIN: f64 = 0.25;
+
+/// The fewest distinct byte values worth decoding. Runs of one repeated byte decode to anything.
+const MIN_DISTINCT_BYTES: usize = 8;
+
+/// Calculate the fraction of `bytes` that are printable. An empty slice is 0.0.
+fn printable_ratio(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let printable = bytes
+        .iter()
+        .filter(|b| is_printable(**b))
+        .count();
+    (printable as f64) / (bytes.len() as f64)
+}
+
+/// Calculate the fraction of `bytes` that are ASCII letters, digits, or spaces. An empty slice is 0.0.
+fn text_ratio(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let text = bytes
+        .iter()
+        .filter(|b| b.is_ascii_alphanumeric() || **b == b' ')
+        .count();
+    (text as f64) / (bytes.len() as f64)
+}
+
+/// Count the distinct byte values in `bytes`.
+fn distinct_bytes(bytes: &[u8]) -> usize {
+    let mut seen = [false; 256];
+    for byte in bytes {
+        seen[*byte as usize] = true;
+    }
+    seen.iter()
+        .filter(|s| **s)
+        .count()
+}
+
+/// Decode `bytes` with `key`, incrementing the key with each byte when `rolling` is set.
+fn decode(bytes: &[u8], key: u8, rolling: bool) -> Vec<u8> {
+    bytes
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            let step = if rolling { i as u8 } else { 0 };
+            b ^ key.wrapping_add(step)
+        })
+        .collect()
+}
+
+/// Try simple XOR keys against the start of `bytes`.
+///
+/// A decoding that reveals a known file signature wins outright. Otherwise the decoding with the most letters, digits, and spaces is returned if it is mostly printable text and clearly more readable than the original. Returns [None] if no key stands out.
+pub fn try_xor(bytes: &[u8]) -> Option<XorCandidate> {
+    let window = &bytes[..bytes.len().min(XOR_WINDOW)];
+    if distinct_bytes(window) < MIN_DISTINCT_BYTES || sniff(window).is_some() {
+        return None;
+    }
+
+    let original = text_ratio(window);
+    let mut best: Option<(XorCandidate, f64)> = None;
+    for rolling in [false, true] {
+        for key in 0..=u8::MAX {
+            if key == 0 && !rolling {
+                continue;
+            }
+            let decoded = decode(window, key, rolling);
+            if let Some(magic) = sniff(&decoded) {
+                return Some(XorCandidate {
+                    key,
+                    rolling,
+                    evidence: format!("{} signature", magic.name),
+                });
+            }
+
+            let text = text_ratio(&decoded);
+            if best.as_ref().is_none_or(|(_, best_text)| text > *best_text) {
+                if printable_ratio(&decoded) < MIN_PRINTABLE {
+                    continue;
+                }
+                let candidate = XorCandidate {
+                    key,
+                    rolling,
+                    evidence: format!("{:.0}% text", text * 100.0),
+                };
+                best = Some((candidate, text));
+            }
+        }
+    }
+
+    best.filter(|(_, text)| *text >= MIN_TEXT && *text - original >= MIN_TEXT_GAIN).map(
+        |(candidate, _)| candidate
+    )
+}
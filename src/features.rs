@@ -0,0 +1,179 @@
+//! Contains the logic for `scan --features-out`: writing each scanned file's [Features] to a Parquet file, for training classifiers on scan output.
+//!
+//! The table has one row per file and one column per feature, with the file's [type class](entropyscan::entropy_scan::features::type_class) one-hot encoded as `type_<class>` columns of 0 or 1. Rows are written in row groups of [ROW_GROUP_SIZE], so memory use doesn't grow with the number of files.
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use parquet::basic::Compression;
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+
+use crate::entropy_scan::features::{ Features, TYPE_CLASSES };
+
+/// The number of rows held before they are written out as a row group.
+pub const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// A column of the feature table and how to read its value from a row.
+enum Column {
+    Text(&'static str, fn(&Features) -> String),
+    Double(&'static str, fn(&Features) -> f64),
+    Long(&'static str, fn(&Features) -> i64),
+    OneHot(&'static str),
+}
+
+impl Column {
+    /// The column's name in the table.
+    fn name(&self) -> String {
+        match self {
+            Column::Text(name, _) | Column::Double(name, _) | Column::Long(name, _) => name.to_string(),
+            Column::OneHot(class) => format!("type_{class}"),
+        }
+    }
+
+    /// The column's Parquet type, as written in a schema.
+    fn parquet_type(&self) -> &'static str {
+        match self {
+            Column::Text(..) => "binary",
+            Column::Double(..) => "double",
+            Column::Long(..) => "int64",
+            Column::OneHot(_) => "int32",
+        }
+    }
+}
+
+/// The columns of the feature table, in order.
+fn columns() -> Vec<Column> {
+    let mut columns = vec![
+        Column::Text("path", |row| row.path.to_string_lossy().to_string()),
+        Column::Double("entropy", |row| row.entropy),
+        Column::Long("size", |row| row.size as i64),
+        Column::Double("printable_ratio", |row| row.printable_ratio),
+        Column::Double("chi_square", |row| row.chi_square),
+        Column::Long("chunk_size", |row| row.chunk_size as i64),
+        Column::Long("chunks", |row| row.chunks as i64),
+        Column::Double("chunk_entropy_mean", |row| row.chunk_entropy_mean),
+        Column::Double("chunk_entropy_std_dev", |row| row.chunk_entropy_std_dev),
+        Column::Double("chunk_entropy_min", |row| row.chunk_entropy_min),
+        Column::Double("chunk_entropy_max", |row| row.chunk_entropy_max),
+    ];
+    columns.extend(TYPE_CLASSES.iter().map(|class| Column::OneHot(class)));
+    columns
+}
+
+/// The Parquet schema of the feature table.
+fn schema() -> String {
+    let fields: String = columns()
+        .iter()
+        .map(|column| {
+            let annotation = match column {
+                Column::Text(..) => " (STRING)",
+                _ => "",
+            };
+            format!("required {} {}{annotation}; ", column.parquet_type(), column.name())
+        })
+        .collect();
+    format!("message features {{ {fields}}}")
+}
+
+/// Writes [Features] to a Parquet file, a row group at a time.
+pub struct FeatureWriter {
+    writer: SerializedFileWriter<File>,
+    rows: Vec<Features>,
+}
+
+impl FeatureWriter {
+    /// Create the Parquet file at `path`, replacing any file already there.
+    ///
+    /// Returns the [FeatureWriter], or an error message if the file can't be created.
+    pub fn create(path: &Path) -> Result<FeatureWriter, String> {
+        let error = |e: String| format!("Couldn't create {}: {e}", path.to_string_lossy());
+        let file = File::create(path).map_err(|e| error(e.to_string()))?;
+        let schema = parse_message_type(&schema()).map_err(|e| error(e.to_string()))?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::UNCOMPRESSED)
+            .set_created_by(format!("entropyscan {}", env!("CARGO_PKG_VERSION")))
+            .build();
+        let writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties)).map_err(|e|
+            error(e.to_string())
+        )?;
+        Ok(FeatureWriter { writer, rows: Vec::new() })
+    }
+
+    /// Add a row, writing out a row group once [ROW_GROUP_SIZE] rows are held.
+    ///
+    /// Returns an error message if the row group can't be written.
+    pub fn push(&mut self, features: Features) -> Result<(), String> {
+        self.rows.push(features);
+        match self.rows.len() >= ROW_GROUP_SIZE {
+            true => self.flush(),
+            false => Ok(()),
+        }
+    }
+
+    /// Write the rows held as a row group.
+    fn flush(&mut self) -> Result<(), String> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        self.write_row_group().map_err(|e| format!("Couldn't write features: {e}"))?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// Write the rows held as a row group, one column after another.
+    fn write_row_group(&mut self) -> Result<(), ParquetError> {
+        let rows = &self.rows;
+        let mut row_group = self.writer.next_row_group()?;
+        for column in columns() {
+            let Some(mut writer) = row_group.next_column()? else {
+                break;
+            };
+            match (&column, writer.untyped()) {
+                (Column::Text(_, value), ColumnWriter::ByteArrayColumnWriter(typed)) => {
+                    let values: Vec<ByteArray> = rows
+                        .iter()
+                        .map(|row| ByteArray::from(value(row).into_bytes()))
+                        .collect();
+                    typed.write_batch(&values, None, None)?;
+                }
+                (Column::Double(_, value), ColumnWriter::DoubleColumnWriter(typed)) => {
+                    let values: Vec<f64> = rows.iter().map(value).collect();
+                    typed.write_batch(&values, None, None)?;
+                }
+                (Column::Long(_, value), ColumnWriter::Int64ColumnWriter(typed)) => {
+                    let values: Vec<i64> = rows.iter().map(value).collect();
+                    typed.write_batch(&values, None, None)?;
+                }
+                (Column::OneHot(class), ColumnWriter::Int32ColumnWriter(typed)) => {
+                    let values: Vec<i32> = rows
+                        .iter()
+                        .map(|row| (row.type_class == *class) as i32)
+                        .collect();
+                    typed.write_batch(&values, None, None)?;
+                }
+                _ => {
+                    return Err(ParquetError::General(format!("column {} doesn't match the schema", column.name())));
+                }
+            }
+            writer.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    /// Write out the rows still held and the file's footer.
+    ///
+    /// Returns an error message if they can't be written.
+    pub fn finish(mut self) -> Result<(), String> {
+        self.flush()?;
+        self.writer
+            .close()
+            .map(|_| ())
+            .map_err(|e| format!("Couldn't write features: {e}"))
+    }
+}
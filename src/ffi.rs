@@ -0,0 +1,103 @@
+//! Contains the C API, so incident-response frameworks in Python, Go, and other languages can embed the scanner instead of running the binary and parsing its output.
+//!
+//! The [es_scan_path] function scans a file or directory and hands each result to a callback as an [EsResult].
+//!
+//! The declarations are mirrored in `include/entropyscan.h`, which can be regenerated with `cbindgen --config cbindgen.toml --output include/entropyscan.h`.
+use std::ffi::{ c_char, c_int, c_void, CStr, CString };
+use std::panic::{ self, AssertUnwindSafe };
+use std::path::PathBuf;
+
+use crate::entropy_scan::{ collect_targets, for_each_entropy };
+use crate::entropy_scan::filters::TargetFilter;
+use crate::entropy_scan::options::ScanOptions;
+
+/// The value [es_scan_path] returns when its arguments are invalid or the scan couldn't run.
+pub const ES_ERROR: c_int = -1;
+
+/// Holds the result of scanning one file, as handed to an [EsCallback].
+///
+/// The `path` field holds the path to the file as a NUL-terminated string. It is only valid until the callback returns.
+///
+/// The `entropy` field holds the entropy of the file, between 0.0 and 8.0.
+///
+/// The `size` field holds the size of the file in bytes.
+///
+/// The `low_confidence` field is set when the file is too small for its entropy to be meaningful.
+///
+/// The `printable_ratio` and `null_ratio` fields hold the fractions of bytes that are printable ASCII and 0x00.
+///
+#[repr(C)]
+#[derive(Debug)]
+pub struct EsResult {
+    pub path: *const c_char,
+    pub entropy: f64,
+    pub size: u64,
+    pub low_confidence: bool,
+    pub printable_ratio: f64,
+    pub null_ratio: f64,
+}
+
+/// The callback [es_scan_path] hands each [EsResult] to, along with the caller's `user_data`.
+pub type EsCallback = Option<unsafe extern "C" fn(result: *const EsResult, user_data: *mut c_void)>;
+
+/// Scan the file or directory at `path` with `jobs` worker threads, calling `callback` once per scanned file.
+///
+/// 0 jobs means one per CPU. Files are reported in a stable order, always on the calling thread.
+///
+/// Returns the number of files that couldn't be read, so 0 means every file was scanned, or [ES_ERROR] if `path` or `callback` is missing, `path` isn't UTF-8 or doesn't exist, or the scan failed.
+///
+/// # Safety
+///
+/// `path` must be NULL or point to a NUL-terminated string. `callback` must be safe to call with `user_data` for the duration of the scan.
+#[no_mangle]
+pub unsafe extern "C" fn es_scan_path(
+    path: *const c_char,
+    jobs: usize,
+    callback: EsCallback,
+    user_data: *mut c_void
+) -> c_int {
+    let callback = match callback {
+        Some(callback) if !path.is_null() => callback,
+        _ => {
+            return ES_ERROR;
+        }
+    };
+    let root = match CStr::from_ptr(path).to_str() {
+        Ok(root) => PathBuf::from(root),
+        Err(_) => {
+            return ES_ERROR;
+        }
+    };
+    if !root.exists() {
+        return ES_ERROR;
+    }
+
+    // A panic must not unwind into the caller's frames.
+    let scan = panic::catch_unwind(
+        AssertUnwindSafe(|| {
+            let targets = collect_targets(root, &TargetFilter::default());
+            let options = ScanOptions { jobs, ..ScanOptions::default() };
+            let unscanned = for_each_entropy(&targets, &options, |entropy| {
+                // Paths can't hold a NUL on any platform the scanner runs on.
+                let path = CString::new(entropy.path.to_string_lossy().into_owned()).unwrap_or_default();
+                let result = EsResult {
+                    path: path.as_ptr(),
+                    entropy: entropy.entropy,
+                    size: entropy.size,
+                    low_confidence: entropy.low_confidence,
+                    printable_ratio: entropy.printable_ratio,
+                    null_ratio: entropy.null_ratio,
+                };
+                callback(&result, user_data);
+            });
+            unscanned
+                .iter()
+                .filter(|target| !target.skipped)
+                .count()
+        })
+    );
+    match scan {
+        Ok(failed) => c_int::try_from(failed).unwrap_or(c_int::MAX),
+        Err(_) => ES_ERROR,
+    }
+}
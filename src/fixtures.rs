@@ -0,0 +1,171 @@
+//! Contains the logic for generating a deterministic test corpus.
+//!
+//! [generate_fixtures] writes the same files, byte for byte, on every run and platform: random data, zeros, text, nested directories, tar and zip archives, a long file name and, on Unix, a non-UTF-8 file name.
+//!
+//! The corpus is used by the crate's own integration tests and lets packagers check a build on their platform.
+use std::fs;
+use std::io;
+use std::path::{ Path, PathBuf };
+
+use crate::entropy_scan::sampling::XorShift;
+
+/// The seed for the corpus' pseudo-random data.
+const FIXTURE_SEED: u64 = 0x5eed_f1c5;
+
+/// The size of the larger random and zero-filled fixtures.
+const FIXTURE_SIZE: usize = 64 * 1024;
+
+/// The text repeated in the text fixtures.
+const LOREM: &str =
+    "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.\n";
+
+/// Calculate the CRC-32 (IEEE) checksum of `data`, as used by zip archives.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Build an uncompressed ustar archive holding `members`.
+fn tar_archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (name, data) in members {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[136..148].copy_from_slice(b"00000000000\0");
+        header[148..156].copy_from_slice(b"        ");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        let checksum: u32 = header
+            .iter()
+            .map(|b| *b as u32)
+            .sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(512), 0);
+    }
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
+
+/// Build a zip archive holding `members`, stored without compression.
+fn zip_archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in members {
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        common.extend_from_slice(&0u16.to_le_bytes()); // flags
+        common.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+        common.extend_from_slice(&0u16.to_le_bytes()); // time
+        common.extend_from_slice(&0x21u16.to_le_bytes()); // date: 1980-01-01
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra length
+
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = archive.len() as u32;
+    let count = members.len() as u16;
+    archive.extend_from_slice(&central);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // disk number
+    archive.extend_from_slice(&0u16.to_le_bytes()); // central directory disk
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&central_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    archive
+}
+
+/// Write `data` to `dir/name`, creating parent directories as needed.
+fn write_fixture(dir: &Path, name: &str, data: &[u8]) -> io::Result<PathBuf> {
+    let path = dir.join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, data)?;
+    Ok(path)
+}
+
+/// Generate the deterministic test corpus in `dir`.
+///
+/// Returns the paths of every file written, or an error message.
+pub fn generate_fixtures(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut rng = XorShift::new(FIXTURE_SEED);
+    let random = rng.bytes(FIXTURE_SIZE);
+    let small_random = rng.bytes(4096);
+    let zeros = vec![0u8; FIXTURE_SIZE];
+    let text = LOREM.repeat(64);
+    let long_name = format!("long/{}.txt", "l".repeat(200));
+
+    let fixtures: Vec<(&str, &[u8])> = vec![
+        ("random.bin", &random),
+        ("zeros.bin", &zeros),
+        ("empty.txt", b""),
+        ("text/lorem.txt", text.as_bytes()),
+        ("nested/a/b/c/deep.bin", &small_random),
+        (&long_name, LOREM.as_bytes())
+    ];
+    let archive_members: Vec<(&str, &[u8])> = vec![
+        ("inner/lorem.txt", text.as_bytes()),
+        ("inner/random.bin", &small_random)
+    ];
+    let tar = tar_archive(&archive_members);
+    let zip = zip_archive(&archive_members);
+
+    let mut written = Vec::new();
+    let error = |e: io::Error| format!("Couldn't write fixtures: {e}");
+    for (name, data) in fixtures
+        .into_iter()
+        .chain([("archives/bundle.tar", tar.as_slice()), ("archives/bundle.zip", zip.as_slice())]) {
+        written.push(write_fixture(dir, name, data).map_err(error)?);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"non-utf8-\xff\xfe.bin");
+        let path = dir.join(name);
+        fs::write(&path, &small_random).map_err(error)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
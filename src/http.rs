@@ -0,0 +1,49 @@
+//! Contains a small HTTP client over [ureq], used to fetch releases for `self-update` and to post job summaries to webhooks.
+//!
+//! [get] and [post_json] send a single request to an `http://` or `https://` URL and return the body of a `200`-range response. HTTPS certificates are checked against the bundled web PKI roots.
+use std::time::Duration;
+
+use ureq::Agent;
+
+/// How long a whole request, from connecting to reading the last byte of the response, may take.
+const TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The largest response body read, which leaves room for release binaries.
+const MAX_BODY: u64 = 1 << 30;
+
+/// Build an agent that gives up after [TIMEOUT].
+fn agent() -> Agent {
+    Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .user_agent(format!("entropyscan/{}", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into()
+}
+
+/// Fetch `url`.
+///
+/// Returns the response body, or an error message if the server can't be reached or doesn't answer with a 2xx status.
+pub fn get(url: &str) -> Result<Vec<u8>, String> {
+    let error = |e: ureq::Error| format!("Couldn't GET {url}: {e}");
+    agent()
+        .get(url)
+        .call()
+        .map_err(error)?
+        .body_mut()
+        .with_config()
+        .limit(MAX_BODY)
+        .read_to_vec()
+        .map_err(error)
+}
+
+/// Post `body`, a JSON document, to `url`.
+///
+/// Returns an error message if the server can't be reached or doesn't answer with a 2xx status.
+pub fn post_json(url: &str, body: &[u8]) -> Result<(), String> {
+    agent()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .send(body)
+        .map(|_| ())
+        .map_err(|e| format!("Couldn't POST {url}: {e}"))
+}
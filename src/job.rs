@@ -0,0 +1,353 @@
+//! Contains the logic for `run`: a batch of scans described by a job file, in place of shell wrappers around several `scan` invocations.
+//!
+//! A job file is YAML, read with `serde_yaml_ng`, so anchors, block scalars, and the rest of YAML work as usual. It lists targets, each with its paths, `scan` options, and outputs, plus options shared by every target and the notification sinks the consolidated summary is sent to:
+//!
+//! ```yaml
+//! defaults:
+//!   min-entropy: 7.5
+//! targets:
+//!   - name: shares
+//!     path: /srv/shares
+//!     options:
+//!       exclude: ["*.zip"]
+//!     outputs:
+//!       - format: json
+//!         file: /var/log/entropyscan/shares.json
+//! notify:
+//!   - webhook: http://alerts.internal/hook
+//!   - command: logger -t entropyscan
+//!   - file: /var/log/entropyscan/jobs.jsonl
+//! suppress:
+//!   - daily: 01:00-03:30
+//!     reason: backup encryption
+//!   - from: 2024-06-01T22:00:00Z
+//!     until: 2024-06-02T04:00:00Z
+//!   - while_exists: /run/entropyscan.maintenance
+//! ```
+//!
+//! Options are `scan`'s long flags without the dashes: `true` passes a flag, `false` leaves it out, and a list repeats it.
+//!
+//! [run_job] scans each target in turn, as a `scan` of its own, so one target failing doesn't stop the others, and [notify] sends the summary on.
+//!
+//! During a suppression window, found by [suppression], reports are still written but the summary isn't sent, so known-noisy periods such as backup encryption or OS upgrades don't page anyone. Windows are daily UTC times, a UTC date range, or the existence of a file, so other tooling can start and end maintenance by creating and removing it.
+use std::env;
+use std::ffi::OsString;
+use std::fs::{ self, OpenOptions };
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+use std::process::{ Command, Stdio };
+use std::time::{ Instant, SystemTime, UNIX_EPOCH };
+
+use serde::Deserialize;
+use serde_json::{ json, Map, Value };
+
+use crate::entropy_scan;
+use crate::http::post_json;
+use entropy_scan::structs::{ JobResult, JobSummary, ScanMeta };
+use entropy_scan::units::{ parse_daily_window, parse_date };
+
+/// Holds a job file.
+///
+/// The `defaults` field holds the `scan` options every target starts from.
+///
+/// The `targets` field holds the targets, scanned in order.
+///
+/// The `notify` field holds where the summary is sent once every target is scanned.
+///
+/// The `suppress` field holds the windows during which it isn't sent.
+///
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    #[serde(default)]
+    defaults: Map<String, Value>,
+    targets: Vec<JobTarget>,
+    #[serde(default)]
+    notify: Vec<Sink>,
+    #[serde(default)]
+    suppress: Vec<Suppression>,
+}
+
+/// Holds one path, or several.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Paths {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+/// Holds one target of a job.
+///
+/// The `name` field holds what the summary calls the target. Its paths are used if it has none.
+///
+/// The `path` field holds the files or directories scanned together.
+///
+/// The `options` field holds its `scan` options, which override the job's defaults.
+///
+/// The `outputs` field holds the reports written for it.
+///
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobTarget {
+    name: Option<String>,
+    path: Paths,
+    #[serde(default)]
+    options: Map<String, Value>,
+    #[serde(default)]
+    outputs: Vec<JobOutput>,
+}
+
+/// Holds a report written for a target: its format, as for `--format`, and the file to write it to, or stdout.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobOutput {
+    format: String,
+    file: Option<PathBuf>,
+}
+
+/// Holds where a job's summary is sent: posted as JSON to an `http://` or `https://` webhook, piped as JSON to a shell command, or appended as a line of JSON to a file.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sink {
+    Webhook(String),
+    Command(String),
+    File(PathBuf),
+}
+
+/// Holds a window during which a job's summary isn't sent: every day between two UTC times, between two UTC dates, or while a file exists.
+///
+/// The `daily` field holds a daily window such as `01:00-03:30`, see [parse_daily_window].
+///
+/// The `from` and `until` fields hold the start and end of a one-off window, see [parse_date]. Either may be left out for a window that is open-ended on that side.
+///
+/// The `while_exists` field holds a file whose existence marks a maintenance window.
+///
+/// The `reason` field holds why notifications are held back, for the summary.
+///
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Suppression {
+    daily: Option<String>,
+    from: Option<String>,
+    until: Option<String>,
+    while_exists: Option<PathBuf>,
+    reason: Option<String>,
+}
+
+impl Suppression {
+    /// Check whether the window is open at `now`, and if so describe why notifications are held back.
+    ///
+    /// Returns an error message if the window isn't exactly one of a daily window, a date range, or a file.
+    fn check(&self, now: SystemTime) -> Result<Option<String>, String> {
+        let (open, description) = match (&self.daily, &self.from, &self.until, &self.while_exists) {
+            (Some(daily), None, None, None) => {
+                let (start, end) = parse_daily_window(daily)?;
+                let time = (now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() % 86400) as u32;
+                let open = match start <= end {
+                    true => start <= time && time < end,
+                    false => time >= start || time < end,
+                };
+                (open, format!("daily window {daily} UTC"))
+            }
+            (None, from, until, None) if from.is_some() || until.is_some() => {
+                let from = from.as_deref().map(parse_date).transpose()?;
+                let until = until.as_deref().map(parse_date).transpose()?;
+                let open = from.is_none_or(|from| now >= from) && until.is_none_or(|until| now < until);
+                let bound = |label: &str, value: &Option<String>| value.as_ref().map(|value| format!("{label} {value}"));
+                let description = [bound("from", &self.from), bound("until", &self.until)]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (open, format!("window {description}"))
+            }
+            (None, None, None, Some(path)) => {
+                (path.exists(), format!("maintenance file {} exists", path.to_string_lossy()))
+            }
+            _ => {
+                return Err("A suppression window needs exactly one of `daily`, `from`/`until`, or `while_exists`".to_string());
+            }
+        };
+        Ok(open.then(|| self.reason.clone().unwrap_or(description)))
+    }
+}
+
+impl JobTarget {
+    fn paths(&self) -> &[PathBuf] {
+        match &self.path {
+            Paths::One(path) => std::slice::from_ref(path),
+            Paths::Many(paths) => paths,
+        }
+    }
+
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.paths()
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+    }
+
+    /// Build the `scan` arguments for this target, starting from `defaults`, with a JSON report also written to `report`.
+    fn scan_args(&self, defaults: &Map<String, Value>, report: &Path, quiet: bool) -> Result<Vec<OsString>, String> {
+        let mut args: Vec<OsString> = vec!["scan".into()];
+        if quiet {
+            args.push("--quiet".into());
+        }
+        for path in self.paths() {
+            args.extend(["--target".into(), path.into()]);
+        }
+        let mut options = defaults.clone();
+        options.extend(self.options.clone());
+        for (key, value) in &options {
+            let flag = format!("--{}", key.replace('_', "-"));
+            let values = match value {
+                Value::Array(values) => values.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            for value in values {
+                match value {
+                    Value::Bool(true) => args.push(flag.clone().into()),
+                    Value::Bool(false) | Value::Null => {}
+                    Value::String(text) => args.extend([flag.clone().into(), text.into()]),
+                    Value::Number(number) => args.extend([flag.clone().into(), number.to_string().into()]),
+                    Value::Array(_) | Value::Object(_) => {
+                        return Err(format!("Option `{key}` of target {} must be a flag, value, or list of values", self.name()));
+                    }
+                }
+            }
+        }
+        // Each --format pairs with the --output at the same position, so formats written to stdout come last.
+        args.extend(["--format".into(), "json".into(), "--output".into(), report.into()]);
+        let (to_files, to_stdout): (Vec<_>, Vec<_>) = self.outputs.iter().partition(|output| output.file.is_some());
+        for output in to_files.into_iter().chain(to_stdout) {
+            args.extend(["--format".into(), output.format.clone().into()]);
+            if let Some(file) = &output.file {
+                args.extend(["--output".into(), file.into()]);
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Read the job file at `path`.
+///
+/// Returns the [Job], or an error message if it can't be read, isn't valid, or lists no targets.
+pub fn read_job(path: &Path) -> Result<Job, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read job {}: {e}", path.to_string_lossy()))?;
+    let error = |e: String| format!("Invalid job {}: {e}", path.to_string_lossy());
+    let document: Value = serde_yaml_ng::from_str(&text).map_err(|e| error(e.to_string()))?;
+    let job: Job = serde_json::from_value(document).map_err(|e| error(e.to_string()))?;
+    if job.targets.is_empty() {
+        return Err(error("no targets".to_string()));
+    }
+    for window in &job.suppress {
+        window.check(SystemTime::now()).map_err(error)?;
+    }
+    Ok(job)
+}
+
+/// Find the first of `job`'s suppression windows open at `now`.
+///
+/// Returns why notifications are held back, or [None] if they should be sent.
+pub fn suppression(job: &Job, now: SystemTime) -> Option<String> {
+    job.suppress
+        .iter()
+        .find_map(|window| window.check(now).ok().flatten())
+}
+
+/// Count the entries of the JSON scan report at `path`.
+fn count_reported(path: &Path) -> Option<usize> {
+    let report: Value = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    ["entropies", "groups"]
+        .iter()
+        .find_map(|key| report[key].as_array())
+        .map(Vec::len)
+}
+
+/// Scan each target of `job`, read from `path`, in turn with this binary's `scan`, passing `quiet` on.
+///
+/// Returns the [JobSummary], or an error message if a target's options are invalid, in which case nothing is scanned.
+pub fn run_job(path: &Path, job: &Job, quiet: bool) -> Result<JobSummary, String> {
+    let exe = env::current_exe().map_err(|e| format!("Couldn't find the running binary: {e}"))?;
+    let reports: Vec<PathBuf> = (0..job.targets.len())
+        .map(|index| env::temp_dir().join(format!("entropyscan-job-{}-{index}.json", std::process::id())))
+        .collect();
+    let args = job.targets
+        .iter()
+        .zip(&reports)
+        .map(|(target, report)| target.scan_args(&job.defaults, report, quiet))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut results = Vec::with_capacity(job.targets.len());
+    for ((target, report), args) in job.targets.iter().zip(&reports).zip(args) {
+        let started = Instant::now();
+        let code = Command::new(&exe)
+            .args(args)
+            .stdin(Stdio::null())
+            .status()
+            .ok()
+            .and_then(|status| status.code());
+        let status = match code {
+            Some(0) => "clean",
+            Some(1) => "findings",
+            Some(2) => "errors",
+            _ => "failed",
+        };
+        results.push(JobResult {
+            name: target.name(),
+            status,
+            reported: count_reported(report),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+        let _ = fs::remove_file(report);
+    }
+    Ok(JobSummary { job: path.to_path_buf(), results, suppressed: None })
+}
+
+/// Send the `summary` of `job`, headed by `meta`, to each of its sinks.
+///
+/// Returns an error message for each sink it couldn't be sent to.
+pub fn notify(job: &Job, meta: &ScanMeta, summary: &JobSummary) -> Vec<String> {
+    let mut report = json!(meta);
+    report["job"] = json!(summary);
+    let payload = report.to_string();
+    job.notify
+        .iter()
+        .filter_map(|sink| send(sink, &payload).err())
+        .collect()
+}
+
+/// Send `payload` to `sink`.
+fn send(sink: &Sink, payload: &str) -> Result<(), String> {
+    match sink {
+        Sink::Webhook(url) => post_json(url, payload.as_bytes()),
+        Sink::Command(command) => {
+            let error = |e: std::io::Error| format!("Couldn't run notification command `{command}`: {e}");
+            let mut shell = match cfg!(windows) {
+                true => Command::new("cmd"),
+                false => Command::new("sh"),
+            };
+            let flag = match cfg!(windows) {
+                true => "/C",
+                false => "-c",
+            };
+            let mut child = shell.args([flag, command]).stdin(Stdio::piped()).spawn().map_err(error)?;
+            if let Some(mut stdin) = child.stdin.take() {
+                // A command that doesn't read its input isn't an error.
+                let _ = writeln!(stdin, "{payload}");
+            }
+            match child.wait().map_err(error)? {
+                status if status.success() => Ok(()),
+                status => Err(format!("Notification command `{command}` failed: {status}")),
+            }
+        }
+        Sink::File(path) => {
+            let error = |e: std::io::Error| format!("Couldn't append to {}: {e}", path.to_string_lossy());
+            let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(error)?;
+            writeln!(file, "{payload}").map_err(error)
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! The entropy scanner as a library.
+//!
+//! The [entropy_scan] module holds the scanner itself, as used by the `entropyscan` binary.
+//!
+//! The library also builds for WebAssembly (`wasm32-wasip1`, or `wasm32-unknown-unknown` for browsers). There, [entropy_scan::entropy_of_bytes], [entropy_scan::windows::sliding_entropy_of], and the [entropy_scan::stats] functions work on data already in memory, with no filesystem, and scans run on a single thread unless the target has atomics.
+//!
+//! The [ffi] module wraps it in a small C API, built into a shared library, so other languages can scan in-process. Its header is `include/entropyscan.h`.
+pub mod entropy_scan;
+pub mod ffi;
@@ -5,41 +5,614 @@
 //! It can also display the stats for a given target, including the [entropy_scan::stats::mean], [entropy_scan::stats::median], [entropy_scan::stats::variance], and [entropy_scan::stats::interquartile_range].
 //!
 //! The utility can also display the outliers with the [entropy_scan::stats::entropy_outliers].
-use std::path::PathBuf;
+//!
+//! Every subcommand can write several [output::OutputFormat]s at once through [output::OutputArgs].
+//!
+//! The utility can also hunt for files whose block-entropy profile resembles a sample with [entropy_scan::similarity::rank_by_similarity].
+//!
+//! Disks and disk images can be split into their MBR or GPT partitions, each with an entropy map, with [entropy_scan::partitions::scan_partitions].
+//!
+//! Large files such as swap and hibernation files can be split into regions of similar entropy with [entropy_scan::regions::region_map].
+//!
+//! Resident file data and file slack inside raw NTFS volumes can be measured with [entropy_scan::ntfs::mft_data], and their unallocated clusters mapped with [entropy_scan::ntfs::unallocated].
+//!
+//! The entropy of every window of a single file can be listed with [entropy_scan::windows::sliding_entropy], to locate payloads embedded in a binary, or of every content-defined chunk with [entropy_scan::cdc::chunk_entropy].
+//!
+//! The feature vector of every scanned file can be written to Parquet for training classifiers with [features::FeatureWriter].
+//!
+//! A deterministic test corpus can be written with [fixtures::generate_fixtures].
+//!
+//! JSON scan reports can be turned into a short human-readable summary with [summary::summarize], and two of them compared with [compare::compare_reports].
+//!
+//! A tree can be recorded as a baseline with [diff::write_baseline] and later diffed against it with [diff::diff_against_baseline], to spot files whose entropy jumped, as ransomware leaves them.
+//!
+//! Several scans can be run as one batch from a job file with [job::run_job].
+//!
+//! The binary can replace itself with a newer, signed release with [update::install_update].
+use std::collections::HashSet;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
 
-use clap::{ Parser, Subcommand, ValueEnum };
-use serde_json::json;
+use clap::{ Args, Parser, Subcommand };
 
-mod entropy_scan;
+use entropyscan::entropy_scan;
+mod compare;
+mod diff;
+mod features;
+mod fixtures;
+mod http;
+mod job;
+mod output;
+mod summary;
+mod update;
 use entropy_scan::{
+    aggregate::{ aggregate, AggregateBy },
+    anomaly::AnomalyModel,
+    block_profile,
+    cdc::{ chunk_entropy, chunk_profile },
+    chunking::chunk_size_for,
     collect_entropies,
     collect_targets,
-    stats::{ entropy_outliers, interquartile_range, mean, median, variance },
-    structs::FileEntropy,
+    entropy_of_reader,
+    features::file_features,
+    filters::{ SymlinkPolicy, TargetFilter },
+    forensic::{ preserve_access_times, provenance },
+    for_each_entropy,
+    git::changed_targets,
+    glob::{ Glob, Globs },
+    mounts::{ mount_point_of, mounts, scan_roots },
+    new_scan_id,
+    ntfs::{ mft_data, unallocated },
+    options::{ ScanOptions, SymbolWidth },
+    packages::PackageDb,
+    partitions::scan_partitions,
+    presets::{ preset_targets, CI_MIN_ENTROPY, CI_SKIP_NAMES, MACOS_ARTIFACTS, STAGING_LOCATIONS },
+    regions::region_map,
+    sandbox::confine,
+    sampling::{ random_seed, sample_targets },
+    scan_cache::{ default_cache_path, ScanCache },
+    similarity::rank_by_similarity,
+    staging::{ prioritize, staging_label },
+    stats::{ created_since, entropy_outliers, interquartile_range, mean, median, variance },
+    structs::{ FileEntropy, Provenance, ScanMeta, Unscanned },
+    target_list::read_target_list,
+    units::{ parse_duration, parse_max_size, parse_min_size, parse_time_bound },
+    windows::sliding_entropy,
+};
+use output::{
+    render_aggregates,
+    render_comparison,
+    render_diff,
+    render_job,
+    render_hunt,
+    render_partitions,
+    render_mft,
+    render_regions,
+    render_scan,
+    render_stats,
+    render_windows,
+    emit_finding,
+    open_emit_socket,
+    stream_scan_header,
+    stream_scan_row,
+    OutputArgs,
+    OutputFormat,
 };
+use compare::compare_reports;
+use diff::{ diff_against_baseline, write_baseline };
+use features::FeatureWriter;
+use job::{ notify, read_job, run_job, suppression };
+use fixtures::generate_fixtures;
+use summary::summarize;
+use update::{ check_for_update, install_update, parse_public_key };
+
+/// The exit codes, shown at the end of `--help`.
+const EXIT_CODES: &str =
+    "Exit codes:
+  0  Clean: nothing above the requested threshold
+  1  Findings: files or windows above --min-entropy, files above --fail-above or outliers with
+     --fail-if-outliers, files above --min-similarity, unowned or modified files with
+     --verify-packages, stats outliers, likely encrypted partitions, a material change between
+     compare-stats reports, new, removed, changed, or wiped files with diff, targets with
+     findings in a run job, or a newer release with self-update --check-only
+  2  Completed with errors: some files couldn't be read, even if there were findings, or a
+     run job's target failed or its summary couldn't be sent. Files skipped by
+     --max-file-size and cloud placeholders are reported but aren't errors
+  3  Fatal: the command couldn't run";
+
+/// The outcome of a command that ran to completion, see [EXIT_CODES].
+enum Status {
+    Clean,
+    Findings,
+    Errors,
+}
+
+impl Status {
+    /// Pick the status for a run with `findings` and `failed` unreadable files.
+    ///
+    /// Errors take precedence over findings.
+    fn of(findings: bool, failed: usize) -> Status {
+        match (findings, failed) {
+            (_, 1..) => Status::Errors,
+            (true, 0) => Status::Findings,
+            (false, 0) => Status::Clean,
+        }
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> ExitCode {
+        match status {
+            Status::Clean => ExitCode::from(0),
+            Status::Findings => ExitCode::from(1),
+            Status::Errors => ExitCode::from(2),
+        }
+    }
+}
+
+/// The exit code for a command that couldn't run.
+const FATAL: u8 = 3;
 
-/// A [Cli] struct holding a [Command] enum for the subcommands [Command::Scan] and [Command::Stats].
+/// The `--target` that reads standard input instead of a file.
+const STDIN_TARGET: &str = "-";
+
+/// A [Cli] struct holding a [Command] enum for the subcommands [Command::Scan], [Command::Stats], [Command::Hunt], [Command::Partitions], [Command::Regions], [Command::Blocks], [Command::Ntfs], [Command::Summarize], [Command::CompareStats], [Command::Baseline], [Command::Diff], [Command::Run], [Command::GenFixtures], and [Command::SelfUpdate].
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    /// Print only machine-readable output: no diagnostics and no tables on stdout.
+    #[arg(short, long, global = true, help = "Print only machine-readable output")]
+    quiet: bool,
 }
 
-/// A custom enum to represent the chosen output format.
-///
-/// Valid values are [OutputFormat::Csv], [OutputFormat::Json], and [OutputFormat::Table]. Default is [OutputFormat::Table].
-#[derive(Clone, ValueEnum)]
-enum OutputFormat {
-    Csv,
-    Json,
-    Table,
+/// Holds the options that decide which files are collected as targets.
+#[derive(Args)]
+struct FilterArgs {
+    /// Skip files smaller than this. Accepts units such as `10K`. `auto` skips files too small for a meaningful entropy.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "Skip files smaller than SIZE, e.g. 10K, or `auto` for files too small to be meaningful",
+        value_parser = parse_min_size
+    )]
+    min_size: Option<u64>,
+
+    /// Leave out files larger than this, such as VM disks, without reporting them. Accepts units such as `5M`. See `--max-file-size` to report them instead.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "Skip files larger than SIZE, e.g. 5M, without reporting them",
+        value_parser = parse_max_size
+    )]
+    max_size: Option<u64>,
+
+    /// Only keep files modified since this: a duration before now such as `24h`, or a UTC date such as `2024-01-01`.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Only scan files modified since WHEN, e.g. 24h or 2024-01-01",
+        value_parser = parse_time_bound
+    )]
+    newer_than: Option<SystemTime>,
+
+    /// Only keep files last modified before this, given like `--newer-than`.
+    #[arg(
+        long,
+        value_name = "WHEN",
+        help = "Only scan files last modified before WHEN, e.g. 30d or 2024-01-01",
+        value_parser = parse_time_bound
+    )]
+    older_than: Option<SystemTime>,
+
+    /// Walk at most this many directory levels below each target. 1 scans only the files directly inside it.
+    #[arg(long, value_name = "N", help = "Walk at most N directory levels below each target")]
+    max_depth: Option<usize>,
+
+    /// Walk into symbolic links to directories too. Each directory and file is visited once, so link loops end and linked trees aren't scanned twice.
+    #[arg(
+        long,
+        help = "Follow symlinks to directories, visiting each directory and file once",
+        overrides_with = "no_follow_symlinks"
+    )]
+    follow_symlinks: bool,
+
+    /// Leave out symbolic links entirely. By default links to files are scanned but links to directories aren't walked into.
+    #[arg(long, help = "Skip symlinks entirely", overrides_with = "follow_symlinks")]
+    no_follow_symlinks: bool,
+
+    /// Walk into hidden files and directories, those whose names start with a `.`, such as `.cache`. This is the default except with `--ci`.
+    #[arg(long, help = "Scan hidden files and directories (the default, except with --ci)", overrides_with = "no_hidden")]
+    hidden: bool,
+
+    /// Leave out hidden files and directories.
+    #[arg(long, help = "Skip hidden files and directories", overrides_with = "hidden")]
+    no_hidden: bool,
+
+    /// Stay on the filesystem each target is on, without walking into other mounts such as `/proc`, NFS shares, or bind mounts.
+    #[arg(long, help = "Don't walk into other filesystems mounted beneath a target")]
+    one_file_system: bool,
+
+    /// Only keep files matching one of these globs.
+    #[arg(long, value_name = "GLOB", help = "Only scan files matching GLOB, e.g. 'bin/**' (repeatable)")]
+    include: Vec<Glob>,
+
+    /// Leave out files and directories matching any of these globs.
+    #[arg(long, value_name = "GLOB", help = "Skip files and directories matching GLOB, e.g. '*.iso' (repeatable)")]
+    exclude: Vec<Glob>,
+
+    /// Only keep files with one of these extensions, given without the dot.
+    #[arg(
+        long,
+        value_name = "EXTS",
+        value_delimiter = ',',
+        help = "Only scan files with these extensions, e.g. exe,dll,so"
+    )]
+    ext: Vec<String>,
+
+    /// Only keep files whose sniffed MIME type is one of these, or in a family such as `image/*`.
+    #[arg(
+        long,
+        value_name = "TYPES",
+        value_delimiter = ',',
+        help = "Only scan files of these MIME types, sniffed from their contents, e.g. application/octet-stream"
+    )]
+    mime: Vec<String>,
+
+    /// Walk into paths ignored by `.gitignore` and `.entropyscanignore` files, and by the `.git/info/exclude` and global excludes files of git repositories, too.
+    ///
+    /// Only ignore files in the directories being scanned are honored, not those above them.
+    #[arg(long, help = "Don't honor .gitignore, .entropyscanignore, and git exclude files. Ignore files above the scanned directories are never read")]
+    no_ignore: bool,
+}
+
+impl FilterArgs {
+    /// Build the [TargetFilter] described by the arguments.
+    fn filter(&self) -> TargetFilter {
+        TargetFilter {
+            min_size: self.min_size,
+            max_size: self.max_size,
+            modified_after: self.newer_than,
+            modified_before: self.older_than,
+            skip_names: Vec::new(),
+            skip_hidden: self.no_hidden,
+            skip_paths: Vec::new(),
+            max_depth: self.max_depth,
+            one_file_system: self.one_file_system,
+            symlinks: match (self.follow_symlinks, self.no_follow_symlinks) {
+                (true, _) => SymlinkPolicy::Follow,
+                (_, true) => SymlinkPolicy::Skip,
+                _ => SymlinkPolicy::Files,
+            },
+            include: Globs::new(&self.include),
+            exclude: Globs::new(&self.exclude),
+            extensions: self.ext
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_string())
+                .collect(),
+            mime_types: self.mime.clone(),
+            ignore_files: !self.no_ignore,
+        }
+    }
 }
 
-/// A [Subcommand] enum for the [Command::Scan] and [Command::Stats] subcommands.
+/// Holds the options that control how each file's entropy is calculated.
+#[derive(Args)]
+struct EntropyArgs {
+    /// The size in bits of the symbols entropy is measured over: 4, 8, or 16.
+    #[arg(
+        long,
+        value_name = "BITS",
+        help = "Measure entropy over 4-bit nibbles, 8-bit bytes, or 16-bit words",
+        default_value = "8"
+    )]
+    symbol_width: SymbolWidth,
+
+    /// Look for repeating structure, such as data XORed with a repeating key.
+    #[arg(long, help = "Look for periodic structure such as a repeating XOR key")]
+    periodicity: bool,
+
+    /// Try simple single-byte and rolling XOR keys against each file.
+    #[arg(long, help = "Try single-byte and rolling XOR keys, reporting likely keys")]
+    try_xor: bool,
+
+    /// Include each file's 256-bucket byte frequency table in JSON output.
+    #[arg(long, help = "Include each file's byte frequency table in JSON output")]
+    dump_histogram: bool,
+
+    /// The number of files scanned at once. 0 uses one worker per CPU. Results are reported in the same order whatever the number.
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        help = "Scan N files at once, 0 for one per CPU",
+        default_value = "1"
+    )]
+    jobs: usize,
+
+    /// Skip, and report, files larger than this. Accepts units such as `2G`, or `unlimited`, the default.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "Skip and report files larger than SIZE, e.g. 2G [default: unlimited]",
+        value_parser = parse_max_size
+    )]
+    max_file_size: Option<u64>,
+
+    /// Read cloud-sync placeholder files (OneDrive, Dropbox, iCloud) even though that downloads them. They are skipped and reported otherwise.
+    #[arg(long, help = "Download and scan cloud-sync placeholder files instead of skipping them")]
+    hydrate_placeholders: bool,
+
+    /// Read character and block devices, FIFOs, and sockets. They are skipped and reported otherwise, as reading them can hang or never end.
+    #[arg(long, help = "Read devices, FIFOs, and sockets instead of skipping them")]
+    read_special_files: bool,
+
+    /// Also scan each member of zip, tar, and gzip (including `.tar.gz`) archives, reported as `archive.zip!member`.
+    #[arg(long, help = "Also scan each member of zip, tar, and gzip archives")]
+    archives: bool,
+
+    /// Open archives nested up to this many levels deep, e.g. 3 for a zip inside a tar inside a zip. Implies `--archives`.
+    #[arg(long, value_name = "N", help = "Open nested archives up to N levels deep (implies --archives)")]
+    container_depth: Option<usize>,
+
+    /// Measure the entropy of each section of PE and ELF executables, and each segment of Mach-O executables, plus any overlay, alongside the whole-file entropy.
+    #[arg(long, help = "Report the entropy of each section of PE, ELF, and Mach-O executables")]
+    sections: bool,
+
+    /// Route each file to the analyzers that suit its signature: section analysis for PE, ELF, and Mach-O executables, member scanning for zip, tar, and gzip archives, and a region map for paging and hibernation files. Other flags still apply to every file.
+    #[arg(long, help = "Run each file through the analyzers its type calls for: sections, archive members, regions")]
+    deep: bool,
+
+    /// Record how long each file, and the whole scan, took.
+    #[arg(long, help = "Report how long each file and the whole scan took, in milliseconds unless --human")]
+    timings: bool,
+
+    /// Wipe file contents from memory as soon as they have been measured, for material under legal hold or classified handling rules. The scan cache is turned off, scans never write temporary files either way, and archives are only ever opened in memory.
+    #[arg(long, help = "Wipe file contents from memory once measured; nothing is ever cached to disk")]
+    no_persist: bool,
+
+    /// Drop each file from the page cache once it has been measured (Linux), so a nightly scan doesn't evict the pages of busy services.
+    #[arg(long, help = "Drop scanned files from the page cache so the scan doesn't evict other programs' pages")]
+    no_cache_pollution: bool,
+
+    /// Map files into memory instead of reading them through a buffer, saving a copy of every page when scanning very large files. Files that can't be mapped are read as usual. Another process truncating a file while it is mapped ends the scan with SIGBUS, so only use this on files nothing else is writing.
+    #[arg(long, help = "Map files into memory instead of reading them, falling back to reads")]
+    mmap: bool,
+
+    /// Wait before starting each file while the one-minute load average is above this (Linux), so a scan yields to a busy server.
+    #[arg(long, value_name = "LOAD", help = "Pause while the one-minute load average is above LOAD, e.g. 2.0")]
+    max_load: Option<f64>,
+
+    /// Wait before starting each file while the machine runs on battery (Linux), so a scan doesn't drain a laptop.
+    #[arg(long, help = "Pause while running on battery")]
+    pause_on_battery: bool,
+
+    /// Scan forensically: open files without updating their access times where permitted (Linux), refuse to write output inside a target, and record the SHA-256 of this binary and its arguments in JSON reports.
+    #[arg(long, help = "Preserve access times, never write inside targets, and record the tool's hash")]
+    forensic: bool,
+}
+
+impl EntropyArgs {
+    /// Build the [ScanOptions] described by the arguments.
+    fn options(&self) -> ScanOptions {
+        ScanOptions {
+            symbol_width: self.symbol_width,
+            periodicity: self.periodicity,
+            try_xor: self.try_xor,
+            container_depth: self.container_depth.unwrap_or(self.archives as usize),
+            sections: self.sections,
+            deep: self.deep,
+            histogram: self.dump_histogram,
+            jobs: self.jobs,
+            timings: self.timings,
+            max_file_size: self.max_file_size,
+            hydrate_placeholders: self.hydrate_placeholders,
+            read_special_files: self.read_special_files,
+            no_cache_pollution: self.no_cache_pollution,
+            mmap: self.mmap,
+            max_load: self.max_load,
+            pause_on_battery: self.pause_on_battery,
+            cache: None,
+            no_persist: self.no_persist,
+        }
+    }
+
+    /// Describe this binary and its arguments for the report, when scanning forensically.
+    fn provenance(&self) -> Result<Option<Provenance>, String> {
+        match self.forensic {
+            true => provenance().map(Some),
+            false => Ok(None),
+        }
+    }
+}
+
+/// Holds the options for scanning a random sample of the targets instead of all of them.
+#[derive(Args)]
+struct SampleArgs {
+    /// Scan only this many targets, picked at random.
+    #[arg(long, value_name = "N", help = "Scan only N targets picked at random")]
+    random_sample: Option<usize>,
+
+    /// The seed used to pick the sample. A random seed is used, and recorded in the report, if omitted.
+    #[arg(long, value_name = "SEED", help = "Seed for --random-sample, for reproducible scans")]
+    seed: Option<u64>,
+}
+
+impl SampleArgs {
+    /// Apply the sample to `targets`.
+    ///
+    /// Returns the targets to scan and the seed used, which is [None] when no sample was requested.
+    fn apply(&self, targets: Vec<PathBuf>) -> (Vec<PathBuf>, Option<u64>) {
+        match self.random_sample {
+            Some(count) => {
+                let seed = self.seed.unwrap_or_else(random_seed);
+                (sample_targets(targets, count, seed), Some(seed))
+            }
+            None => (targets, None),
+        }
+    }
+}
+
+/// A [Subcommand] enum for the [Command::Scan], [Command::Stats], [Command::Hunt], [Command::Partitions], [Command::Regions], [Command::Blocks], [Command::Ntfs], [Command::Summarize], [Command::CompareStats], [Command::Baseline], [Command::Diff], [Command::Run], [Command::GenFixtures], and [Command::SelfUpdate] subcommands.
 #[derive(Subcommand)]
 enum Command {
     Scan {
+        #[arg(
+            short,
+            long,
+            value_name = "TARGET",
+            help = "Target file or path to scan, or - for standard input; repeat to scan several",
+            required_unless_present_any = ["macos_artifacts", "staging_locations", "all_local_filesystems", "targets_from"]
+        )]
+        /// The target files or paths to scan, merged into one report. `-` scans whatever is piped to standard input as a single file.
+        target: Vec<PathBuf>,
+
+        /// Also scan the macOS locations responders look at first, and group app bundles.
+        #[arg(long, help = "Scan common macOS artifact locations, grouping app bundles")]
+        macos_artifacts: bool,
+
+        /// Also scan the usual exfiltration staging areas (trash, temp, shared memory, downloads, cloud-sync caches), label files found in any staging area, and list them first, archives ahead.
+        #[arg(long, help = "Scan and label exfil staging areas, listing staged archives first")]
+        staging_locations: bool,
+
+        /// Also scan every mounted local filesystem, each without crossing into the others, and tag each file with its mount point. Pseudo filesystems such as `/proc` are never scanned.
+        #[arg(long, help = "Scan every mounted local filesystem, tagging files by mount point")]
+        all_local_filesystems: bool,
+
+        /// Include network filesystems, such as NFS and SMB shares, in `--all-local-filesystems`.
+        #[arg(long, help = "Include network filesystems in --all-local-filesystems", requires = "all_local_filesystems")]
+        network_filesystems: bool,
+
+        /// Also scan the files listed in this file, or on standard input for `-`, one per line, e.g. from `find`. Listed directories are not walked.
+        #[arg(long, value_name = "FILE", help = "Also scan the files listed in FILE, or - for standard input")]
+        targets_from: Option<PathBuf>,
+
+        /// Read the `--targets-from` list as NUL-separated, as written by `find -print0`, so any file name can be listed.
+        #[arg(short = '0', long, help = "Read the --targets-from list as NUL-separated", requires = "targets_from")]
+        null: bool,
+
+        /// Scan a repository in CI: skip vendored, generated, lock, and hidden files unless `--hidden` is given, report files from an entropy of 7.5 unless `--min-entropy` is given, and write SARIF unless `--format` is given.
+        #[arg(long, help = "Scan a repository in CI: skip vendored and lock files, write SARIF")]
+        ci: bool,
+
+        /// Only scan the files added, modified, or renamed in this git commit range, e.g. `origin/main..HEAD`.
+        #[arg(
+            long,
+            value_name = "RANGE",
+            help = "Only scan files changed in a git commit range, e.g. origin/main..HEAD",
+            conflicts_with_all = ["macos_artifacts", "staging_locations", "all_local_filesystems", "targets_from"]
+        )]
+        git_diff: Option<String>,
+
+        /// Check every file against the system package database (dpkg or rpm), flagging unowned and modified files.
+        #[arg(long, help = "Flag files not owned by, or modified since, their dpkg/rpm package")]
+        verify_packages: bool,
+
+        /// Score every file against a baseline JSON scan report, written earlier by `scan --format json`, to catch files unlike the rest.
+        #[arg(long, value_name = "REPORT", help = "Score files for how unusual they are against a baseline JSON report")]
+        anomaly_baseline: Option<PathBuf>,
+
+        /// Group results by bundle (app bundle, `node_modules` or Python package) or by owning system package. `--macos-artifacts` groups by bundle unless told otherwise.
+        #[arg(long, value_name = "GROUP", help = "Group results by: bundle or package")]
+        aggregate_by: Option<AggregateBy>,
+
+        /// Confine the scan before any file is parsed, so it can only read beneath the targets: no writing, deleting, or running anything. Symlinks leading outside the targets are reported as unreadable. Linux only, using Landlock.
+        #[arg(long, help = "Only allow reading beneath the targets once scanning starts (Linux)")]
+        sandbox: bool,
+
+        /// Also stream each finding as a line of JSON to this local socket as soon as it is measured, so an agent such as an EDR can consume results in real time. Each finding carries the `scan_id`, so findings from overlapping scans can be told apart. On Unix this is a listening Unix domain socket or a FIFO, on Windows a named pipe such as `\\.\pipe\entropyscan`.
+        #[arg(long, value_name = "PATH", help = "Stream findings as NDJSON to a Unix socket, FIFO, or named pipe")]
+        emit_socket: Option<PathBuf>,
+
+        /// Also write a numeric feature vector of every scanned file to this Parquet file, for training classifiers: entropy, size, printable ratio, chi-square, the spread of chunk entropies, and a one-hot file type. Each file is read a second time to extract them, so files that can't be, such as standard input and archive members, are left out. Files below `--min-entropy` are kept.
+        #[arg(long, value_name = "FILE", help = "Write a feature vector of every scanned file to FILE as Parquet")]
+        features_out: Option<PathBuf>,
+
+        /// Measure every file, without reading or updating the scan cache. By default files whose size, modification and change times, and inode haven't changed since an earlier scan aren't read again. The change time can't be set back from user space, so a file encrypted in place with its modification time restored is still measured again. It can still miss a change made below the filesystem, e.g. to a raw device or an offline disk image, so use this when every byte must be read. The cache is also off with `--no-persist`, `--forensic`, and `--sandbox`, and on platforms without a change time.
+        #[arg(
+            long,
+            help = "Don't use the scan cache, which skips files whose size, mtime, ctime, and inode are unchanged"
+        )]
+        no_cache: bool,
+
+        /// Measure every file again and replace the scan cache with the results.
+        #[arg(long, help = "Rebuild the scan cache, measuring every file again", conflicts_with = "no_cache")]
+        refresh: bool,
+
+        #[arg(short, long, value_name = "MIN_ENTROPY", help = "Minimum entropy to display")]
+        /// The minimum entropy to display. Files at or above it are reported as findings.
+        min_entropy: Option<f64>,
+
+        /// Exit with 1 if any scanned file's entropy is above this, whether or not it is displayed, to gate CI on committed encrypted blobs and packed binaries.
+        #[arg(long, value_name = "ENTROPY", help = "Exit with 1 if any file's entropy is above ENTROPY")]
+        fail_above: Option<f64>,
+
+        /// Exit with 1 if any scanned file is an entropy outlier among the files scanned, as `stats` reports them.
+        #[arg(long, help = "Exit with 1 if any file is an entropy outlier")]
+        fail_if_outliers: bool,
+
+        /// The entropy calculation options.
+        #[command(flatten)]
+        entropy: EntropyArgs,
+
+        /// The target filtering options.
+        #[command(flatten)]
+        filters: FilterArgs,
+
+        /// The random sampling options.
+        #[command(flatten)]
+        sample: SampleArgs,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Stats {
+        #[arg(short, long, value_name = "TARGET", help = "Target file or path to scan; repeat to scan several", required = true)]
+        /// The target files or paths to scan, whose stats are calculated together.
+        target: Vec<PathBuf>,
+
+        /// Do not print outliers.
+        #[arg(short, help = "Do not print outliers")]
+        no_outliers: bool,
+
+        /// Flag outliers created within this window (e.g. `48h`) separately.
+        #[arg(
+            long,
+            value_name = "WINDOW",
+            help = "Flag outliers created within this window, e.g. 48h",
+            value_parser = parse_duration
+        )]
+        highlight_recent: Option<Duration>,
+
+        /// The entropy calculation options.
+        #[command(flatten)]
+        entropy: EntropyArgs,
+
+        /// The target filtering options.
+        #[command(flatten)]
+        filters: FilterArgs,
+
+        /// The random sampling options.
+        #[command(flatten)]
+        sample: SampleArgs,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Hunt {
+        #[arg(short, long, value_name = "SAMPLE", help = "Sample file to compare against")]
+        /// The sample file whose block-entropy profile is searched for.
+        like: PathBuf,
+
         #[arg(short, long, value_name = "TARGET", help = "Target file or path to scan")]
         /// The target file or path to scan.
         target: PathBuf,
@@ -47,142 +620,977 @@ enum Command {
         #[arg(
             short,
             long,
-            value_name = "MIN_ENTROPY",
-            help = "Minimum entropy to display",
-            default_value = "0.0"
+            value_name = "BLOCK_SIZE",
+            help = "Block size in bytes for entropy profiles",
+            default_value = "4096"
+        )]
+        /// The block size in bytes used to build entropy profiles.
+        block_size: usize,
+
+        /// Build profiles from FastCDC content-defined chunks averaging the block size instead of fixed blocks, so files that differ by inserted or removed bytes still line up, see [entropy_scan::cdc].
+        #[arg(long, help = "Use content-defined chunks averaging BLOCK_SIZE bytes instead of fixed blocks")]
+        content_defined: bool,
+
+        #[arg(short, long, value_name = "MIN_SIMILARITY", help = "Minimum similarity to display")]
+        /// The minimum similarity to display. Files at or above it are reported as findings.
+        min_similarity: Option<f64>,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Partitions {
+        #[arg(short, long, value_name = "TARGET", help = "Disk or disk image to read")]
+        /// The disk or disk image whose partition table is read.
+        target: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "BLOCK_SIZE",
+            help = "Block size in bytes for partition entropy maps",
+            default_value = "1048576"
+        )]
+        /// The block size in bytes used to build each partition's entropy map.
+        block_size: usize,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Regions {
+        #[arg(short, long, value_name = "TARGET", help = "File to map, e.g. a swap or hibernation file")]
+        /// The file to map, of any size.
+        target: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "BLOCK_SIZE",
+            help = "Block size in bytes for the region map",
+            default_value = "65536"
         )]
-        /// The minimum entropy to display.
+        /// The block size in bytes regions are built from.
+        block_size: usize,
+
+        /// Pick the block size from the file's size and type instead, see [entropy_scan::chunking::adaptive_chunk_size].
+        #[arg(long, conflicts_with = "block_size", help = "Pick the block size from the file's size and type")]
+        adaptive_chunks: bool,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Blocks {
+        #[arg(short, long, value_name = "TARGET", help = "File to measure window by window")]
+        /// The file whose windows are measured.
+        target: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "WINDOW",
+            help = "Window size in bytes",
+            default_value = "4096"
+        )]
+        /// The size of each window in bytes.
+        window: usize,
+
+        /// Pick the window size from the file's size and type instead, see [entropy_scan::chunking::adaptive_chunk_size].
+        #[arg(long, conflicts_with = "window", help = "Pick the window size from the file's size and type")]
+        adaptive_chunks: bool,
+
+        /// Measure FastCDC content-defined chunks averaging the window size instead of fixed windows, so the chunks of files that differ by inserted or removed bytes still line up, see [entropy_scan::cdc].
+        #[arg(long, conflicts_with = "stride", help = "Measure content-defined chunks averaging WINDOW bytes instead")]
+        content_defined: bool,
+
+        #[arg(short, long, value_name = "STRIDE", help = "Distance in bytes between windows [default: WINDOW]")]
+        /// The distance in bytes between the starts of neighbouring windows. Defaults to the window size, so windows don't overlap.
+        stride: Option<usize>,
+
+        #[arg(short, long, value_name = "MIN_ENTROPY", help = "Minimum window entropy to display")]
+        /// The minimum window entropy to display. Windows at or above it are reported as findings.
         min_entropy: Option<f64>,
 
-        /// The output format. Valid values are [OutputFormat::Csv], [OutputFormat::Json], and [OutputFormat::Table]. Default is [OutputFormat::Table].
-        #[arg(short, long, value_name = "FORMAT", help = "Output format", default_value = "table")]
-        format: OutputFormat,
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
     },
-    Stats {
-        #[arg(short, long, value_name = "TARGET", help = "Target file or path to scan")]
-        /// The target file or path to scan.
+    Ntfs {
+        #[arg(short, long, value_name = "TARGET", help = "Raw NTFS volume or disk image")]
+        /// The raw NTFS volume, or disk image holding one.
         target: PathBuf,
 
-        /// Do not print outliers.
-        #[arg(short, help = "Do not print outliers")]
-        no_outliers: bool,
+        /// Where the NTFS volume starts in the image, in bytes, e.g. a partition start listed by `partitions`.
+        #[arg(long, value_name = "BYTES", help = "Byte offset of the NTFS volume in the image", default_value = "0")]
+        offset: u64,
 
-        /// The output format. Valid values are [OutputFormat::Csv], [OutputFormat::Json], and [OutputFormat::Table]. Default is [OutputFormat::Table].
-        #[arg(short, long, value_name = "FORMAT", help = "Output format", default_value = "table")]
-        format: OutputFormat,
+        /// Measure data stored inside the file records themselves. Both resident data and slack are measured unless one of `--resident`, `--slack`, or `--unallocated` is asked for.
+        #[arg(long, help = "Measure file data stored inside MFT records")]
+        resident: bool,
+
+        /// Measure file slack, the unused tail of each file's last cluster.
+        #[arg(long, help = "Measure the slack space after the end of each file")]
+        slack: bool,
+
+        /// Map the entropy of the clusters no file owns, in chunks of up to 1MB, to find encrypted remnants and wiped space before carving.
+        #[arg(long, help = "Map the entropy of the volume's unallocated clusters")]
+        unallocated: bool,
+
+        #[arg(short, long, value_name = "MIN_ENTROPY", help = "Minimum entropy to display")]
+        /// The minimum entropy to display. Data at or above it is reported as a finding.
+        min_entropy: Option<f64>,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Summarize {
+        #[arg(value_name = "REPORT", help = "JSON report written by scan --format json")]
+        /// The JSON scan report to summarize.
+        report: PathBuf,
+
+        #[arg(long, value_name = "TEMPLATE", help = "Template to render the summary with")]
+        /// The template to render the summary with. A built-in Markdown template is used if omitted.
+        template: Option<PathBuf>,
+
+        #[arg(long, value_name = "TOP", help = "Number of top findings to list", default_value = "10")]
+        /// The number of highest-entropy files to list.
+        top: usize,
+    },
+    CompareStats {
+        #[arg(value_name = "BEFORE", help = "JSON report written by scan --format json before")]
+        /// The earlier JSON scan report.
+        before: PathBuf,
+
+        #[arg(value_name = "AFTER", help = "JSON report written by scan --format json after")]
+        /// The later JSON scan report.
+        after: PathBuf,
+
+        /// The p-value below which the entropy distributions are reported as materially different.
+        #[arg(long, value_name = "P", help = "Significance level for a material change", default_value = "0.05")]
+        significance: f64,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Baseline {
+        #[arg(short, long, value_name = "TARGET", help = "Target file or path to record; repeat to record several", required = true)]
+        /// The target files or paths to record.
+        target: Vec<PathBuf>,
+
+        #[arg(long, value_name = "FILE", help = "File to write the baseline to")]
+        /// The file to write the baseline to, as a JSON scan report.
+        out: PathBuf,
+
+        /// The entropy calculation options.
+        #[command(flatten)]
+        entropy: EntropyArgs,
+
+        /// The target filtering options.
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+    Diff {
+        #[arg(long, value_name = "FILE", help = "Baseline written by the baseline command")]
+        /// The baseline to diff against.
+        baseline: PathBuf,
+
+        #[arg(short, long, value_name = "TARGET", help = "Target file or path to scan; repeat to scan several", required = true)]
+        /// The target files or paths to scan, the same ones the baseline was recorded from.
+        target: Vec<PathBuf>,
+
+        /// How far, in bits per symbol, a file's entropy has to move to be reported as changed.
+        #[arg(long, value_name = "BITS", help = "Report files whose entropy moved by more than BITS", default_value = "1.0")]
+        delta: f64,
+
+        /// The entropy calculation options.
+        #[command(flatten)]
+        entropy: EntropyArgs,
+
+        /// The target filtering options.
+        #[command(flatten)]
+        filters: FilterArgs,
+
+        /// The output formats and files.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Run {
+        #[arg(value_name = "JOB", help = "YAML job file listing the targets to scan")]
+        /// The YAML job file listing the targets, their options and outputs, and where to send the summary.
+        job: PathBuf,
+
+        /// The output formats and files for the consolidated summary.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    GenFixtures {
+        #[arg(short, long, value_name = "DIR", help = "Directory to write the test corpus to")]
+        /// The directory to write the test corpus to. It is created if missing.
+        output: PathBuf,
+    },
+    SelfUpdate {
+        #[arg(long, value_name = "SOURCE", help = "Release directory, file:// URL, or http(s):// URL")]
+        /// The release source: a directory, `file://` URL, or `http://` or `https://` URL holding the signed `latest.json` and the binaries it lists.
+        source: String,
+
+        #[arg(long, value_name = "HEX", help = "Ed25519 public key releases are signed with, as hex")]
+        /// The Ed25519 public key releases must be signed with. Defaults to the key pinned at build time with `ENTROPYSCAN_RELEASE_KEY`.
+        public_key: Option<String>,
+
+        #[arg(long, help = "Only report whether an update is available")]
+        /// Only check for a newer release, for change management. Exits with 1 if there is one. The manifest's signature is still checked.
+        check_only: bool,
     },
 }
 
-fn main() -> Result<(), String> {
+impl Command {
+    /// Whether the command reads files, and so has a scan ID worth printing. Commands that only read reports or maintain the tool don't.
+    fn scans(&self) -> bool {
+        !matches!(self, Command::Summarize { .. } | Command::CompareStats { .. } | Command::GenFixtures { .. } | Command::SelfUpdate { .. })
+    }
+}
+
+/// The symbol width to record in a report, which is [None] for the default of 8 bits.
+fn symbol_width_bits(options: &ScanOptions) -> Option<u8> {
+    match options.symbol_width {
+        SymbolWidth::Byte => None,
+        width => Some(width.bits()),
+    }
+}
+
+/// Report every target that wasn't scanned on stderr, unless `quiet` is set.
+///
+/// Returns the number of targets that couldn't be scanned because of an error, leaving out those skipped on purpose.
+fn report_unscanned(unscanned: &[Unscanned], quiet: bool) -> usize {
+    if !quiet {
+        for target in unscanned {
+            let verb = match target.skipped {
+                true => "Skipped",
+                false => "Couldn't scan",
+            };
+            eprintln!("{verb} {}: {}", target.path.to_string_lossy(), target.reason);
+        }
+    }
+    unscanned
+        .iter()
+        .filter(|target| !target.skipped)
+        .count()
+}
+
+/// Check that a target given on the command line exists.
+fn check_target(target: &Path) -> Result<(), String> {
+    match target.exists() {
+        true => Ok(()),
+        false => Err(format!("Target not found: {}", target.to_string_lossy())),
+    }
+}
+
+/// Pick the chunk size for `target`: `fixed`, or the adaptive size if `adaptive` is set, which is noted on stderr unless `quiet` is set.
+fn chunk_size(target: &Path, fixed: usize, adaptive: bool, quiet: bool) -> Result<usize, String> {
+    if !adaptive {
+        return Ok(fixed);
+    }
+    let size = chunk_size_for(target)?;
+    if !quiet {
+        eprintln!("Adaptive chunk size for {}: {size} bytes", target.to_string_lossy());
+    }
+    Ok(size)
+}
+
+/// Check that no `outputs` file would be written inside any of `roots`, as forensic scans promise.
+fn check_outputs_outside(outputs: &[PathBuf], roots: &[PathBuf]) -> Result<(), String> {
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect();
+    for output in outputs {
+        // The file may not exist yet, but its directory must.
+        let parent = match output.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let Ok(parent) = parent.canonicalize() else {
+            continue;
+        };
+        let resolved = parent.join(output.file_name().unwrap_or_default());
+        if roots.iter().any(|root| resolved.starts_with(root)) {
+            return Err(format!("--forensic won't write {} inside a target", output.to_string_lossy()));
+        }
+    }
+    Ok(())
+}
+
+/// Scan `targets` for `baseline` and `diff`. Targets are made absolute first, so a baseline and a later diff match up wherever each was run from.
+///
+/// Returns the entropies and the number of files that couldn't be scanned, or an error message if a target doesn't exist.
+fn scan_absolute(
+    targets: &[PathBuf],
+    entropy: &EntropyArgs,
+    filters: &FilterArgs,
+    quiet: bool
+) -> Result<(Vec<FileEntropy>, usize), String> {
+    let mut absolute = Vec::with_capacity(targets.len());
+    for target in targets {
+        check_target(target)?;
+        absolute.push(target.canonicalize().map_err(|e| format!("Couldn't resolve {}: {e}", target.to_string_lossy()))?);
+    }
+    if entropy.forensic {
+        preserve_access_times();
+    }
+    let filter = filters.filter();
+    let collected = absolute
+        .iter()
+        .flat_map(|target| collect_targets(target.clone(), &filter))
+        .collect();
+    let (entropies, unscanned) = collect_entropies(&distinct(collected), &entropy.options());
+    Ok((entropies, report_unscanned(&unscanned, quiet)))
+}
+
+/// Drop repeated targets, such as a file inside two overlapping target directories, keeping the first of each.
+fn distinct(targets: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    targets
+        .into_iter()
+        .filter(|target| seen.insert(target.clone()))
+        .collect()
+}
+
+/// Join several targets into one, separated like the entries of `PATH`, for reports that name a single target.
+fn joined_targets(targets: &[PathBuf]) -> PathBuf {
+    match env::join_paths(targets) {
+        Ok(joined) => PathBuf::from(joined),
+        Err(_) => {
+            let names: Vec<String> = targets
+                .iter()
+                .map(|target| target.to_string_lossy().to_string())
+                .collect();
+            PathBuf::from(names.join(" "))
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Cli::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            // Usage errors are fatal, but --help and --version are not errors at all.
+            let _ = e.print();
+            return match e.use_stderr() {
+                true => ExitCode::from(FATAL),
+                false => ExitCode::SUCCESS,
+            };
+        }
+    };
+    match run(args) {
+        Ok(status) => status.into(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(FATAL)
+        }
+    }
+}
+
+/// Run the parsed command.
+///
+/// Returns the [Status] of a command that ran to completion, or an error message if it couldn't run.
+fn run(args: Cli) -> Result<Status, String> {
     use Command::*;
-    use OutputFormat::*;
 
-    let args = Cli::parse();
+    let quiet = args.quiet;
+    let scan_id = new_scan_id()?;
+    if !quiet && args.command.scans() {
+        eprintln!("scan_id={scan_id}");
+    }
 
     match args.command {
-        Scan { target, min_entropy, format } => {
-            let parent_path_buf = target;
-            let min_entropy = min_entropy.unwrap();
-            let targets = collect_targets(parent_path_buf);
-            let entropies: Vec<FileEntropy> = collect_entropies(&targets)
-                .into_iter()
-                .filter(|e| e.entropy >= min_entropy)
-                .collect();
+        Scan {
+            target,
+            macos_artifacts,
+            staging_locations,
+            all_local_filesystems,
+            network_filesystems,
+            targets_from,
+            null,
+            ci,
+            git_diff,
+            verify_packages,
+            anomaly_baseline,
+            aggregate_by,
+            sandbox,
+            emit_socket,
+            features_out,
+            no_cache,
+            refresh,
+            min_entropy,
+            fail_above,
+            fail_if_outliers,
+            entropy,
+            filters,
+            sample,
+            mut output,
+        } => {
+            let started = Instant::now();
+            let mut roots = Vec::new();
+            let stdin = target.iter().any(|target| target == Path::new(STDIN_TARGET));
+            if stdin && git_diff.is_some() {
+                return Err("Standard input can't be scanned with --git-diff".to_string());
+            }
+            let list_stdin = targets_from.as_deref() == Some(Path::new(STDIN_TARGET));
+            if stdin && list_stdin {
+                return Err("Standard input can't be both a target and the --targets-from list".to_string());
+            }
+            for target in target.into_iter().filter(|target| target != Path::new(STDIN_TARGET)) {
+                check_target(&target)?;
+                roots.push(target);
+            }
+            if macos_artifacts {
+                roots.extend(preset_targets(MACOS_ARTIFACTS));
+                if roots.is_empty() {
+                    return Err("None of the macOS artifact locations exist".to_string());
+                }
+            }
+            if staging_locations {
+                roots.extend(preset_targets(STAGING_LOCATIONS));
+                if roots.is_empty() {
+                    return Err("None of the staging locations exist".to_string());
+                }
+            }
+            // Each filesystem is walked on its own, so no other mount point is walked into.
+            let mut mount_points = Vec::new();
+            let mut other_mounts = Vec::new();
+            if all_local_filesystems {
+                let mounted = mounts()?;
+                mount_points = scan_roots(&mounted, network_filesystems);
+                other_mounts = mounted
+                    .into_iter()
+                    .map(|mount| mount.point)
+                    .collect();
+                roots.extend(mount_points.iter().cloned());
+            }
+            let aggregate_by = aggregate_by.or(macos_artifacts.then_some(AggregateBy::Bundle));
+            if sandbox && aggregate_by == Some(AggregateBy::Package) {
+                return Err("--sandbox can't be combined with --aggregate-by package, which reads the package database after scanning".to_string());
+            }
+            let min_entropy = min_entropy.or(ci.then_some(CI_MIN_ENTROPY));
+            if ci && output.format.is_empty() {
+                output.format.push(OutputFormat::Sarif);
+            }
+            if entropy.forensic {
+                preserve_access_times();
+            }
+            let threshold = min_entropy.unwrap_or(0.0);
+            let mut filter = filters.filter();
+            if ci {
+                filter.skip_names.extend(CI_SKIP_NAMES.iter().map(|name| name.to_string()));
+                filter.skip_hidden = !filters.hidden;
+            }
+            filter.skip_paths = other_mounts;
+            let mut collected = Vec::new();
+            for root in &roots {
+                match &git_diff {
+                    Some(range) => collected.extend(changed_targets(root, range, &filter)?),
+                    None => collected.extend(collect_targets(root.clone(), &filter)),
+                }
+            }
+            if let Some(list) = &targets_from {
+                let error = |e: io::Error| format!("Couldn't read target list {}: {e}", list.to_string_lossy());
+                let listed = match list_stdin {
+                    true => read_target_list(io::stdin().lock(), null, &filter).map_err(error)?,
+                    false => read_target_list(File::open(list).map_err(error)?, null, &filter).map_err(error)?,
+                };
+                // Listed files may be anywhere, so each one is a root of its own for the sandbox and the forensic output check.
+                roots.extend(listed.iter().filter(|path| path.exists()).cloned());
+                collected.extend(listed);
+            }
+            // Only once every root is known, listed files included, and before any output is created.
+            if entropy.forensic {
+                let outputs: Vec<PathBuf> = output.output
+                    .iter()
+                    .chain(&features_out)
+                    .cloned()
+                    .collect();
+                check_outputs_outside(&outputs, &roots)?;
+            }
+            let mut destinations = output.destinations(quiet)?;
+            let (targets, seed) = sample.apply(distinct(collected));
+            let mut options = entropy.options();
+            // A forensic or sandboxed scan measures everything afresh and writes nothing outside its reports.
+            let cached = !no_cache && !options.no_persist && !entropy.forensic && !sandbox;
+            options.cache = cached
+                .then(default_cache_path)
+                .flatten()
+                .map(|path| Arc::new(ScanCache::load(&path, refresh)));
+            let mut meta = ScanMeta {
+                scan_id,
+                seed,
+                symbol_width: symbol_width_bits(&options),
+                duration_ms: None,
+                provenance: entropy.provenance()?,
+            };
+            let packages = verify_packages.then(|| PackageDb::load(&targets, true));
+            let model = match &anomaly_baseline {
+                Some(report) => Some(AnomalyModel::load(report, meta.symbol_width)?),
+                None => None,
+            };
 
-            match format {
-                Csv => {
-                    println!("-----Entropies-----");
-                    println!("path,entropy");
-                    for item in entropies {
-                        println!("{},{:.3}", item.path.to_string_lossy(), item.entropy);
+            // Only keep every result in memory when a non-streaming format needs it. Grouped and prioritized results are never streamed.
+            let streaming = aggregate_by.is_none() && !staging_locations;
+            let streamed = |format: &OutputFormat| streaming && matches!(format, OutputFormat::TableStream | OutputFormat::Ndjson);
+            let buffered = destinations
+                .iter()
+                .any(|(format, _)| !streamed(format));
+            for (format, out) in destinations.iter_mut() {
+                if streaming && matches!(format, OutputFormat::TableStream) {
+                    stream_scan_header(out, &output).map_err(|e| e.to_string())?;
+                }
+            }
+
+            let mut emitter = match &emit_socket {
+                Some(path) => Some(open_emit_socket(path)?),
+                None => None,
+            };
+            let mut features = match &features_out {
+                Some(path) => Some(FeatureWriter::create(path)?),
+                None => None,
+            };
+
+            // Everything outside the targets is read by now, and output files and the socket are already open.
+            if sandbox {
+                confine(&roots)?;
+            }
+
+            let mut entropies: Vec<FileEntropy> = Vec::new();
+            let mut stream_error = None;
+            let mut found = 0;
+            let mut suspicious = 0;
+            let mut above = 0;
+            let mut measured = Vec::new();
+            let mut on_entropy = |mut entropy: FileEntropy| {
+                above += fail_above.is_some_and(|limit| entropy.entropy > limit) as usize;
+                if let Some(writer) = features.as_mut().filter(|_| entropy.path != Path::new(STDIN_TARGET)) {
+                    match file_features(&entropy) {
+                        Ok(row) => {
+                            if let Err(e) = writer.push(row) {
+                                stream_error.get_or_insert(e);
+                            }
+                        }
+                        Err(e) if !quiet => eprintln!("Left out of --features-out: {e}"),
+                        Err(_) => (),
                     }
                 }
-                Json => {
-                    let json = serde_json::to_string_pretty(&entropies).unwrap();
-                    print!("{}", json);
+                if fail_if_outliers {
+                    measured.push(entropy.clone());
+                }
+                if entropy.entropy < threshold {
+                    return;
                 }
-                Table => {
-                    println!("-----Entropies-----");
-                    let table = tabled::Table::new(entropies).to_string();
-                    print!("{table}");
+                found += 1;
+                if let Some(packages) = &packages {
+                    let (package, status) = packages.verify(&entropy.path);
+                    suspicious += matches!(status, "unowned" | "modified") as usize;
+                    entropy.package = package;
+                    entropy.package_status = Some(status.to_string());
                 }
+                if let Some(model) = &model {
+                    entropy.anomaly_score = Some(model.score(&entropy));
+                }
+                if staging_locations {
+                    entropy.staging = staging_label(&entropy.path).map(str::to_string);
+                }
+                if all_local_filesystems {
+                    entropy.mount_point = mount_point_of(&entropy.path, &mount_points).map(|point|
+                        point.to_string_lossy().to_string()
+                    );
+                }
+                for (format, out) in destinations.iter_mut() {
+                    let written = match format {
+                        OutputFormat::TableStream if streaming => stream_scan_row(out, &output, &entropy),
+                        OutputFormat::Ndjson if streaming => emit_finding(out, &meta.scan_id, &entropy),
+                        _ => Ok(()),
+                    };
+                    if let Err(e) = written {
+                        stream_error.get_or_insert(e.to_string());
+                    }
+                }
+                if let Some(out) = emitter.as_mut() {
+                    if let Err(e) = emit_finding(out, &meta.scan_id, &entropy) {
+                        stream_error.get_or_insert(format!("Couldn't write to --emit-socket: {e}"));
+                    }
+                }
+                if buffered {
+                    entropies.push(entropy);
+                }
+            };
+            // Standard input is read first, as a single file. Archive members and sections need a file on disk.
+            let mut unscanned = Vec::new();
+            if stdin {
+                let path = Path::new(STDIN_TARGET);
+                match entropy_of_reader(path, io::stdin().lock(), &options) {
+                    Ok(entropy) => on_entropy(entropy),
+                    Err(reason) => unscanned.push(Unscanned { path: path.to_path_buf(), reason, skipped: false }),
+                }
+            }
+            unscanned.extend(for_each_entropy(&targets, &options, &mut on_entropy));
+            if let Some(e) = stream_error {
+                return Err(e);
+            }
+            if let Some(writer) = features {
+                writer.finish()?;
+            }
+            let failed = report_unscanned(&unscanned, quiet);
+            if staging_locations {
+                entropies = prioritize(entropies);
+            }
+            if options.timings {
+                meta.duration_ms = Some(started.elapsed().as_millis() as u64);
             }
 
-            Ok(())
+            match aggregate_by {
+                Some(by) => {
+                    let groups = aggregate(&entropies, by);
+                    for (format, mut out) in destinations {
+                        render_aggregates(&mut out, &format, &output, &meta, &groups).map_err(|e|
+                            e.to_string()
+                        )?;
+                    }
+                }
+                None => {
+                    for (format, mut out) in destinations {
+                        if streaming && matches!(format, OutputFormat::Ndjson) {
+                            continue;
+                        }
+                        render_scan(&mut out, &format, &output, &meta, &entropies).map_err(|e|
+                            e.to_string()
+                        )?;
+                    }
+                }
+            }
+
+            if let Some(cache) = &options.cache {
+                if let Err(e) = cache.save() {
+                    if !quiet {
+                        eprintln!("{e}");
+                    }
+                }
+            }
+
+            let outliers = match fail_if_outliers {
+                true => entropy_outliers(&measured).map_or(0, |outliers| outliers.len()),
+                false => 0,
+            };
+            if !quiet {
+                if let Some(limit) = fail_above.filter(|_| above > 0) {
+                    eprintln!("{above} files above --fail-above {limit}");
+                }
+                if outliers > 0 {
+                    eprintln!("{outliers} entropy outliers with --fail-if-outliers");
+                }
+            }
+
+            let findings = (min_entropy.is_some() && found > 0) || suspicious > 0 || above > 0 || outliers > 0;
+            Ok(Status::of(findings, failed))
         }
 
-        Stats { target, no_outliers, format } => {
-            let targets = collect_targets(target.clone());
-            let entropies = collect_entropies(&targets);
+        Stats { target, no_outliers, highlight_recent, entropy, filters, sample, output } => {
+            let started = Instant::now();
+            for target in &target {
+                check_target(target)?;
+            }
+            if entropy.forensic {
+                check_outputs_outside(&output.output, &target)?;
+                preserve_access_times();
+            }
+            let destinations = output.destinations(quiet)?;
+            let recent_since = highlight_recent.map(|window| {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                now.saturating_sub(window).as_secs()
+            });
+            let filter = filters.filter();
+            let collected = target
+                .iter()
+                .flat_map(|target| collect_targets(target.clone(), &filter))
+                .collect();
+            let (targets, seed) = sample.apply(distinct(collected));
+            let options = entropy.options();
+            let (entropies, unscanned) = collect_entropies(&targets, &options);
+            let failed = report_unscanned(&unscanned, quiet);
+            if entropies.is_empty() {
+                return Err("No files to compute stats for".to_string());
+            }
+            let meta = ScanMeta {
+                scan_id,
+                seed,
+                symbol_width: symbol_width_bits(&options),
+                duration_ms: options.timings.then(|| started.elapsed().as_millis() as u64),
+                provenance: entropy.provenance()?,
+            };
             let stats = entropy_scan::structs::Stats {
-                target,
+                target: joined_targets(&target),
                 total: targets.len(),
                 mean: mean(&entropies).unwrap(),
                 median: median(&entropies).unwrap(),
                 variance: variance(&entropies).unwrap(),
-                iqr: interquartile_range(&entropies).unwrap().range,
-            };
-
-            match format {
-                Csv => {
-                    println!("-----Stats-----");
-                    println!("target,total,mean,median,variance,iqr");
-                    println!(
-                        "{},{},{:.3},{:.3},{:.3},{:.3}",
-                        stats.target.to_string_lossy(),
-                        stats.total,
-                        stats.mean,
-                        stats.median,
-                        stats.variance,
-                        stats.iqr
-                    );
-                    match no_outliers {
-                        true => (),
-                        false => {
-                            let outliers = entropy_outliers(&entropies).unwrap();
-                            println!("\n-----Outliers-----");
-                            println!("path,entropy");
-                            for item in outliers {
-                                println!("{},{:.3}", item.path.to_string_lossy(), item.entropy);
-                            }
-                        }
-                    }
+                iqr: interquartile_range(&entropies).map(|iqr| iqr.range),
+            };
+            let outliers = match no_outliers {
+                true => None,
+                false => entropy_outliers(&entropies),
+            };
+            let recent = match (&outliers, recent_since) {
+                (Some(outliers), Some(since)) => Some(created_since(outliers, since)),
+                _ => None,
+            };
+
+            for (format, mut out) in destinations {
+                render_stats(
+                    &mut out,
+                    &format,
+                    &output,
+                    &meta,
+                    &stats,
+                    outliers.as_deref(),
+                    recent.as_deref()
+                ).map_err(
+                    |e| e.to_string()
+                )?;
+            }
+
+            let findings = outliers.is_some_and(|outliers| !outliers.is_empty());
+            Ok(Status::of(findings, failed))
+        }
+
+        Hunt { like, target, block_size, content_defined, min_similarity, output } => {
+            check_target(&target)?;
+            let destinations = output.destinations(quiet)?;
+            let sample = match content_defined {
+                true => chunk_profile(&like, block_size)?,
+                false => block_profile(&like, block_size)?,
+            };
+            if sample.is_empty() {
+                return Err("Sample file is empty".to_string());
+            }
+            let targets = collect_targets(target, &TargetFilter::default());
+            let (ranked, failed) = rank_by_similarity(&sample, &targets, block_size, content_defined);
+            let matches: Vec<_> = ranked
+                .into_iter()
+                .filter(|m| m.similarity >= min_similarity.unwrap_or(0.0))
+                .collect();
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_hunt(&mut out, &format, &output, &meta, &matches).map_err(|e| e.to_string())?;
+            }
+
+            Ok(Status::of(min_similarity.is_some() && !matches.is_empty(), failed))
+        }
+
+        Partitions { target, block_size, output } => {
+            check_target(&target)?;
+            let destinations = output.destinations(quiet)?;
+            let partitions = scan_partitions(&target, block_size)?;
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_partitions(&mut out, &format, &output, &meta, &partitions).map_err(|e|
+                    e.to_string()
+                )?;
+            }
+
+            let findings = partitions.iter().any(|partition| partition.likely_encrypted);
+            Ok(Status::of(findings, 0))
+        }
+
+        Regions { target, block_size, adaptive_chunks, output } => {
+            check_target(&target)?;
+            let destinations = output.destinations(quiet)?;
+            let block_size = chunk_size(&target, block_size, adaptive_chunks, quiet)?;
+            let map = region_map(&target, block_size)?;
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_regions(&mut out, &format, &output, &meta, &map).map_err(|e| e.to_string())?;
+            }
+
+            Ok(Status::Clean)
+        }
+
+        Blocks { target, window, adaptive_chunks, content_defined, stride, min_entropy, output } => {
+            check_target(&target)?;
+            let destinations = output.destinations(quiet)?;
+            let window = chunk_size(&target, window, adaptive_chunks, quiet)?;
+            let windows = match content_defined {
+                true => chunk_entropy(&target, window)?,
+                false => sliding_entropy(&target, window, stride.unwrap_or(window))?,
+            };
+            let windows: Vec<_> = windows
+                .into_iter()
+                .filter(|w| w.entropy >= min_entropy.unwrap_or(0.0))
+                .collect();
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_windows(&mut out, &format, &output, &meta, &target, &windows).map_err(|e|
+                    e.to_string()
+                )?;
+            }
+
+            Ok(Status::of(min_entropy.is_some() && !windows.is_empty(), 0))
+        }
+
+        Ntfs { target, offset, resident, slack, unallocated: free, min_entropy, output } => {
+            check_target(&target)?;
+            let destinations = output.destinations(quiet)?;
+            let both = !resident && !slack && !free;
+            let mut found = Vec::new();
+            if resident || slack || both {
+                found = mft_data(&target, offset, resident || both, slack || both)?;
+            }
+            if free {
+                found.extend(unallocated(&target, offset)?);
+            }
+            let found: Vec<_> = found
+                .into_iter()
+                .filter(|data| data.entropy >= min_entropy.unwrap_or(0.0))
+                .collect();
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_mft(&mut out, &format, &output, &meta, &target, &found).map_err(|e| e.to_string())?;
+            }
+
+            Ok(Status::of(min_entropy.is_some() && !found.is_empty(), 0))
+        }
+
+        Summarize { report, template, top } => {
+            let summary = summarize(&report, template.as_ref(), top)?;
+            print!("{summary}");
+
+            Ok(Status::Clean)
+        }
+
+        CompareStats { before, after, significance, output } => {
+            let destinations = output.destinations(quiet)?;
+            let comparison = compare_reports(&before, &after, significance)?;
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_comparison(&mut out, &format, &output, &meta, &comparison).map_err(|e| e.to_string())?;
+            }
+
+            Ok(Status::of(comparison.changed, 0))
+        }
+
+        Baseline { target, out, entropy, filters } => {
+            if entropy.forensic {
+                check_outputs_outside(std::slice::from_ref(&out), &target)?;
+            }
+            let (entropies, failed) = scan_absolute(&target, &entropy, &filters, quiet)?;
+            let options = entropy.options();
+            let meta = ScanMeta {
+                scan_id,
+                seed: None,
+                symbol_width: symbol_width_bits(&options),
+                duration_ms: None,
+                provenance: entropy.provenance()?,
+            };
+            write_baseline(&out, &meta, &entropies)?;
+            if !quiet {
+                eprintln!("Recorded {} files in {}", entropies.len(), out.to_string_lossy());
+            }
+
+            Ok(Status::of(false, failed))
+        }
+
+        Diff { baseline, target, delta, entropy, filters, output } => {
+            if entropy.forensic {
+                check_outputs_outside(&output.output, &target)?;
+            }
+            let destinations = output.destinations(quiet)?;
+            let (entropies, failed) = scan_absolute(&target, &entropy, &filters, quiet)?;
+            let diff = diff_against_baseline(&baseline, &entropies, delta)?;
+
+            let options = entropy.options();
+            let meta = ScanMeta {
+                scan_id,
+                seed: None,
+                symbol_width: symbol_width_bits(&options),
+                duration_ms: None,
+                provenance: entropy.provenance()?,
+            };
+            for (format, mut out) in destinations {
+                render_diff(&mut out, &format, &output, &meta, &diff).map_err(|e| e.to_string())?;
+            }
+
+            Ok(Status::of(!diff.changes.is_empty(), failed))
+        }
+
+        Run { job: path, output } => {
+            let destinations = output.destinations(quiet)?;
+            let job = read_job(&path)?;
+            let mut summary = run_job(&path, &job, quiet)?;
+            summary.suppressed = suppression(&job, SystemTime::now());
+
+            let meta = ScanMeta { scan_id, seed: None, symbol_width: None, duration_ms: None, provenance: None };
+            for (format, mut out) in destinations {
+                render_job(&mut out, &format, &output, &meta, &summary).map_err(|e| e.to_string())?;
+            }
+            let undelivered = match &summary.suppressed {
+                Some(_) => Vec::new(),
+                None => notify(&job, &meta, &summary),
+            };
+            if !quiet {
+                if let Some(reason) = &summary.suppressed {
+                    eprintln!("Notifications suppressed: {reason}");
+                }
+                for e in &undelivered {
+                    eprintln!("{e}");
                 }
+            }
 
-                Json => {
-                    let json = json!(&stats);
-                    match no_outliers {
-                        true => (),
-                        false => {
-                            let outliers = entropy_outliers(&entropies).unwrap();
-                            let json_string =
-                                json![{
-                                "stats": &stats,
-                                "outliers": &outliers,
-                        }];
-                            println!("{}", json_string);
-                        }
-                    }
-                    print!("{}", json);
-                }
-
-                Table => {
-                    println!("-----Entropies-----");
-                    let table = tabled::Table::new(vec![stats]);
-                    println!("{table}");
-                    match no_outliers {
-                        true => (),
-                        false => {
-                            let outliers = entropy_outliers(&entropies).unwrap();
-                            println!("\n-----Outliers-----");
-                            let table = tabled::Table::new(outliers);
-                            println!("{table}");
-                        }
-                    }
+            let failed = summary.count("errors") + summary.count("failed") + undelivered.len();
+            Ok(Status::of(summary.count("findings") > 0, failed))
+        }
+
+        GenFixtures { output } => {
+            for path in generate_fixtures(&output)? {
+                if !quiet {
+                    println!("{}", path.to_string_lossy());
+                }
+            }
+
+            Ok(Status::Clean)
+        }
+
+        SelfUpdate { source, public_key, check_only } => {
+            let current = env!("CARGO_PKG_VERSION");
+            let public_key = public_key
+                .as_deref()
+                .or(option_env!("ENTROPYSCAN_RELEASE_KEY"))
+                .ok_or("No release public key: pass --public-key")?;
+            let public_key = parse_public_key(public_key)?;
+            let Some(release) = check_for_update(&source, &public_key)? else {
+                if !quiet {
+                    println!("entropyscan {current} is up to date");
+                }
+                return Ok(Status::Clean);
+            };
+            if check_only {
+                if !quiet {
+                    println!("Update available: {current} -> {}", release.version);
                 }
+                return Ok(Status::Findings);
+            }
+
+            let exe = env::current_exe().map_err(|e| format!("Couldn't find the running binary: {e}"))?;
+            install_update(&source, &release, &exe)?;
+            if !quiet {
+                println!("Updated {} from {current} to {}", exe.to_string_lossy(), release.version);
             }
 
-            Ok(())
+            Ok(Status::Clean)
         }
     }
 }
@@ -0,0 +1,1457 @@
+//! Contains the logic for rendering reports in the supported [OutputFormat]s.
+//!
+//! [OutputArgs] holds the repeatable `--format`/`--output` pairs shared by every subcommand, so one scan can produce several reports.
+//!
+//! The [render_scan], [render_stats], [render_hunt], and the other `render_` functions write a report to any [Write]r.
+//!
+//! With `--human`, tables show sizes, counts, and durations in a readable form while CSV and JSON keep raw numbers.
+//!
+//! [render_diff] writes how a tree changed since a baseline scan, for `diff`, and [render_job] the consolidated summary of a batch job, for `run`.
+//!
+//! [OutputFormat::TableStream] prints scan rows as they are produced using fixed column widths, see [stream_scan_header] and [stream_scan_row]. [OutputFormat::Ndjson] writes them as lines of JSON, see [emit_finding].
+//!
+//! The [emit_finding] function writes a finding as a line of JSON to the socket opened by [open_emit_socket], for `scan --emit-socket`.
+//!
+//! Long paths in tables are shortened in the middle to `--max-path-width` characters unless `--full-paths` is given.
+//!
+//! [OutputFormat::Sarif] maps scan results to SARIF 2.1.0 so code-scanning tools such as GitHub can show them next to the files, as warnings or, above `--sarif-error-above`, errors.
+//!
+//! [OutputFormat::Markdown] renders the same tables as GitHub-flavored Markdown, see [markdown].
+//!
+//! [OutputFormat::Openmetrics] summarises a scan as OpenMetrics text, an entropy histogram plus totals, for the node_exporter textfile collector.
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{ self, BufWriter, Write };
+use std::path::{ Component, Path, PathBuf };
+use std::time::Duration;
+
+use clap::{ Args, ValueEnum };
+use serde_json::json;
+use tabled::{ settings::{ object::{ Columns, Segment }, Format, Modify, Style }, Tabled };
+
+use crate::entropy_scan::{
+    archive::nest_members,
+    structs::{
+        Aggregate,
+        BaselineDiff,
+        Comparison,
+        FileEntropy,
+        Finding,
+        JobSummary,
+        MftData,
+        Partition,
+        Region,
+        RegionMap,
+        ScanMeta,
+        Similarity,
+        Stats,
+        Window,
+    },
+    units::{ format_count, format_duration, format_size },
+};
+
+/// A custom enum to represent the chosen output format.
+///
+/// Valid values are [OutputFormat::Csv], [OutputFormat::Json], [OutputFormat::Markdown], [OutputFormat::Ndjson], [OutputFormat::Openmetrics], [OutputFormat::Sarif], [OutputFormat::Table], and [OutputFormat::TableStream]. Default is [OutputFormat::Table].
+///
+/// [OutputFormat::TableStream] only streams scan results; stats and hunt reports need every result first and render it like [OutputFormat::Table].
+///
+/// [OutputFormat::Ndjson] writes each scanned file as a line of JSON as soon as it is measured, for `jq` and log shippers, or each group once a grouped scan ends. It is only available for scan results.
+///
+/// [OutputFormat::Json] scan reports nest each file's analyzer results in its record: `sections`, `regions`, and `archive_members`, each left out when the analyzer didn't run or found nothing. Every other format lists archive members as files of their own.
+///
+/// [OutputFormat::Ndjson] writes each scan result as a line of JSON with the scan's `scan_id` beside its fields, see [Finding].
+///
+/// [OutputFormat::Markdown] renders scan results and stats as GitHub-flavored tables, to paste into tickets and pull requests.
+///
+/// [OutputFormat::Openmetrics] and [OutputFormat::Sarif] are only available for ungrouped scan results.
+#[derive(Clone, ValueEnum)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+    Markdown,
+    Ndjson,
+    Openmetrics,
+    Sarif,
+    Table,
+    TableStream,
+}
+
+/// The SARIF rule every high-entropy file is reported under.
+const SARIF_RULE_ID: &str = "high-entropy-file";
+
+/// The upper bounds of the OpenMetrics entropy buckets for 8-bit symbols. Other symbol widths scale them.
+///
+/// The buckets narrow towards the top, where compressed and encrypted files sit.
+const OPENMETRICS_BUCKETS: [f64; 10] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 7.5, 7.9, 8.0];
+
+/// The width of the entropy column in streamed tables.
+const STREAM_ENTROPY_WIDTH: usize = 7;
+
+/// The width of the size column in streamed tables.
+const STREAM_SIZE_WIDTH: usize = 12;
+
+/// The width of the printable and null ratio columns in streamed tables.
+const STREAM_RATIO_WIDTH: usize = 9;
+
+/// An output format paired with the writer it is rendered to.
+pub type Destination = (OutputFormat, Box<dyn Write>);
+
+/// Holds the output formats and files for a subcommand.
+///
+/// Each `--format` is paired in order with an `--output` file. Formats without a matching file are written to stdout.
+#[derive(Args)]
+pub struct OutputArgs {
+    /// The output formats. Valid values are [OutputFormat::Csv], [OutputFormat::Json], [OutputFormat::Markdown], [OutputFormat::Ndjson], [OutputFormat::Openmetrics], [OutputFormat::Sarif], [OutputFormat::Table], and [OutputFormat::TableStream]. Default is [OutputFormat::Table], or [OutputFormat::Sarif] for `scan --ci`.
+    #[arg(short, long, value_name = "FORMAT", help = "Output format, may be repeated [default: table]")]
+    pub format: Vec<OutputFormat>,
+
+    /// The files to write each format to, in the same order as `format`.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        help = "Write the matching --format to FILE, may be repeated"
+    )]
+    pub output: Vec<PathBuf>,
+
+    /// Show human-readable sizes, counts, and durations in tables.
+    #[arg(long, help = "Show human-readable sizes, counts, and durations in tables")]
+    pub human: bool,
+
+    /// The maximum width of the path column in tables. Longer paths are shortened in the middle.
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Maximum width of the path column in tables",
+        default_value = "80"
+    )]
+    pub max_path_width: usize,
+
+    /// Never shorten paths in tables.
+    #[arg(long, help = "Never shorten paths in tables")]
+    pub full_paths: bool,
+
+    /// Don't print the `-----Title-----` banners above tables, or the headings above Markdown tables. CSV and JSON never have banners.
+    #[arg(long, help = "Don't print banners or headings above tables")]
+    pub no_banner: bool,
+
+    /// Don't print the header rows of CSV output, e.g. when appending to an existing file.
+    #[arg(long, help = "Don't print CSV header rows")]
+    pub no_header: bool,
+
+    /// Report SARIF results at or above this entropy as errors rather than warnings, so code scanning can block on them. See [sarif_level].
+    #[arg(long, value_name = "ENTROPY", help = "Report SARIF results at or above ENTROPY as errors")]
+    pub sarif_error_above: Option<f64>,
+}
+
+/// Write a decorative `-----title-----` banner above a table, unless `--no-banner` is given.
+fn banner(out: &mut dyn Write, args: &OutputArgs, title: &str) -> io::Result<()> {
+    match args.no_banner {
+        true => Ok(()),
+        false => writeln!(out, "-----{title}-----"),
+    }
+}
+
+/// Write a `### title` heading above a Markdown table, unless `--no-banner` is given.
+fn heading(out: &mut dyn Write, args: &OutputArgs, title: &str) -> io::Result<()> {
+    match args.no_banner {
+        true => Ok(()),
+        false => writeln!(out, "### {title}\n"),
+    }
+}
+
+/// Style `table` as a GitHub-flavored Markdown table, escaping the pipes and line breaks that would otherwise split its cells.
+fn markdown(mut table: tabled::Table) -> tabled::Table {
+    table
+        .with(
+            Modify::new(Segment::all()).with(Format::content(|s| s.replace('|', "\\|").replace('\n', "<br>")))
+        )
+        .with(Style::markdown());
+    table
+}
+
+/// Start a CSV document on `out` with the `header` row, unless `--no-header` is given.
+///
+/// Fields are quoted by the [csv] crate where they need it, e.g. paths holding commas, quotes, or line breaks.
+fn csv_writer<'a>(out: &'a mut dyn Write, args: &OutputArgs, header: &[&str]) -> io::Result<csv::Writer<&'a mut dyn Write>> {
+    let mut writer = csv::Writer::from_writer(out);
+    if !args.no_header {
+        writer.write_record(header)?;
+    }
+    Ok(writer)
+}
+
+/// Shorten `text` to at most `width` characters by replacing its middle with an ellipsis.
+fn truncate_middle(text: &str, width: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let tail = (width - 1) / 2;
+    let head = width - 1 - tail;
+    let mut shortened: String = chars[..head].iter().collect();
+    shortened.push('…');
+    shortened.extend(&chars[chars.len() - tail..]);
+    shortened
+}
+
+/// Shorten the path column of `table` to `--max-path-width`, unless `--full-paths` is given.
+fn fit_paths(mut table: tabled::Table, args: &OutputArgs) -> tabled::Table {
+    if !args.full_paths {
+        let width = args.max_path_width;
+        table.with(
+            Modify::new(Columns::first()).with(Format::content(|s| truncate_middle(s, width)))
+        );
+    }
+    table
+}
+
+/// Wraps a row so its numeric columns are rendered for humans in tables.
+struct Human<'a, T>(&'a T);
+
+impl Tabled for Human<'_, FileEntropy> {
+    const LENGTH: usize = FileEntropy::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        FileEntropy::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[2] = Cow::from(format_size(self.0.size));
+        fields[5] = Cow::from(format_size(self.0.longest_zero_run));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, Stats> {
+    const LENGTH: usize = Stats::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        Stats::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[1] = Cow::from(format_count(self.0.total));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, Partition> {
+    const LENGTH: usize = Partition::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        Partition::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[3] = Cow::from(format_size(self.0.start));
+        fields[4] = Cow::from(format_size(self.0.size));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, RegionMap> {
+    const LENGTH: usize = RegionMap::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        RegionMap::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[3] = Cow::from(format_size(self.0.size));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, Region> {
+    const LENGTH: usize = Region::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        Region::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[0] = Cow::from(format_size(self.0.start));
+        fields[1] = Cow::from(format_size(self.0.length));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, Window> {
+    const LENGTH: usize = Window::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        Window::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[0] = Cow::from(format_size(self.0.offset));
+        fields[1] = Cow::from(format_size(self.0.length));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, MftData> {
+    const LENGTH: usize = MftData::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        MftData::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[3] = Cow::from(format_size(self.0.offset));
+        fields[4] = Cow::from(format_size(self.0.length));
+        fields
+    }
+}
+
+impl Tabled for Human<'_, Aggregate> {
+    const LENGTH: usize = Aggregate::LENGTH;
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        Aggregate::headers()
+    }
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        let mut fields = self.0.fields();
+        fields[2] = Cow::from(format_count(self.0.files));
+        fields[3] = Cow::from(format_size(self.0.size));
+        fields
+    }
+}
+
+/// Reads the cell value of an [ExtraColumn] for a file, or [None] if the analysis didn't run for it.
+type CellValue = fn(&FileEntropy) -> Option<String>;
+
+/// An optional per-file column, filled in by an analysis the user opted into or a label that only some files have.
+struct ExtraColumn {
+    /// The column header in tables.
+    header: &'static str,
+    /// The column header in CSV.
+    csv_header: &'static str,
+    /// The cell value, or [None] if the analysis didn't run for this file.
+    value: CellValue,
+    /// The header and cell value used instead in tables with `--human`, for columns that read differently there.
+    human: Option<(&'static str, CellValue)>,
+}
+
+impl ExtraColumn {
+    /// The column header in tables, honouring `--human`.
+    fn table_header(&self, human: bool) -> &'static str {
+        match (human, self.human) {
+            (true, Some((header, _))) => header,
+            _ => self.header,
+        }
+    }
+
+    /// The cell value for `item` in tables, honouring `--human`.
+    fn table_value(&self, item: &FileEntropy, human: bool) -> Option<String> {
+        match (human, self.human) {
+            (true, Some((_, value))) => value(item),
+            _ => (self.value)(item),
+        }
+    }
+}
+
+/// Every optional per-file column, in display order.
+const EXTRA_COLUMNS: &[ExtraColumn] = &[
+    ExtraColumn {
+        header: "CONTAINER",
+        csv_header: "container",
+        value: |e| e.container.clone(),
+        human: None,
+    },
+    ExtraColumn {
+        header: "PACKAGE",
+        csv_header: "package",
+        value: |e| e.package_status.as_ref().map(|_| e.package.clone().unwrap_or_default()),
+        human: None,
+    },
+    ExtraColumn {
+        header: "PACKAGE STATUS",
+        csv_header: "package_status",
+        value: |e| e.package_status.clone(),
+        human: None,
+    },
+    ExtraColumn {
+        header: "ANOMALY",
+        csv_header: "anomaly_score",
+        value: |e| e.anomaly_score.map(|score| format!("{score:.2}")),
+        human: None,
+    },
+    ExtraColumn {
+        header: "STAGING",
+        csv_header: "staging",
+        value: |e| e.staging.clone(),
+        human: None,
+    },
+    ExtraColumn {
+        header: "MOUNT",
+        csv_header: "mount_point",
+        value: |e| e.mount_point.clone(),
+        human: None,
+    },
+    ExtraColumn {
+        header: "LINK TARGET",
+        csv_header: "link_target",
+        value: |e| e.link_target.clone(),
+        human: None,
+    },
+    ExtraColumn {
+        header: "TIME (MS)",
+        csv_header: "duration_ms",
+        value: |e| e.duration_ms.map(|ms| format!("{ms:.3}")),
+        human: Some(("TIME", |e| e.duration_ms.map(|ms| format_duration(Duration::from_secs_f64(ms / 1000.0))))),
+    },
+    ExtraColumn {
+        header: "PERIOD",
+        csv_header: "period",
+        value: |e| {
+            e.periodicity
+                .as_ref()
+                .map(|p| p.period.map(|period| period.to_string()).unwrap_or_default())
+        },
+        human: None,
+    },
+    ExtraColumn {
+        header: "XOR KEY",
+        csv_header: "xor_key",
+        value: |e| {
+            e.xor.as_ref().map(|x| {
+                match x.rolling {
+                    true => format!("0x{:02x}+i", x.key),
+                    false => format!("0x{:02x}", x.key),
+                }
+            })
+        },
+        human: None,
+    },
+    ExtraColumn {
+        header: "XOR EVIDENCE",
+        csv_header: "xor_evidence",
+        value: |e| e.xor.as_ref().map(|x| x.evidence.clone()),
+        human: None,
+    },
+    ExtraColumn {
+        header: "SECTIONS",
+        csv_header: "sections",
+        value: |e| {
+            e.sections.as_ref().map(|sections| {
+                sections
+                    .iter()
+                    .map(|section| format!("{}:{:.3}", section.name, section.entropy))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+        },
+        human: None,
+    },
+];
+
+/// Pick the optional columns that have a value for at least one of `entropies`.
+fn extra_columns(entropies: &[FileEntropy]) -> Vec<&'static ExtraColumn> {
+    EXTRA_COLUMNS.iter()
+        .filter(|column| entropies.iter().any(|e| (column.value)(e).is_some()))
+        .collect()
+}
+
+/// Build a table of [FileEntropy] rows, honouring `--human` and `--max-path-width`.
+///
+/// Optional analysis columns are appended when the analysis ran.
+fn entropy_table(entropies: &[FileEntropy], args: &OutputArgs) -> tabled::Table {
+    let extras = extra_columns(entropies);
+    let table = match (extras.is_empty(), args.human) {
+        (true, true) => tabled::Table::new(entropies.iter().map(Human)),
+        (true, false) => tabled::Table::new(entropies),
+        (false, human) => {
+            let mut builder = tabled::builder::Builder::default();
+            builder.push_record(
+                FileEntropy::headers()
+                    .into_iter()
+                    .map(|h| h.to_string())
+                    .chain(extras.iter().map(|column| column.table_header(human).to_string()))
+            );
+            for item in entropies {
+                let fields: Vec<String> = match human {
+                    true => Human(item).fields().into_iter().map(|f| f.to_string()).collect(),
+                    false => item.fields().into_iter().map(|f| f.to_string()).collect(),
+                };
+                builder.push_record(
+                    fields
+                        .into_iter()
+                        .chain(extras.iter().map(|column| column.table_value(item, human).unwrap_or_default()))
+                );
+            }
+            builder.build()
+        }
+    };
+    fit_paths(table, args)
+}
+
+impl OutputArgs {
+    /// Open every requested destination.
+    ///
+    /// When `quiet` is set, table formats that would be written to stdout are dropped so only machine-readable output remains.
+    ///
+    /// Returns a [Vec] of formats paired with their writer, or an error message if there are more files than formats or a file can't be created.
+    pub fn destinations(&self, quiet: bool) -> Result<Vec<Destination>, String> {
+        let formats = match self.format.is_empty() {
+            true => &[OutputFormat::Table][..],
+            false => &self.format[..],
+        };
+        if self.output.len() > formats.len() {
+            return Err("Each --output needs a matching --format".to_string());
+        }
+
+        let mut destinations: Vec<Destination> = Vec::new();
+        for (i, format) in formats.iter().enumerate() {
+            let writer: Box<dyn Write> = match self.output.get(i) {
+                Some(path) => {
+                    let file = File::create(path).map_err(|e|
+                        format!("Couldn't create {}: {e}", path.to_string_lossy())
+                    )?;
+                    Box::new(BufWriter::new(file))
+                }
+                None if quiet && matches!(format, OutputFormat::Table | OutputFormat::TableStream) => {
+                    continue;
+                }
+                None => Box::new(io::stdout()),
+            };
+            destinations.push((format.clone(), writer));
+        }
+        Ok(destinations)
+    }
+}
+
+/// Write the banner and header of a streamed scan table.
+///
+/// Column widths are fixed up front from `--max-path-width` so rows can be printed as soon as they are scanned.
+pub fn stream_scan_header(out: &mut dyn Write, args: &OutputArgs) -> io::Result<()> {
+    banner(out, args, "Entropies")?;
+    writeln!(
+        out,
+        "{:<path_width$}  {:>STREAM_ENTROPY_WIDTH$}  {:>STREAM_SIZE_WIDTH$}  {:>STREAM_RATIO_WIDTH$}  {:>STREAM_RATIO_WIDTH$}  {:>STREAM_SIZE_WIDTH$}",
+        "PATH",
+        "ENTROPY",
+        "SIZE",
+        "PRINTABLE",
+        "NULLS",
+        "ZERO RUN",
+        path_width = args.max_path_width
+    )?;
+    out.flush()
+}
+
+/// Write a single row of a streamed scan table.
+pub fn stream_scan_row(out: &mut dyn Write, args: &OutputArgs, item: &FileEntropy) -> io::Result<()> {
+    let path = item.path.to_string_lossy();
+    let path = match args.full_paths {
+        true => path.to_string(),
+        false => truncate_middle(&path, args.max_path_width),
+    };
+    let (size, zero_run) = match args.human {
+        true => (format_size(item.size), format_size(item.longest_zero_run)),
+        false => (item.size.to_string(), item.longest_zero_run.to_string()),
+    };
+    writeln!(
+        out,
+        "{:<path_width$}  {:>STREAM_ENTROPY_WIDTH$.3}  {:>STREAM_SIZE_WIDTH$}  {:>STREAM_RATIO_WIDTH$.3}  {:>STREAM_RATIO_WIDTH$.3}  {:>STREAM_SIZE_WIDTH$}",
+        path,
+        item.entropy,
+        size,
+        item.printable_ratio,
+        item.null_ratio,
+        zero_run,
+        path_width = args.max_path_width
+    )?;
+    out.flush()
+}
+
+/// Open the local socket or pipe at `path` to stream findings to.
+///
+/// A FIFO is opened for writing, which waits for a reader. Anything else is connected to as a Unix domain socket, so the consumer must already be listening.
+///
+/// Returns the writer, or an error message if nothing is listening at `path`.
+#[cfg(unix)]
+pub fn open_emit_socket(path: &Path) -> Result<Box<dyn Write>, String> {
+    use std::fs::{ self, OpenOptions };
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    let error = |e: io::Error| format!("Couldn't open {}: {e}", path.to_string_lossy());
+    let fifo = fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo());
+    match fifo {
+        true => Ok(Box::new(OpenOptions::new().write(true).open(path).map_err(error)?)),
+        false => Ok(Box::new(UnixStream::connect(path).map_err(error)?)),
+    }
+}
+
+/// Open the named pipe at `path`, such as `\\.\pipe\entropyscan`, to stream findings to.
+///
+/// Returns the writer, or an error message if no pipe server is listening at `path`.
+#[cfg(not(unix))]
+pub fn open_emit_socket(path: &Path) -> Result<Box<dyn Write>, String> {
+    use std::fs::OpenOptions;
+
+    let pipe = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Couldn't open {}: {e}", path.to_string_lossy()))?;
+    Ok(Box::new(pipe))
+}
+
+/// Write a single finding of the scan `scan_id` as a line of JSON, flushed straight away so the consumer sees it as soon as it is measured. This is also how [OutputFormat::Ndjson] streams scan results.
+///
+/// Each line is a [Finding]: the [FileEntropy] with the `scan_id` beside its fields.
+pub fn emit_finding(out: &mut dyn Write, scan_id: &str, item: &FileEntropy) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, &Finding { scan_id, entropy: item })?;
+    writeln!(out)?;
+    out.flush()
+}
+
+/// The error for a report that can't be written in `format`.
+fn unsupported(format: &OutputFormat, report: &str) -> io::Error {
+    let name = format.to_possible_value().unwrap();
+    io::Error::new(io::ErrorKind::Unsupported, format!("{} output isn't available for {report}", name.get_name()))
+}
+
+/// Turn `path` into a SARIF artifact URI.
+///
+/// Relative paths stay relative, so code-scanning tools resolve them against the repository root. Absolute paths become `file://` URIs.
+fn artifact_uri(path: &Path) -> String {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|component| {
+            match component {
+                Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                Component::ParentDir => Some("..".to_string()),
+                _ => None,
+            }
+        })
+        .collect();
+    let mut uri = parts.join("/");
+    for (from, to) in [("%", "%25"), (" ", "%20"), ("#", "%23"), ("?", "%3F")] {
+        uri = uri.replace(from, to);
+    }
+    match path.is_absolute() {
+        true => format!("file:///{uri}"),
+        false => uri,
+    }
+}
+
+/// Pick the SARIF level of a finding: `note` for a file too small for its entropy to be meaningful, `error` at or above `error_above`, and `warning` otherwise.
+fn sarif_level(item: &FileEntropy, error_above: Option<f64>) -> &'static str {
+    match (item.low_confidence, error_above) {
+        (true, _) => "note",
+        (false, Some(limit)) if item.entropy >= limit => "error",
+        _ => "warning",
+    }
+}
+
+/// Build a SARIF 2.1.0 log reporting each of `entropies` as a [SARIF_RULE_ID] result, at the level [sarif_level] picks with `error_above`.
+fn sarif_log(meta: &ScanMeta, entropies: &[FileEntropy], error_above: Option<f64>) -> serde_json::Value {
+    let results: Vec<serde_json::Value> = entropies
+        .iter()
+        .map(|item| {
+            json!({
+                "ruleId": SARIF_RULE_ID,
+                "level": sarif_level(item, error_above),
+                "message": {
+                    "text": format!("High-entropy file: {:.3} bits per symbol over {} bytes", item.entropy, item.size),
+                },
+                "locations": [
+                    { "physicalLocation": { "artifactLocation": { "uri": artifact_uri(&item.path) } } },
+                ],
+                "properties": {
+                    "entropy": item.entropy,
+                    "size": item.size,
+                    "low_confidence": item.low_confidence,
+                },
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [
+            {
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": [
+                            {
+                                "id": SARIF_RULE_ID,
+                                "name": "HighEntropyFile",
+                                "shortDescription": { "text": "High-entropy file" },
+                                "fullDescription": {
+                                    "text": "The file's contents look compressed, encrypted, or random, such as a committed binary blob or key material.",
+                                },
+                            },
+                        ],
+                    },
+                },
+                "automationDetails": { "guid": meta.scan_id },
+                "properties": meta,
+                "results": results,
+            },
+        ],
+    })
+}
+
+/// Write a scan as OpenMetrics text: a histogram of file entropies and the number and total size of the files.
+///
+/// Every sample is labelled with the `scan_id` of the scan, so metrics can be traced back to its report. `bits` is the symbol width entropy was measured over, which scales the buckets.
+fn openmetrics(out: &mut dyn Write, scan_id: &str, bits: u8, entropies: &[FileEntropy]) -> io::Result<()> {
+    let label = format!("scan_id=\"{scan_id}\"");
+    let scale = (bits as f64) / 8.0;
+    writeln!(out, "# TYPE entropyscan_file_entropy histogram")?;
+    writeln!(out, "# HELP entropyscan_file_entropy Entropy of the scanned files in bits per symbol.")?;
+    for bound in OPENMETRICS_BUCKETS {
+        let bound = bound * scale;
+        let count = entropies
+            .iter()
+            .filter(|item| item.entropy <= bound)
+            .count();
+        // Bucket bounds keep a decimal point, as OpenMetrics expects, without rounding away 7.9.
+        let bound = match bound.fract() {
+            0.0 => format!("{bound:.1}"),
+            _ => bound.to_string(),
+        };
+        writeln!(out, "entropyscan_file_entropy_bucket{{le=\"{bound}\",{label}}} {count}")?;
+    }
+    writeln!(out, "entropyscan_file_entropy_bucket{{le=\"+Inf\",{label}}} {}", entropies.len())?;
+    writeln!(out, "entropyscan_file_entropy_count{{{label}}} {}", entropies.len())?;
+    let sum: f64 = entropies
+        .iter()
+        .map(|item| item.entropy)
+        .sum();
+    writeln!(out, "entropyscan_file_entropy_sum{{{label}}} {sum}")?;
+    writeln!(out, "# TYPE entropyscan_files gauge")?;
+    writeln!(out, "# HELP entropyscan_files Number of files scanned.")?;
+    writeln!(out, "entropyscan_files{{{label}}} {}", entropies.len())?;
+    writeln!(out, "# TYPE entropyscan_size_bytes gauge")?;
+    writeln!(out, "# UNIT entropyscan_size_bytes bytes")?;
+    writeln!(out, "# HELP entropyscan_size_bytes Total size of the files scanned.")?;
+    let size: u64 = entropies
+        .iter()
+        .map(|item| item.size)
+        .sum();
+    writeln!(out, "entropyscan_size_bytes{{{label}}} {size}")?;
+    writeln!(out, "# TYPE entropyscan_max_entropy gauge")?;
+    writeln!(out, "# HELP entropyscan_max_entropy Highest entropy of the files scanned, in bits per symbol.")?;
+    let max = entropies
+        .iter()
+        .map(|item| item.entropy)
+        .fold(0.0f64, f64::max);
+    writeln!(out, "entropyscan_max_entropy{{{label}}} {max}")?;
+    writeln!(out, "# EOF")
+}
+
+/// Render the results of a scan.
+///
+/// [OutputFormat::TableStream] rows are written as they are scanned, so nothing is rendered for it here. [OutputFormat::Ndjson] lines are usually streamed too, and only rendered here when they couldn't be.
+pub fn render_scan(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    entropies: &[FileEntropy]
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let extras = extra_columns(entropies);
+            let header: Vec<&str> = [
+                "path",
+                "entropy",
+                "size",
+                "low_confidence",
+                "printable_ratio",
+                "null_ratio",
+                "longest_zero_run",
+            ]
+                .into_iter()
+                .chain(extras.iter().map(|column| column.csv_header))
+                .collect();
+            let mut writer = csv_writer(out, args, &header)?;
+            for item in entropies {
+                let mut record = vec![
+                    item.path.to_string_lossy().into_owned(),
+                    format!("{:.3}", item.entropy),
+                    item.size.to_string(),
+                    item.low_confidence.to_string(),
+                    format!("{:.3}", item.printable_ratio),
+                    format!("{:.3}", item.null_ratio),
+                    item.longest_zero_run.to_string()
+                ];
+                record.extend(extras.iter().map(|column| (column.value)(item).unwrap_or_default()));
+                writer.write_record(&record)?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["entropies"] = json!(nest_members(entropies));
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown => {
+            heading(out, args, "Entropies")?;
+            writeln!(out, "{}", markdown(entropy_table(entropies, args)))?;
+            if let Some(ms) = meta.duration_ms {
+                writeln!(out)?;
+                scan_took(out, args, ms)?;
+            }
+        }
+        Ndjson => {
+            for item in entropies {
+                emit_finding(out, &meta.scan_id, item)?;
+            }
+        }
+        Openmetrics => {
+            openmetrics(out, &meta.scan_id, meta.symbol_width.unwrap_or(8), entropies)?;
+        }
+        Sarif => {
+            let json = serde_json::to_string_pretty(&sarif_log(meta, entropies, args.sarif_error_above)).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Table => {
+            banner(out, args, "Entropies")?;
+            let table = entropy_table(entropies, args).to_string();
+            write!(out, "{table}")?;
+            if let Some(ms) = meta.duration_ms {
+                writeln!(out)?;
+                scan_took(out, args, ms)?;
+            }
+        }
+        TableStream => {
+            if let Some(ms) = meta.duration_ms {
+                scan_took(out, args, ms)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Write how long the scan took, `ms` milliseconds, under a table, honouring `--human`.
+fn scan_took(out: &mut dyn Write, args: &OutputArgs, ms: u64) -> io::Result<()> {
+    match args.human {
+        true => writeln!(out, "Scan took {}", format_duration(Duration::from_millis(ms))),
+        false => writeln!(out, "Scan took {ms} ms"),
+    }
+}
+
+/// Render the results of a scan grouped with `--aggregate-by`.
+///
+/// Groups can't be streamed, so [OutputFormat::TableStream] is rendered as a [OutputFormat::Table].
+pub fn render_aggregates(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    groups: &[Aggregate]
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let header = ["path", "kind", "files", "size", "max_entropy", "mean_entropy", "mach_o", "signed"];
+            let mut writer = csv_writer(out, args, &header)?;
+            for item in groups {
+                writer.write_record([
+                    item.path.to_string_lossy().into_owned(),
+                    item.kind.to_string(),
+                    item.files.to_string(),
+                    item.size.to_string(),
+                    format!("{:.3}", item.max_entropy),
+                    format!("{:.3}", item.mean_entropy),
+                    item.mach_o.to_string(),
+                    item.signed.map(|signed| signed.to_string()).unwrap_or_default(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["groups"] = json!(groups);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown => {
+            heading(out, args, "Groups")?;
+            let table = match args.human {
+                true => tabled::Table::new(groups.iter().map(Human)),
+                false => tabled::Table::new(groups),
+            };
+            writeln!(out, "{}", markdown(fit_paths(table, args)))?;
+        }
+        Ndjson => {
+            for item in groups {
+                serde_json::to_writer(&mut *out, item)?;
+                writeln!(out)?;
+            }
+        }
+        Openmetrics | Sarif => {
+            return Err(unsupported(format, "grouped results"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Groups")?;
+            let table = match args.human {
+                true => tabled::Table::new(groups.iter().map(Human)),
+                false => tabled::Table::new(groups),
+            };
+            write!(out, "{}", fit_paths(table, args))?;
+        }
+    }
+    out.flush()
+}
+
+/// Render the stats for a target.
+///
+/// `outliers` is [None] when outliers were not requested. `recent` is [None] when recent outliers were not requested.
+///
+/// JSON is a single document headed by `meta`, holding `stats` and, when requested, `outliers` and `recent_outliers`. CSV is a single table, whose `section` column tells the `stats` row from `recent_outlier` and `outlier` rows.
+pub fn render_stats(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    stats: &Stats,
+    outliers: Option<&[FileEntropy]>,
+    recent: Option<&[FileEntropy]>
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let header = [
+                "section",
+                "target",
+                "total",
+                "mean",
+                "median",
+                "variance",
+                "iqr",
+                "path",
+                "entropy",
+                "created",
+            ];
+            let mut writer = csv_writer(out, args, &header)?;
+            writer.write_record([
+                "stats".to_string(),
+                stats.target.to_string_lossy().into_owned(),
+                stats.total.to_string(),
+                format!("{:.3}", stats.mean),
+                format!("{:.3}", stats.median),
+                format!("{:.3}", stats.variance),
+                stats.iqr.map(|iqr| format!("{iqr:.3}")).unwrap_or_default(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ])?;
+            let outlier = |section: &str, item: &FileEntropy| {
+                let mut record = vec![section.to_string()];
+                record.resize(7, String::new());
+                record.push(item.path.to_string_lossy().into_owned());
+                record.push(format!("{:.3}", item.entropy));
+                record.push(item.created.map(|created| created.to_string()).unwrap_or_default());
+                record
+            };
+            for item in recent.unwrap_or_default() {
+                writer.write_record(outlier("recent_outlier", item))?;
+            }
+            for item in outliers.unwrap_or_default() {
+                writer.write_record(outlier("outlier", item))?;
+            }
+            writer.flush()?;
+        }
+
+        Json => {
+            let mut report = json!(meta);
+            report["stats"] = json!(stats);
+            if let Some(outliers) = outliers {
+                report["outliers"] = json!(outliers);
+            }
+            if let Some(recent) = recent {
+                report["recent_outliers"] = json!(recent);
+            }
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+
+        Markdown => {
+            heading(out, args, "Stats")?;
+            let table = match args.human {
+                true => tabled::Table::new([Human(stats)]),
+                false => tabled::Table::new([stats]),
+            };
+            writeln!(out, "{}", markdown(fit_paths(table, args)))?;
+            if let Some(outliers) = outliers {
+                if let Some(recent) = recent {
+                    writeln!(out)?;
+                    heading(out, args, "Recent Outliers")?;
+                    writeln!(out, "{}", markdown(entropy_table(recent, args)))?;
+                }
+                writeln!(out)?;
+                heading(out, args, "Outliers")?;
+                writeln!(out, "{}", markdown(entropy_table(outliers, args)))?;
+            }
+        }
+
+        Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "stats"));
+        }
+
+        Table | TableStream => {
+            banner(out, args, "Entropies")?;
+            let table = match args.human {
+                true => tabled::Table::new([Human(stats)]),
+                false => tabled::Table::new([stats]),
+            };
+            let table = fit_paths(table, args);
+            writeln!(out, "{table}")?;
+            if let Some(outliers) = outliers {
+                if let Some(recent) = recent {
+                    writeln!(out)?;
+                    banner(out, args, "Recent Outliers")?;
+                    let table = entropy_table(recent, args);
+                    writeln!(out, "{table}")?;
+                }
+                writeln!(out)?;
+                banner(out, args, "Outliers")?;
+                let table = entropy_table(outliers, args);
+                writeln!(out, "{table}")?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Render the files ranked by similarity to a sample.
+pub fn render_hunt(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    matches: &[Similarity]
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let mut writer = csv_writer(out, args, &["path", "blocks", "similarity"])?;
+            for item in matches {
+                writer.write_record([
+                    item.path.to_string_lossy().into_owned(),
+                    item.blocks.to_string(),
+                    format!("{:.3}", item.similarity),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["matches"] = json!(matches);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "hunt results"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Matches")?;
+            let table = fit_paths(tabled::Table::new(matches), args).to_string();
+            write!(out, "{table}")?;
+        }
+    }
+    out.flush()
+}
+
+/// Render the partitions of a disk or disk image.
+///
+/// Block entropy maps are only included in JSON.
+pub fn render_partitions(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    partitions: &[Partition]
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let header = [
+                "index",
+                "scheme",
+                "start",
+                "size",
+                "type",
+                "label",
+                "signature",
+                "entropy",
+                "min_entropy",
+                "likely_encrypted",
+            ];
+            let mut writer = csv_writer(out, args, &header)?;
+            for item in partitions {
+                writer.write_record([
+                    item.index.to_string(),
+                    item.scheme.to_string(),
+                    item.start.to_string(),
+                    item.size.to_string(),
+                    item.kind.clone(),
+                    item.label.clone().unwrap_or_default(),
+                    item.signature.unwrap_or_default().to_string(),
+                    format!("{:.3}", item.entropy),
+                    format!("{:.3}", item.min_entropy),
+                    item.likely_encrypted.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["partitions"] = json!(partitions);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "partitions"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Partitions")?;
+            let table = match args.human {
+                true => tabled::Table::new(partitions.iter().map(Human)),
+                false => tabled::Table::new(partitions),
+            };
+            write!(out, "{table}")?;
+        }
+    }
+    out.flush()
+}
+
+/// Render the region-level entropy map of a file.
+pub fn render_regions(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    map: &RegionMap
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let mut writer = csv_writer(out, args, &["start", "length", "class", "entropy"])?;
+            for item in &map.regions {
+                writer.write_record([
+                    item.start.to_string(),
+                    item.length.to_string(),
+                    item.class.to_string(),
+                    format!("{:.3}", item.entropy),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["map"] = json!(map);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "region maps"));
+        }
+        Table | TableStream => {
+            banner(out, args, "File")?;
+            let table = match args.human {
+                true => tabled::Table::new([Human(map)]),
+                false => tabled::Table::new([map]),
+            };
+            writeln!(out, "{}", fit_paths(table, args))?;
+            writeln!(out)?;
+            banner(out, args, "Regions")?;
+            let table = match args.human {
+                true => tabled::Table::new(map.regions.iter().map(Human)),
+                false => tabled::Table::new(&map.regions),
+            };
+            write!(out, "{table}")?;
+        }
+    }
+    out.flush()
+}
+
+/// Render the resident data and file slack found through the `$MFT` of the NTFS volume in `target`.
+pub fn render_mft(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    target: &Path,
+    found: &[MftData]
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let mut writer = csv_writer(out, args, &["record", "name", "kind", "offset", "length", "entropy"])?;
+            for item in found {
+                writer.write_record([
+                    item.record.to_string(),
+                    item.name.clone(),
+                    item.kind.to_string(),
+                    item.offset.to_string(),
+                    item.length.to_string(),
+                    format!("{:.3}", item.entropy),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["target"] = json!(target.to_string_lossy());
+            report["mft_data"] = json!(found);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "NTFS"));
+        }
+        Table | TableStream => {
+            banner(out, args, "MFT Data")?;
+            let table = match args.human {
+                true => tabled::Table::new(found.iter().map(Human)),
+                false => tabled::Table::new(found),
+            };
+            write!(out, "{table}")?;
+        }
+    }
+    out.flush()
+}
+
+/// Render the comparison of two scan reports.
+///
+/// CSV is a single table, whose `section` column tells the per-metric `change` rows from the `test` row holding the KS test result.
+pub fn render_comparison(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    comparison: &Comparison
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let header = ["section", "metric", "before", "after", "change", "ks_distance", "p_value", "changed"];
+            let mut writer = csv_writer(out, args, &header)?;
+            for item in &comparison.changes {
+                writer.write_record([
+                    "change".to_string(),
+                    item.metric.to_string(),
+                    format!("{:.3}", item.before),
+                    format!("{:.3}", item.after),
+                    format!("{:.3}", item.change),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                ])?;
+            }
+            writer.write_record([
+                "test".to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                format!("{:.3}", comparison.ks_distance),
+                format!("{:.4}", comparison.p_value),
+                comparison.changed.to_string(),
+            ])?;
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["comparison"] = json!(comparison);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "comparisons"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Comparison")?;
+            writeln!(out, "{}", tabled::Table::new(&comparison.changes))?;
+            let verdict = match comparison.changed {
+                true => "the corpus changed materially",
+                false => "no material change",
+            };
+            writeln!(
+                out,
+                "KS distance {:.3} (p = {:.4}): {verdict}",
+                comparison.ks_distance,
+                comparison.p_value
+            )?;
+        }
+    }
+    out.flush()
+}
+
+/// Render how a tree changed since a baseline scan.
+pub fn render_diff(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    diff: &BaselineDiff
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    let entropy = |value: Option<f64>| value.map(|value| format!("{value:.3}")).unwrap_or_default();
+    match format {
+        Csv => {
+            let mut writer = csv_writer(out, args, &["path", "change", "before", "after"])?;
+            for item in &diff.changes {
+                writer.write_record([
+                    item.path.to_string_lossy().into_owned(),
+                    item.change.to_string(),
+                    entropy(item.before),
+                    entropy(item.after),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["diff"] = json!(diff);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "baseline diffs"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Changes since baseline")?;
+            if !diff.changes.is_empty() {
+                writeln!(out, "{}", tabled::Table::new(&diff.changes))?;
+            }
+            writeln!(
+                out,
+                "{} new, {} removed, {} changed by more than {}, {} wiped, {} unchanged",
+                diff.new,
+                diff.removed,
+                diff.changed,
+                diff.delta,
+                diff.wiped,
+                diff.unchanged
+            )?;
+        }
+    }
+    out.flush()
+}
+
+/// Render the consolidated summary of a batch job.
+pub fn render_job(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    summary: &JobSummary
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let mut writer = csv_writer(out, args, &["target", "status", "reported", "duration_ms"])?;
+            for item in &summary.results {
+                writer.write_record([
+                    item.name.clone(),
+                    item.status.to_string(),
+                    item.reported.map(|count| count.to_string()).unwrap_or_default(),
+                    item.duration_ms.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["job"] = json!(summary);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "job summaries"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Job")?;
+            writeln!(out, "{}", tabled::Table::new(&summary.results))?;
+            writeln!(
+                out,
+                "{} clean, {} with findings, {} with errors, {} failed",
+                summary.count("clean"),
+                summary.count("findings"),
+                summary.count("errors"),
+                summary.count("failed")
+            )?;
+            if let Some(reason) = &summary.suppressed {
+                writeln!(out, "Notifications suppressed: {reason}")?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Render the sliding-window entropy of the file at `target`.
+pub fn render_windows(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    args: &OutputArgs,
+    meta: &ScanMeta,
+    target: &Path,
+    windows: &[Window]
+) -> io::Result<()> {
+    use OutputFormat::*;
+
+    match format {
+        Csv => {
+            let mut writer = csv_writer(out, args, &["offset", "length", "entropy"])?;
+            for item in windows {
+                writer.write_record([item.offset.to_string(), item.length.to_string(), format!("{:.3}", item.entropy)])?;
+            }
+            writer.flush()?;
+        }
+        Json => {
+            let mut report = json!(meta);
+            report["target"] = json!(target.to_string_lossy());
+            report["windows"] = json!(windows);
+            let json = serde_json::to_string_pretty(&report).unwrap();
+            write!(out, "{}", json)?;
+        }
+        Markdown | Ndjson | Openmetrics | Sarif => {
+            return Err(unsupported(format, "windows"));
+        }
+        Table | TableStream => {
+            banner(out, args, "Windows")?;
+            let table = match args.human {
+                true => tabled::Table::new(windows.iter().map(Human)),
+                false => tabled::Table::new(windows),
+            };
+            write!(out, "{table}")?;
+        }
+    }
+    out.flush()
+}
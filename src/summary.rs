@@ -0,0 +1,122 @@
+//! Contains the logic for rendering a short human-readable summary of a JSON scan report.
+//!
+//! [summarize] reads a report written by `scan --format json` and renders it through a template.
+//!
+//! Templates use a small Handlebars-style syntax: `{{name}}` is replaced with a value, and `{{#each top}}...{{/each}}` repeats its body for every top finding.
+//!
+//! The available values are `scan_id`, `total`, `mean`, `median`, `variance`, `iqr`, `outliers`, and `duration_ms`. Inside `{{#each top}}` the values `rank`, `path`, `entropy`, and `duration_ms` are also available. Durations are `n/a` unless the scan was run with `--timings`, and `iqr` and `outliers` are `n/a` for reports of fewer than 4 files, which have no quartiles.
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::entropy_scan::{
+    archive::flatten_members,
+    stats::{ entropy_outliers, interquartile_range, mean, median, variance },
+    structs::FileEntropy,
+};
+
+/// The template used when none is given on the command line.
+const DEFAULT_TEMPLATE: &str =
+    "# Entropy scan summary
+
+Scan `{{scan_id}}` examined {{total}} files.
+
+| Mean | Median | Variance | IQR |
+| ---- | ------ | -------- | --- |
+| {{mean}} | {{median}} | {{variance}} | {{iqr}} |
+
+{{outliers}} files are statistical outliers.
+
+## Top findings
+
+{{#each top}}{{rank}}. `{{path}}` (entropy {{entropy}})
+{{/each}}";
+
+/// The opening tag of the top findings loop.
+const EACH_TOP: &str = "{{#each top}}";
+
+/// The closing tag of a loop.
+const END_EACH: &str = "{{/each}}";
+
+/// The parts of a JSON scan report needed for a summary.
+#[derive(Deserialize)]
+struct Report {
+    scan_id: String,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    entropies: Vec<FileEntropy>,
+}
+
+/// Replace every `{{name}}` in `template` with its value from `values`.
+///
+/// Unknown names are left untouched so typos are visible in the output.
+fn substitute(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut rendered = template.to_string();
+    for (name, value) in values {
+        rendered = rendered.replace(&format!("{{{{{name}}}}}"), value);
+    }
+    rendered
+}
+
+/// Render a summary of the JSON scan report at `report`.
+///
+/// Uses the template at `template` if given, otherwise a built-in Markdown template. `top` limits the number of findings listed.
+///
+/// Returns the rendered summary or an error message.
+pub fn summarize(report: &PathBuf, template: Option<&PathBuf>, top: usize) -> Result<String, String> {
+    let report = fs::read_to_string(report).map_err(|e| format!("Couldn't read report: {e}"))?;
+    let report: Report = serde_json::from_str(&report).map_err(|e|
+        format!("Couldn't parse report: {e}")
+    )?;
+    let template = match template {
+        Some(path) =>
+            fs::read_to_string(path).map_err(|e| format!("Couldn't read template: {e}"))?,
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+
+    let entropies = flatten_members(report.entropies);
+    let mut values: HashMap<&str, String> = HashMap::new();
+    values.insert("scan_id", report.scan_id);
+    values.insert("total", entropies.len().to_string());
+    values.insert("duration_ms", report.duration_ms.map_or("n/a".to_string(), |ms| ms.to_string()));
+    for (name, value) in [
+        ("mean", mean(&entropies)),
+        ("median", median(&entropies)),
+        ("variance", variance(&entropies)),
+        ("iqr", interquartile_range(&entropies).map(|iqr| iqr.range)),
+    ] {
+        let value = value.map_or("n/a".to_string(), |v| format!("{v:.3}"));
+        values.insert(name, value);
+    }
+    let outliers = entropy_outliers(&entropies).map_or("n/a".to_string(), |outliers| outliers.len().to_string());
+    values.insert("outliers", outliers);
+
+    let mut ranked = entropies.clone();
+    ranked.sort_by(|a, b| b.entropy.partial_cmp(&a.entropy).unwrap());
+    ranked.truncate(top);
+
+    let mut rendered = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find(EACH_TOP) {
+        let body_start = start + EACH_TOP.len();
+        let body_len = rest[body_start..]
+            .find(END_EACH)
+            .ok_or("Template has an unclosed {{#each top}}")?;
+        let body = &rest[body_start..body_start + body_len];
+
+        rendered.push_str(&substitute(&rest[..start], &values));
+        for (i, item) in ranked.iter().enumerate() {
+            let mut item_values = values.clone();
+            item_values.insert("rank", (i + 1).to_string());
+            item_values.insert("path", item.path.to_string_lossy().to_string());
+            item_values.insert("entropy", format!("{:.3}", item.entropy));
+            item_values.insert("duration_ms", item.duration_ms.map_or("n/a".to_string(), |ms| format!("{ms:.3}")));
+            rendered.push_str(&substitute(body, &item_values));
+        }
+        rest = &rest[body_start + body_len + END_EACH.len()..];
+    }
+    rendered.push_str(&substitute(rest, &values));
+    Ok(rendered)
+}
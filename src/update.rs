@@ -0,0 +1,205 @@
+//! Contains the logic for `self-update`: checking a release source for a newer version, verifying its signature, and swapping it in for the running binary.
+//!
+//! A release source is a directory, a `file://` URL, or an `http://` or `https://` URL holding a [MANIFEST] that names the latest version and, per platform, its binary and the binary's SHA-256, e.g. `{"version": "0.2.0", "assets": {"linux-x86_64": {"file": "entropyscan-linux-x86_64", "sha256": "…"}}}`. The manifest has an Ed25519 signature beside it, with [SIGNATURE_SUFFIX] appended to its name, either as 64 raw bytes or as hex.
+//!
+//! The signature covers the manifest as fetched, so the version, the platform, and the hash of the binary are all vouched for together. A source can't pass off an older, validly signed binary as a newer release, and a binary that doesn't hash to what the signed manifest says is never installed. Releases that aren't strictly newer than the running version are refused, whatever the manifest says.
+//!
+//! [check_for_update] reads and verifies the manifest, and [install_update] fetches, checks, and installs the new binary.
+use std::collections::HashMap;
+use std::env;
+use std::fs::{ self, File };
+use std::io::Write;
+use std::path::Path;
+
+use ed25519_dalek::{ Signature, VerifyingKey };
+use serde::Deserialize;
+use sha2::{ Digest, Sha256 };
+
+use crate::http::get;
+
+/// The name of the manifest at the root of a release source.
+pub const MANIFEST: &str = "latest.json";
+
+/// What is appended to the manifest's name to find its signature.
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// Holds the contents of a release source's [MANIFEST].
+///
+/// The `version` field holds the version of the latest release, e.g. `0.2.0`.
+///
+/// The `assets` field maps each platform, as named by [platform], to its binary.
+///
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    version: String,
+    assets: HashMap<String, Asset>,
+}
+
+/// Holds a platform's binary in a [Manifest].
+///
+/// The `file` field holds its path relative to the release source, which must stay inside it, see [check_asset_name].
+///
+/// The `sha256` field holds its SHA-256 as hex.
+///
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Asset {
+    file: String,
+    sha256: String,
+}
+
+/// Holds a newer release found by [check_for_update] in a manifest whose signature was verified.
+///
+/// The `version` field holds its version.
+///
+/// The `asset` field holds its binary's path for this platform, relative to the release source.
+///
+/// The `sha256` field holds the SHA-256 the binary must have, as hex.
+///
+#[derive(Debug)]
+pub struct Release {
+    pub version: String,
+    asset: String,
+    sha256: String,
+}
+
+/// Name this platform the way release manifests do, e.g. `linux-x86_64`.
+pub fn platform() -> String {
+    format!("{}-{}", env::consts::OS, env::consts::ARCH)
+}
+
+/// Parse a version such as `0.2.0` or `v1.4` into its numeric parts.
+fn parse_version(version: &str) -> Result<Vec<u64>, String> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().map_err(|_| format!("Invalid version: {version}")))
+        .collect()
+}
+
+/// Whether `version` is strictly newer than the running version.
+fn is_newer(version: &str) -> Result<bool, String> {
+    Ok(parse_version(version)? > parse_version(env!("CARGO_PKG_VERSION"))?)
+}
+
+/// Check that `name`, a binary named by a manifest, is a plain relative path, so it can't point outside the release source.
+///
+/// Returns an error message if it is empty or absolute, or has `.`, `..`, backslashes, or drive letters in it.
+fn check_asset_name(name: &str) -> Result<(), String> {
+    let plain = name
+        .split('/')
+        .all(|part| !matches!(part, "" | "." | "..") && !part.contains(['\\', ':']));
+    match plain {
+        true => Ok(()),
+        false => Err(format!("The release binary {name:?} isn't a plain relative path, not fetching it")),
+    }
+}
+
+/// Fetch the file `name` from the release `source`.
+///
+/// Returns its contents, or an error message if it can't be fetched.
+fn fetch(source: &str, name: &str) -> Result<Vec<u8>, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return get(&format!("{}/{name}", source.trim_end_matches('/')));
+    }
+    let dir = source.strip_prefix("file://").unwrap_or(source);
+    let path = Path::new(dir).join(name);
+    fs::read(&path).map_err(|e| format!("Couldn't read {}: {e}", path.to_string_lossy()))
+}
+
+/// Parse a hex Ed25519 public key.
+pub fn parse_public_key(hex: &str) -> Result<[u8; 32], String> {
+    decode_hex(hex.trim())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "The public key must be 64 hex digits".to_string())
+}
+
+/// Decode hex digits, or return [None] if they aren't valid hex.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Read a signature file: 64 raw bytes, or 128 hex digits.
+fn parse_signature(bytes: &[u8]) -> Option<[u8; 64]> {
+    if let Ok(raw) = bytes.try_into() {
+        return Some(raw);
+    }
+    decode_hex(std::str::from_utf8(bytes).ok()?.trim())?
+        .try_into()
+        .ok()
+}
+
+/// Check the release `source` for a version newer than this one, trusting its manifest only if it is signed with `public_key`.
+///
+/// Returns the newer [Release], [None] if this is the latest, or an error message if the manifest can't be read, isn't signed with `public_key`, or has no valid binary for this platform.
+pub fn check_for_update(source: &str, public_key: &[u8; 32]) -> Result<Option<Release>, String> {
+    let manifest = fetch(source, MANIFEST)?;
+    let signature = fetch(source, &format!("{MANIFEST}{SIGNATURE_SUFFIX}"))?;
+    let signature = parse_signature(&signature).ok_or_else(||
+        format!("The signature of {MANIFEST} is neither 64 bytes nor 128 hex digits")
+    )?;
+    let verified = VerifyingKey::from_bytes(public_key)
+        .and_then(|key| key.verify_strict(&manifest, &Signature::from_bytes(&signature)));
+    if verified.is_err() {
+        return Err(format!("The signature of {MANIFEST} doesn't match the public key, not trusting it"));
+    }
+
+    let manifest: Manifest = serde_json::from_slice(&manifest).map_err(|e| format!("Couldn't parse {MANIFEST}: {e}"))?;
+    if !is_newer(&manifest.version)? {
+        return Ok(None);
+    }
+    let asset = manifest.assets
+        .get(&platform())
+        .ok_or_else(|| format!("Release {} has no binary for {}", manifest.version, platform()))?;
+    check_asset_name(&asset.file)?;
+    Ok(Some(Release { version: manifest.version, asset: asset.file.clone(), sha256: asset.sha256.to_ascii_lowercase() }))
+}
+
+/// Fetch the `release` from `source`, check it against its signed manifest, and swap it in for the binary at `exe`.
+///
+/// The new binary is written beside the old one and renamed over it, so the binary at `exe` is always complete. On Windows the running binary can't be replaced, so it is renamed to `.old` first.
+///
+/// Returns an error message if the release isn't newer than this one, or it can't be fetched, doesn't have the SHA-256 its manifest was signed with, or can't be installed. Nothing is changed then.
+pub fn install_update(source: &str, release: &Release, exe: &Path) -> Result<(), String> {
+    if !is_newer(&release.version)? {
+        return Err(format!("Release {} isn't newer than {}, not installing it", release.version, env!("CARGO_PKG_VERSION")));
+    }
+    check_asset_name(&release.asset)?;
+    let binary = fetch(source, &release.asset)?;
+    let sha256: String = Sha256::digest(&binary).iter().map(|b| format!("{b:02x}")).collect();
+    if sha256 != release.sha256 {
+        return Err(format!("The SHA-256 of {} doesn't match the signed manifest, not installing it", release.asset));
+    }
+
+    let file_name = exe.file_name().unwrap_or_default().to_string_lossy();
+    let staged = exe.with_file_name(format!("{file_name}.new"));
+    let error = |e: std::io::Error| format!("Couldn't install {}: {e}", exe.to_string_lossy());
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&staged)?;
+        file.write_all(&binary)?;
+        file.set_permissions(fs::metadata(exe)?.permissions())?;
+        file.sync_all()
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&staged);
+        return Err(error(e));
+    }
+    let old = exe.with_file_name(format!("{file_name}.old"));
+    if cfg!(windows) {
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).map_err(error)?;
+    }
+    fs::rename(&staged, exe).map_err(|e| {
+        if cfg!(windows) {
+            let _ = fs::rename(&old, exe);
+        }
+        let _ = fs::remove_file(&staged);
+        error(e)
+    })
+}
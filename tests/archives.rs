@@ -0,0 +1,220 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ entropy_of, entropy_record, run, scratch_dir };
+
+/// Build a ustar archive holding `members`, each a name and its contents.
+fn tar(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (name, contents) in members {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(contents);
+        archive.resize(archive.len().div_ceil(512) * 512, 0);
+    }
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
+
+/// Build a zip archive holding `members` stored without compression. Checksums are left at zero, as the scanner doesn't check them.
+fn stored_zip(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, contents) in members {
+        let offset = archive.len() as u32;
+        let size = (contents.len() as u32).to_le_bytes();
+        let name_length = (name.len() as u16).to_le_bytes();
+        archive.extend_from_slice(b"PK\x03\x04\x14\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        archive.extend_from_slice(&size);
+        archive.extend_from_slice(&size);
+        archive.extend_from_slice(&name_length);
+        archive.extend_from_slice(&[0, 0]);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(contents);
+
+        directory.extend_from_slice(b"PK\x01\x02\x14\0\x14\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        directory.extend_from_slice(&size);
+        directory.extend_from_slice(&size);
+        directory.extend_from_slice(&name_length);
+        directory.extend_from_slice(&[0; 12]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let count = (members.len() as u16).to_le_bytes();
+    let directory_offset = (archive.len() as u32).to_le_bytes();
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(b"PK\x05\x06\0\0\0\0");
+    archive.extend_from_slice(&count);
+    archive.extend_from_slice(&count);
+    archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&directory_offset);
+    archive.extend_from_slice(&[0, 0]);
+    archive
+}
+
+/// Count the files in `entries`, including the archive members nested in them.
+fn count_nested(entries: &serde_json::Value) -> usize {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| 1 + entry.get("archive_members").map_or(0, count_nested))
+        .sum()
+}
+
+/// Run `scan --archives --format json` over `target` and return the parsed report.
+fn scan_archives_json(target: &Path) -> serde_json::Value {
+    let output = run([Path::new("scan"), Path::new("-t"), target, Path::new("--archives"), Path::new("-f"), Path::new("json")]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn archive_members_get_their_own_entropy() {
+    let dir = scratch_dir("archive-members");
+    let uniform: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    let members: [(&str, &[u8]); 2] = [("inner/payload.bin", &uniform), ("inner/zeros.bin", &[0; 4096])];
+    fs::write(dir.join("bundle.tar"), tar(&members)).unwrap();
+    fs::write(dir.join("bundle.zip"), stored_zip(&members)).unwrap();
+
+    let report = scan_archives_json(&dir);
+    // Members are nested in their archive's record.
+    assert_eq!(report["entropies"].as_array().unwrap().len(), 2);
+    for archive in ["bundle.tar", "bundle.zip"] {
+        let members = serde_json::json!({ "entropies": entropy_record(&report, archive)["archive_members"] });
+        assert_eq!(entropy_of(&members, &format!("{archive}!inner/payload.bin")), 8.0);
+        assert_eq!(entropy_of(&members, &format!("{archive}!inner/zeros.bin")), 0.0);
+    }
+    assert_eq!(count_nested(&report["entropies"]), 6);
+
+    // Other formats list members as files of their own, and reports read back flat.
+    let output = run([Path::new("scan"), Path::new("-t"), &dir, Path::new("--archives"), Path::new("-f"), Path::new("ndjson")]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 6);
+    let path = dir.with_extension("json");
+    fs::write(&path, report.to_string()).unwrap();
+    let output = run([Path::new("compare-stats"), &path, &path, Path::new("-f"), Path::new("json")]);
+    let comparison: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(comparison["comparison"]["changes"][0]["before"], 6.0);
+    fs::remove_file(path).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn archives_are_opaque_by_default() {
+    let dir = scratch_dir("archive-opaque");
+    fs::write(dir.join("bundle.tar"), tar(&[("payload.bin", &[0x41; 1024])])).unwrap();
+    let output = run([Path::new("scan"), Path::new("-t"), &dir, Path::new("-f"), Path::new("json")]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["entropies"].as_array().unwrap().len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn nested_archives_open_up_to_the_container_depth() {
+    let dir = scratch_dir("archive-nested");
+    let uniform: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    let inner = stored_zip(&[("payload.bin", &uniform)]);
+    fs::write(dir.join("outer.tar"), tar(&[("inner.zip", &inner)])).unwrap();
+
+    for (depth, expected) in [("1", 2), ("2", 3)] {
+        let output = run([
+            Path::new("scan"),
+            Path::new("-t"),
+            &dir,
+            Path::new("--container-depth"),
+            Path::new(depth),
+            Path::new("-f"),
+            Path::new("json"),
+        ]);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(count_nested(&report["entropies"]), expected, "depth {depth}");
+    }
+    let report = scan_archives_json(&dir);
+    let inner = &entropy_record(&report, "outer.tar")["archive_members"][0];
+    assert!(inner["path"].as_str().unwrap().ends_with("outer.tar!inner.zip"));
+    assert!(inner["entropy"].as_f64().unwrap() > 0.0);
+    assert!(inner.get("archive_members").is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn members_larger_than_their_headers_claim_are_reported() {
+    let dir = scratch_dir("archive-lying-sizes");
+    let uniform: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    // A gzip file of one stored DEFLATE block whose trailer claims 16 bytes.
+    let mut gzip = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 1];
+    gzip.extend_from_slice(&4096u16.to_le_bytes());
+    gzip.extend_from_slice(&(!4096u16).to_le_bytes());
+    gzip.extend_from_slice(&uniform);
+    gzip.extend_from_slice(&[0; 4]);
+    gzip.extend_from_slice(&16u32.to_le_bytes());
+    fs::write(dir.join("payload.gz"), gzip).unwrap();
+    // A stored zip member whose central directory claims 16 bytes.
+    let mut zip = stored_zip(&[("payload.bin", &uniform)]);
+    let directory = zip.windows(4).position(|window| window == b"PK\x01\x02").unwrap();
+    zip[directory + 24..directory + 28].copy_from_slice(&16u32.to_le_bytes());
+    fs::write(dir.join("bundle.zip"), zip).unwrap();
+
+    // Members are only held in memory when they might be opened in turn.
+    let output = run([
+        Path::new("scan"),
+        Path::new("-t"),
+        &dir,
+        Path::new("--container-depth"),
+        Path::new("2"),
+        Path::new("--no-persist"),
+        Path::new("-f"),
+        Path::new("json"),
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("payload.gz!payload: Decompresses to more than 16 bytes"), "unexpected stderr: {stderr}");
+    assert!(stderr.contains("bundle.zip!payload.bin: holds more data than its header says"), "unexpected stderr: {stderr}");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["entropies"].as_array().unwrap().len(), 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn deep_routes_each_file_to_its_analyzers() {
+    let dir = scratch_dir("deep");
+    let uniform: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    fs::write(dir.join("bundle.tar"), tar(&[("payload.bin", &uniform)])).unwrap();
+    // A zeroed swap file with an encrypted-looking tail.
+    let mut swap = vec![0u8; 256 * 1024];
+    swap[4086..4096].copy_from_slice(b"SWAPSPACE2");
+    swap.extend(uniform.iter().cycle().take(128 * 1024));
+    fs::write(dir.join("swap.part"), swap).unwrap();
+    fs::write(dir.join("notes.txt"), "deep ".repeat(1000)).unwrap();
+
+    let deep_scan = |target: &Path| {
+        let output = run([Path::new("scan"), Path::new("-t"), target, Path::new("--deep"), Path::new("-f"), Path::new("json")]);
+        assert!(output.status.code().is_some_and(|code| code < 2), "scan failed: {:?}", output);
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+    let report = deep_scan(&dir);
+    assert_eq!(entropy_record(&report, "bundle.tar")["archive_members"][0]["entropy"], 8.0);
+    let entries = report["entropies"].as_array().unwrap();
+    let entry = |suffix: &str| entries.iter().find(|e| e["path"].as_str().unwrap().ends_with(suffix)).unwrap();
+    let classes: Vec<&str> = entry("swap.part")["regions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|region| region["class"].as_str().unwrap())
+        .collect();
+    assert_eq!(classes, ["low", "zero", "high"]);
+    for other in ["notes.txt", "bundle.tar"] {
+        assert!(entry(other).get("regions").is_none() && entry(other).get("sections").is_none(), "{other} was routed");
+    }
+
+    // This binary is an executable on every platform.
+    let report = deep_scan(Path::new(env!("CARGO_BIN_EXE_entropyscan")));
+    assert!(!report["entropies"][0]["sections"].as_array().unwrap().is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
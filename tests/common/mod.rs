@@ -0,0 +1,59 @@
+//! Shared helpers for the integration tests.
+//!
+//! [fixtures] generates the deterministic corpus with `entropyscan gen-fixtures` into a fresh directory, and [run] invokes the binary and captures its output.
+#![allow(dead_code)]
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::process::{ Command, Output };
+
+/// Run the `entropyscan` binary with `args`.
+///
+/// The scan cache is kept in a directory of this test process's own, away from the user's.
+pub fn run<I, S>(args: I) -> Output where I: IntoIterator<Item = S>, S: AsRef<OsStr> {
+    Command::new(env!("CARGO_BIN_EXE_entropyscan"))
+        .args(args)
+        .env("XDG_CACHE_HOME", env::temp_dir().join(format!("entropyscan-{}-cache", std::process::id())))
+        .output()
+        .expect("failed to run entropyscan")
+}
+
+/// Create an empty scratch directory unique to this test process and `name`.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("entropyscan-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Generate the fixture corpus into a scratch directory named `name` and return its path.
+pub fn fixtures(name: &str) -> PathBuf {
+    let dir = scratch_dir(name);
+    let output = run([Path::new("gen-fixtures"), Path::new("-o"), &dir]);
+    assert!(output.status.success(), "gen-fixtures failed: {:?}", output);
+    dir
+}
+
+/// Run `scan --format json` over `target` and return the parsed report.
+pub fn scan_json(target: &Path) -> serde_json::Value {
+    let output = run([Path::new("scan"), Path::new("-t"), target, Path::new("-f"), Path::new("json")]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Find the record reported for the file whose path ends with `suffix`.
+pub fn entropy_record<'a>(report: &'a serde_json::Value, suffix: &str) -> &'a serde_json::Value {
+    report["entropies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["path"].as_str().unwrap().ends_with(suffix))
+        .unwrap_or_else(|| panic!("{suffix} missing from report"))
+}
+
+/// Find the entropy reported for the file whose path ends with `suffix`.
+pub fn entropy_of(report: &serde_json::Value, suffix: &str) -> f64 {
+    entropy_record(report, suffix)["entropy"].as_f64().unwrap()
+}
@@ -0,0 +1,617 @@
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use common::{ entropy_of, run, scan_json, scratch_dir };
+
+/// Larger than the 2.5MB chunks entropy used to be summed over, and than many read blocks.
+const LARGE: usize = 25 * 1024 * 1024;
+
+/// Run `scan --format json --symbol-width BITS` over `target` and return the parsed report.
+fn scan_json_width(target: &Path, bits: &str) -> serde_json::Value {
+    let output = run([
+        Path::new("scan"),
+        Path::new("-t"),
+        target,
+        Path::new("-f"),
+        Path::new("json"),
+        Path::new("--symbol-width"),
+        Path::new(bits),
+    ]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Assert that `actual` is within rounding error of `expected`.
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+}
+
+#[test]
+fn large_uniform_file_has_eight_bits_of_entropy() {
+    let dir = scratch_dir("large-uniform");
+    let bytes: Vec<u8> = (0..=255u8).cycle().take(LARGE).collect();
+    fs::write(dir.join("uniform.bin"), bytes).unwrap();
+    let report = scan_json(&dir);
+    assert_close(entropy_of(&report, "uniform.bin"), 8.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn large_files_of_known_entropy() {
+    let dir = scratch_dir("large-known");
+    fs::write(dir.join("zeros.bin"), vec![0u8; LARGE]).unwrap();
+    let alternating: Vec<u8> = [0x00, 0xff].iter().copied().cycle().take(LARGE).collect();
+    fs::write(dir.join("alternating.bin"), alternating).unwrap();
+    let sixteen: Vec<u8> = (0..16u8).cycle().take(LARGE + 1).collect();
+    fs::write(dir.join("sixteen.bin"), sixteen).unwrap();
+
+    let report = scan_json(&dir);
+    assert_eq!(entropy_of(&report, "zeros.bin"), 0.0);
+    assert_close(entropy_of(&report, "alternating.bin"), 1.0);
+    // One byte more than a whole number of cycles skews the counts very slightly.
+    assert!((entropy_of(&report, "sixteen.bin") - 4.0).abs() < 1e-6);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn large_file_entropy_by_symbol_width() {
+    let dir = scratch_dir("large-widths");
+    let bytes: Vec<u8> = (0..=255u8).cycle().take(LARGE).collect();
+    fs::write(dir.join("uniform.bin"), bytes).unwrap();
+
+    // Every nibble value is equally common.
+    assert_close(entropy_of(&scan_json_width(&dir, "4"), "uniform.bin"), 4.0);
+    // Pairs (0, 1), (2, 3), ... give 128 equally common words.
+    assert_close(entropy_of(&scan_json_width(&dir, "16"), "uniform.bin"), 7.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mapped_files_measure_the_same_as_read_files() {
+    let dir = scratch_dir("mmap");
+    let bytes: Vec<u8> = (0..=255u8).cycle().take(LARGE + 1).collect();
+    fs::write(dir.join("uniform.bin"), bytes).unwrap();
+    fs::write(dir.join("text.txt"), "mapped ".repeat(1000)).unwrap();
+    // Nothing to map: read instead.
+    fs::write(dir.join("empty.bin"), b"").unwrap();
+
+    let entropies = |mmap: bool| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "-f", "json", "--no-cache"];
+        if mmap {
+            args.push("--mmap");
+        }
+        let output = run(args);
+        assert!(output.status.code().is_some_and(|code| code < 2), "scan failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["entropies"].clone()
+    };
+    let mapped = entropies(true);
+    assert_eq!(mapped, entropies(false));
+    assert_eq!(mapped.as_array().unwrap().len(), 3);
+    assert_close(entropy_of(&serde_json::json!({ "entropies": mapped }), "uniform.bin"), 8.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn targets_from_reads_a_nul_separated_list() {
+    let dir = scratch_dir("targets-from");
+    fs::write(dir.join("zeros.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(dir.join("new\nline.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(dir.join("unlisted.bin"), vec![0u8; 4096]).unwrap();
+    let mut list = Vec::new();
+    for path in [dir.join("zeros.bin"), dir.join("new\nline.bin"), dir.clone()] {
+        list.extend_from_slice(path.to_str().unwrap().as_bytes());
+        list.push(0);
+    }
+    let list_path = dir.join("targets.lst");
+    fs::write(&list_path, list).unwrap();
+
+    let output = run([Path::new("scan"), Path::new("--targets-from"), &list_path, Path::new("-0"), Path::new("-f"), Path::new("json")]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // The listed directory is not walked, so neither the list nor the unlisted file is scanned.
+    assert_eq!(report["entropies"].as_array().unwrap().len(), 2);
+    assert_eq!(entropy_of(&report, "line.bin"), 0.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn scan_honors_ignore_files_unless_told_not_to() {
+    let dir = scratch_dir("ignore-files");
+    fs::create_dir_all(dir.join("target/debug")).unwrap();
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+    fs::write(dir.join("src/.entropyscanignore"), "*.bin\n").unwrap();
+    fs::write(dir.join("target/debug/build.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(dir.join("src/blob.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(dir.join("src/main.rs"), vec![b'a'; 4096]).unwrap();
+
+    let scanned = |extra: &[&str]| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "-f", "json"];
+        args.extend(extra);
+        let output = run(args);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["entropies"].as_array().unwrap().len()
+    };
+    // The ignore files themselves are scanned, the ignored files aren't.
+    assert_eq!(scanned(&[]), 3);
+    assert_eq!(scanned(&["--no-ignore"]), 5);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn scan_follows_git_ignore_precedence() {
+    let dir = scratch_dir("ignore-precedence");
+    fs::create_dir_all(dir.join(".git/info")).unwrap();
+    fs::create_dir_all(dir.join("fixtures/large")).unwrap();
+    fs::write(dir.join(".git/info/exclude"), "*.tmp\n").unwrap();
+    fs::write(dir.join(".gitignore"), "*.bin\n").unwrap();
+    fs::write(dir.join("fixtures/.gitignore"), "!*.bin\n!keep.tmp\n").unwrap();
+    fs::write(dir.join("fixtures/large/.gitignore"), "huge.bin\n").unwrap();
+    for name in ["blob.bin", "notes.tmp", "fixtures/blob.bin", "fixtures/keep.tmp", "fixtures/large/huge.bin", "fixtures/large/small.bin"] {
+        fs::write(dir.join(name), vec![b'a'; 4096]).unwrap();
+    }
+
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "json"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut scanned: Vec<String> = report["entropies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| Path::new(e["path"].as_str().unwrap()).strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+        .filter(|path| !path.contains(".git"))
+        .collect();
+    scanned.sort();
+    // The repository's exclude file loses to the .gitignore files, and deeper files win over shallower ones.
+    assert_eq!(scanned, ["fixtures/blob.bin", "fixtures/keep.tmp", "fixtures/large/small.bin"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn max_depth_bounds_the_walk_and_link_loops_end() {
+    let dir = scratch_dir("max-depth");
+    fs::create_dir_all(dir.join("a/b/c")).unwrap();
+    for (i, sub) in ["", "a", "a/b", "a/b/c"].iter().enumerate() {
+        fs::write(dir.join(sub).join(format!("{i}.bin")), vec![0u8; 4096]).unwrap();
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("..", dir.join("a/b/loop")).unwrap();
+
+    let scanned = |extra: &[&str]| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "-f", "json"];
+        args.extend(extra);
+        let output = run(args);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["entropies"].as_array().unwrap().len()
+    };
+    assert_eq!(scanned(&[]), 4);
+    assert_eq!(scanned(&["--max-depth", "2"]), 2);
+    assert_eq!(scanned(&["--max-depth", "0"]), 0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_policy_decides_which_links_are_walked() {
+    use std::os::unix::fs::symlink;
+
+    let dir = scratch_dir("symlinks");
+    let outside = scratch_dir("symlinks-outside");
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::write(dir.join("data/zeros.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(outside.join("linked.bin"), vec![0u8; 4096]).unwrap();
+    symlink(&outside, dir.join("linked-dir")).unwrap();
+    symlink(dir.join("data/zeros.bin"), dir.join("zeros-link")).unwrap();
+    symlink("..", dir.join("data/loop")).unwrap();
+
+    let scanned = |extra: &[&str]| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "-f", "json"];
+        args.extend(extra);
+        let output = run(args);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let mut paths: Vec<String> = report["entropies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entropy| {
+                let name = Path::new(entropy["path"].as_str().unwrap()).file_name().unwrap();
+                let link = entropy["link_target"].is_string();
+                format!("{}{}", name.to_string_lossy(), if link { " (link)" } else { "" })
+            })
+            .collect();
+        paths.sort();
+        paths
+    };
+    assert_eq!(scanned(&[]), ["zeros-link (link)", "zeros.bin"]);
+    assert_eq!(scanned(&["--no-follow-symlinks"]), ["zeros.bin"]);
+    // Following links reaches the outside tree once, and the linked file only by one of its paths.
+    let followed = scanned(&["--follow-symlinks"]);
+    assert_eq!(followed.len(), 2);
+    assert!(followed.contains(&"linked.bin".to_string()));
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(outside).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn special_files_are_skipped_and_reported() {
+    let dir = scratch_dir("special-files");
+    fs::write(dir.join("zeros.bin"), vec![0u8; 4096]).unwrap();
+    let status = std::process::Command::new("mkfifo").arg(dir.join("pipe")).status().unwrap();
+    assert!(status.success());
+
+    // Nothing writes to the FIFO, so the scan would hang if it were opened.
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "json"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["entropies"].as_array().unwrap().len(), 1);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pipe: FIFO"), "{stderr}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn modification_time_filters_leave_out_unchanged_files() {
+    use std::time::{ Duration, UNIX_EPOCH };
+
+    let dir = scratch_dir("mtime");
+    fs::write(dir.join("old.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(dir.join("new.bin"), vec![0u8; 4096]).unwrap();
+    // 2020-06-01T00:00:00Z
+    let old = UNIX_EPOCH + Duration::from_secs(1_590_969_600);
+    fs::File::options().write(true).open(dir.join("old.bin")).unwrap().set_modified(old).unwrap();
+
+    let scanned = |extra: &[&str]| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "-f", "json"];
+        args.extend(extra);
+        let output = run(args);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["entropies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entropy| Path::new(entropy["path"].as_str().unwrap()).file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(scanned(&["--newer-than", "24h"]), ["new.bin"]);
+    assert_eq!(scanned(&["--older-than", "2021-01-01"]), ["old.bin"]);
+    assert!(scanned(&["--newer-than", "2020-06-01T00:00:01Z", "--older-than", "1d"]).is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn findings_stream_to_a_unix_socket() {
+    use std::io::{ BufRead, BufReader };
+    use std::os::unix::net::UnixListener;
+
+    let dir = scratch_dir("emit-socket");
+    let data = dir.join("data");
+    fs::create_dir_all(&data).unwrap();
+    fs::write(data.join("zeros.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(data.join("uniform.bin"), (0..=255u8).cycle().take(4096).collect::<Vec<u8>>()).unwrap();
+    let socket = dir.join("findings.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let reader = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        BufReader::new(stream)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let output = run(["scan", "-t", data.to_str().unwrap(), "-m", "7", "-f", "json", "--emit-socket", socket.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1), "scan failed: {:?}", output);
+    // Only findings are streamed, one JSON object per line.
+    let findings = reader.join().unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0]["entropy"], 8.0);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let scan_id = stderr.lines().find_map(|line| line.strip_prefix("scan_id=")).unwrap();
+    assert_eq!(findings[0]["scan_id"], scan_id);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn unchanged_files_come_from_the_scan_cache() {
+    let dir = scratch_dir("scan-cache");
+    let path = dir.join("data.bin");
+    fs::write(&path, vec![0u8; 4096]).unwrap();
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+    assert_eq!(entropy_of(&scan_json(&dir), "data.bin"), 0.0);
+
+    // An unchanged file isn't read again, so a doctored cache entry shows through.
+    let cache = env::temp_dir().join(format!("entropyscan-{}-cache/entropyscan/scan-cache.jsonl", std::process::id()));
+    let entries = fs::read_to_string(&cache).unwrap();
+    let line = entries.lines().find(|line| line.contains("scan-cache/data.bin")).unwrap();
+    fs::write(&cache, entries.replace(line, &line.replace("\"entropy\":0.0", "\"entropy\":5.0"))).unwrap();
+    assert_eq!(entropy_of(&scan_json(&dir), "data.bin"), 5.0);
+
+    // Same size and modification time, different contents: the change time still gives it away.
+    fs::write(&path, (0..=255u8).cycle().take(4096).collect::<Vec<u8>>()).unwrap();
+    fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+    assert_eq!(entropy_of(&scan_json(&dir), "data.bin"), 8.0);
+    let entropy = |flag: &str| {
+        let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "json", flag]);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        entropy_of(&serde_json::from_slice(&output.stdout).unwrap(), "data.bin")
+    };
+    assert_eq!(entropy("--no-cache"), 8.0);
+    assert_eq!(entropy("--refresh"), 8.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn human_tables_show_readable_durations() {
+    let dir = scratch_dir("human-timings");
+    fs::write(dir.join("data.bin"), (0..=255u8).cycle().take(4096).collect::<Vec<u8>>()).unwrap();
+    let scan = |extra: &[&str]| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "--timings"];
+        args.extend(extra);
+        let output = run(args);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let table = scan(&[]);
+    assert!(table.contains("TIME (MS)") && table.contains(" ms\n"), "{table}");
+    let human = scan(&["--human"]);
+    assert!(!human.contains("TIME (MS)") && human.contains("| TIME"), "{human}");
+    let took = human.lines().find_map(|line| line.strip_prefix("Scan took ")).unwrap();
+    assert!(!took.contains(" ms"), "{took}");
+    // CSV keeps raw milliseconds.
+    let csv = scan(&["--human", "-f", "csv"]);
+    assert!(csv.lines().next().unwrap().ends_with(",duration_ms"), "{csv}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn include_and_exclude_globs_filter_targets() {
+    let dir = scratch_dir("globs");
+    for file in ["node_modules/pkg/index.js", "bin/tool", "disk.iso", "notes.txt"] {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "entropy").unwrap();
+    }
+    let scanned = |extra: &[&str]| {
+        let mut args = vec!["scan", "-t", dir.to_str().unwrap(), "-f", "json"];
+        args.extend(extra);
+        let output = run(args);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let mut names: Vec<String> = report["entropies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap().strip_prefix(dir.to_str().unwrap()).unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(scanned(&["--exclude", "node_modules", "--exclude", "*.iso"]), ["/bin/tool", "/notes.txt"]);
+    assert_eq!(scanned(&["--include", "bin/**", "--include", "*.txt"]), ["/bin/tool", "/notes.txt"]);
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "--exclude", "[abc"]);
+    assert_eq!(output.status.code(), Some(3));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ndjson_writes_a_line_per_file() {
+    let dir = scratch_dir("ndjson");
+    fs::write(dir.join("zeros.bin"), vec![0u8; 4096]).unwrap();
+    fs::write(dir.join("cycle.bin"), (0..=255u8).cycle().take(4096).collect::<Vec<u8>>()).unwrap();
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "ndjson"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let mut lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    lines.sort_by(|a, b| a["entropy"].as_f64().partial_cmp(&b["entropy"].as_f64()).unwrap());
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["entropy"], 0.0);
+    assert_eq!(lines[1]["entropy"], 8.0);
+    // Every line carries the ID of the scan, as printed on stderr.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let scan_id = stderr.lines().find_map(|line| line.strip_prefix("scan_id=")).unwrap();
+    assert!(lines.iter().all(|line| line["scan_id"] == scan_id), "{lines:?}");
+
+    let output = run(["blocks", "-t", dir.join("cycle.bin").to_str().unwrap(), "-f", "ndjson"]);
+    assert_eq!(output.status.code(), Some(3));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn content_defined_chunks_realign_after_an_insertion() {
+    let dir = scratch_dir("content-defined");
+    let mut state = 0x9e3779b9u32;
+    let data: Vec<u8> = (0..64 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    fs::write(dir.join("original.bin"), &data).unwrap();
+    fs::write(dir.join("shifted.bin"), [b"inserted".as_slice(), &data].concat()).unwrap();
+
+    let chunks = |name: &str| {
+        let target = dir.join(name);
+        let output = run(["blocks", "-t", target.to_str().unwrap(), "-w", "1024", "--content-defined", "-f", "json"]);
+        assert!(output.status.success(), "blocks failed: {:?}", output);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["windows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|window| (window["length"].as_u64().unwrap(), window["entropy"].as_f64().unwrap()))
+            .collect::<Vec<_>>()
+    };
+    let (original, shifted) = (chunks("original.bin"), chunks("shifted.bin"));
+    assert!(original.len() > 16);
+    // Past the first chunk or two, the same content is cut the same way.
+    assert_eq!(original[2..], shifted[shifted.len() - (original.len() - 2)..]);
+
+    let output = run(["blocks", "-t", dir.join("original.bin").to_str().unwrap(), "--content-defined", "-s", "16"]);
+    assert_eq!(output.status.code(), Some(3));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sarif_levels_follow_thresholds() {
+    let dir = scratch_dir("sarif-levels");
+    fs::write(dir.join("cycle.bin"), (0..=255u8).cycle().take(4096).collect::<Vec<u8>>()).unwrap();
+    fs::write(dir.join("text.txt"), "abcdefg".repeat(600)).unwrap();
+    fs::write(dir.join("tiny.txt"), "abc").unwrap();
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "sarif", "--sarif-error-above", "7.9"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut levels: Vec<(String, String)> = log["runs"][0]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            let uri = result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str().unwrap();
+            let name = uri.rsplit('/').next().unwrap().to_string();
+            (name, result["level"].as_str().unwrap().to_string())
+        })
+        .collect();
+    levels.sort();
+    let expected = [("cycle.bin", "error"), ("text.txt", "warning"), ("tiny.txt", "note")];
+    assert_eq!(levels, expected.map(|(name, level)| (name.to_string(), level.to_string())));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn csv_quotes_awkward_paths() {
+    let dir = scratch_dir("csv-quoting");
+    fs::write(dir.join("a, \"b\".bin"), vec![0u8; 4096]).unwrap();
+    let target = dir.join("a, \"b\".bin");
+    let output = run(["scan", "-t", target.to_str().unwrap(), "-f", "csv"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let quoted = format!("\"{}\"", target.to_str().unwrap().replace('"', "\"\""));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("path,entropy,"));
+    assert!(lines[1].starts_with(&format!("{quoted},0.000,4096,")), "unquoted row: {}", lines[1]);
+
+    let output = run(["scan", "-t", target.to_str().unwrap(), "-f", "csv", "--no-header"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.starts_with(&quoted));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn markdown_renders_scan_results_and_stats_as_tables() {
+    let dir = scratch_dir("markdown");
+    fs::write(dir.join("a|b.bin"), vec![0u8; 4096]).unwrap();
+    for i in 0..4 {
+        fs::write(dir.join(format!("{i}.txt")), "abcdefg".repeat(600)).unwrap();
+    }
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "markdown", "--full-paths"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "### Entropies");
+    assert!(lines[2].starts_with("| PATH"));
+    assert!(lines[3].starts_with("|---"));
+    assert!(stdout.contains("a\\|b.bin |"));
+
+    let output = run(["stats", "-t", dir.to_str().unwrap(), "-f", "markdown", "--no-banner"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("| TARGET"), "unexpected stats: {stdout}");
+    assert!(!stdout.contains("###"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn scan_ids_label_metrics_and_are_only_printed_for_scans() {
+    let dir = scratch_dir("scan-id");
+    fs::write(dir.join("zeros.bin"), vec![0u8; 4096]).unwrap();
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "openmetrics"]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let scan_id = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("scan_id="))
+        .unwrap_or_else(|| panic!("no scan_id printed: {stderr}"));
+    let metrics = String::from_utf8(output.stdout).unwrap();
+    let label = format!("scan_id=\"{scan_id}\"");
+    // Every sample is labelled, and bucket labels keep `le` first.
+    for sample in metrics.lines().filter(|line| !line.starts_with('#')) {
+        assert!(sample.contains(&label), "unlabelled sample: {sample}");
+    }
+    assert!(metrics.contains(&format!("entropyscan_file_entropy_bucket{{le=\"+Inf\",{label}}} 1")));
+    assert!(metrics.contains(&format!("entropyscan_files{{{label}}} 1")));
+
+    let output = run(["gen-fixtures", "-o", dir.join("fixtures").to_str().unwrap()]);
+    assert!(output.status.success(), "gen-fixtures failed: {:?}", output);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("scan_id="));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn parallel_scans_report_in_target_order() {
+    let dir = scratch_dir("parallel-order");
+    // Many more files than the workers may run ahead, some slower to measure than others.
+    for i in 0..200usize {
+        let size = match i % 7 {
+            0 => 1024 * 1024,
+            _ => 512,
+        };
+        fs::write(dir.join(format!("{i:03}.bin")), vec![(i % 256) as u8; size]).unwrap();
+    }
+    let paths = |jobs: &str| {
+        let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "ndjson", "--no-cache", "--jobs", jobs]);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["path"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let serial = paths("1");
+    assert_eq!(serial.len(), 200);
+    assert_eq!(paths("8"), serial);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn forensic_scans_never_write_over_listed_targets() {
+    let dir = scratch_dir("forensic-listed");
+    let evidence = dir.join("evidence.bin");
+    fs::write(&evidence, vec![0x5a; 4096]).unwrap();
+    let list = dir.join("targets.lst");
+    fs::write(&list, format!("{}\n", evidence.to_str().unwrap())).unwrap();
+
+    let scan = |report: &Path| {
+        run([
+            Path::new("scan"),
+            Path::new("--targets-from"),
+            &list,
+            Path::new("--forensic"),
+            Path::new("-f"),
+            Path::new("json"),
+            Path::new("-o"),
+            report,
+        ])
+    };
+    let output = scan(&evidence);
+    assert_eq!(output.status.code(), Some(3), "scan wasn't refused: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("won't write"));
+    assert_eq!(fs::read(&evidence).unwrap(), vec![0x5a; 4096]);
+
+    let output = scan(&dir.join("report.json"));
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use std::fs::{ self, File };
+
+use parquet::file::reader::{ FileReader, SerializedFileReader };
+use parquet::record::RowAccessor;
+
+use common::{ run, scratch_dir };
+
+#[test]
+fn features_out_writes_one_parquet_row_per_file() {
+    let dir = scratch_dir("features");
+    let targets = dir.join("targets");
+    fs::create_dir(&targets).unwrap();
+    // Every byte value equally often, then plain text.
+    let uniform: Vec<u8> = (0..64).flat_map(|_| 0..=255u8).collect();
+    fs::write(targets.join("uniform.bin"), &uniform).unwrap();
+    fs::write(targets.join("notes.txt"), "entropy ".repeat(512)).unwrap();
+    let features = dir.join("features.parquet");
+
+    let output = run([
+        "scan", "-t", targets.to_str().unwrap(), "--min-entropy", "7.5", "-f", "json",
+        "--features-out", features.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1), "scan failed: {:?}", output);
+
+    let reader = SerializedFileReader::new(File::open(&features).unwrap()).unwrap();
+    let fields = reader.metadata().file_metadata().schema().get_fields();
+    let index = |name: &str| fields.iter().position(|field| field.name() == name).unwrap();
+    let mut rows: Vec<_> = reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    rows.sort_by_key(|row| row.get_string(index("path")).unwrap().clone());
+    // Files below --min-entropy are kept.
+    assert_eq!(rows.len(), 2);
+
+    let (text, random) = (&rows[0], &rows[1]);
+    assert!(text.get_string(index("path")).unwrap().ends_with("notes.txt"));
+    assert_eq!(text.get_int(index("type_text")).unwrap(), 1);
+    assert_eq!(text.get_int(index("type_other")).unwrap(), 0);
+    assert_eq!(text.get_double(index("printable_ratio")).unwrap(), 1.0);
+
+    assert_eq!(random.get_long(index("size")).unwrap(), uniform.len() as i64);
+    assert_eq!(random.get_double(index("entropy")).unwrap(), 8.0);
+    assert_eq!(random.get_double(index("chi_square")).unwrap(), 0.0);
+    assert_eq!(random.get_double(index("chunk_entropy_min")).unwrap(), 8.0);
+    assert_eq!(random.get_double(index("chunk_entropy_std_dev")).unwrap(), 0.0);
+    assert_eq!(random.get_int(index("type_other")).unwrap(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ entropy_of, fixtures, run, scan_json };
+
+#[test]
+fn gen_fixtures_is_deterministic() {
+    let first = fixtures("deterministic-a");
+    let second = fixtures("deterministic-b");
+    for name in ["random.bin", "nested/a/b/c/deep.bin", "archives/bundle.tar", "archives/bundle.zip"] {
+        assert_eq!(fs::read(first.join(name)).unwrap(), fs::read(second.join(name)).unwrap(), "{name}");
+    }
+}
+
+#[test]
+fn scan_reports_known_entropies() {
+    let dir = fixtures("known-entropies");
+    let report = scan_json(&dir);
+    assert!(entropy_of(&report, "random.bin") > 7.9);
+    assert_eq!(entropy_of(&report, "zeros.bin"), 0.0);
+    assert_eq!(entropy_of(&report, "empty.txt"), 0.0);
+    let text = entropy_of(&report, "text/lorem.txt");
+    assert!(text > 3.5 && text < 5.0, "{text}");
+}
+
+#[test]
+fn scan_reports_every_fixture() {
+    let dir = fixtures("every-fixture");
+    let expected = run([Path::new("gen-fixtures"), Path::new("-o"), &dir]);
+    let written = String::from_utf8_lossy(&expected.stdout).lines().count();
+    let report = scan_json(&dir);
+    assert_eq!(report["entropies"].as_array().unwrap().len(), written);
+}
+
+#[test]
+fn table_output_handles_unusual_names() {
+    let dir = fixtures("unusual-names");
+    let output = run([Path::new("scan"), Path::new("-t"), &dir]);
+    assert!(output.status.success(), "{:?}", output);
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table.contains("non-utf8-") || cfg!(not(unix)));
+    assert!(table.contains("…"));
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use std::fs;
+use std::io::{ BufRead, BufReader, Read, Write };
+use std::net::TcpListener;
+use std::thread;
+
+use common::{ run, scratch_dir };
+
+#[test]
+fn run_scans_every_target_and_notifies() {
+    let dir = scratch_dir("job");
+    let (random, text) = (dir.join("random"), dir.join("text"));
+    fs::create_dir_all(&random).unwrap();
+    fs::create_dir_all(&text).unwrap();
+    fs::write(random.join("data.bin"), (0..8192u32).map(|j| (j.wrapping_mul(2654435761) >> 13) as u8).collect::<Vec<u8>>()).unwrap();
+    fs::write(text.join("notes.txt"), "hello world ".repeat(100)).unwrap();
+
+    // A webhook that accepts one POST and hands its body back.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let webhook = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut().write_all(b"HTTP/1.0 204 No Content\r\n\r\n").unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    });
+
+    let csv = dir.join("random.csv");
+    let job = dir.join("job.yaml");
+    fs::write(
+        &job,
+        format!(
+            "defaults:\n  min-entropy: 7.0\ntargets:\n  - name: random\n    path: {}\n    outputs:\n      - format: csv\n        file: {}\n  - path: {}\n  - name: missing\n    path: {}\nnotify:\n  - webhook: http://127.0.0.1:{port}/hook\n",
+            random.display(),
+            csv.display(),
+            text.display(),
+            dir.join("missing").display()
+        )
+    ).unwrap();
+    let output = run(["run", job.to_str().unwrap(), "-f", "json"]);
+    assert_eq!(output.status.code(), Some(2), "run didn't report the failed target: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let statuses: Vec<&str> = report["job"]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["findings", "clean", "failed"]);
+    assert_eq!(report["job"]["results"][0]["reported"], 1);
+    assert!(fs::read_to_string(&csv).unwrap().contains("data.bin"));
+
+    let notified = webhook.join().unwrap();
+    assert_eq!(notified["job"], report["job"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn suppression_windows_hold_notifications_back() {
+    let dir = scratch_dir("job-suppress");
+    fs::write(dir.join("notes.txt"), "hello world ".repeat(100)).unwrap();
+    let (maintenance, notified, csv) = (dir.join("maintenance"), dir.join("notified.jsonl"), dir.join("notes.csv"));
+    let job = dir.join("job.yaml");
+    fs::write(
+        &job,
+        format!(
+            "targets:\n  - path: {}\n    outputs: [{{format: csv, file: {}}}]\nnotify:\n  - file: {}\nsuppress:\n  - until: 2000-01-01\n  - while_exists: {}\n    reason: OS upgrade\n",
+            dir.join("notes.txt").display(),
+            csv.display(),
+            notified.display(),
+            maintenance.display()
+        )
+    ).unwrap();
+
+    fs::write(&maintenance, "").unwrap();
+    let output = run(["run", job.to_str().unwrap(), "-f", "json"]);
+    assert!(output.status.success(), "run failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["job"]["suppressed"], "OS upgrade");
+    assert!(csv.exists());
+    assert!(!notified.exists());
+
+    fs::remove_file(&maintenance).unwrap();
+    let output = run(["run", job.to_str().unwrap(), "-f", "json"]);
+    assert!(output.status.success(), "run failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report["job"].get("suppressed").is_none());
+    assert_eq!(fs::read_to_string(&notified).unwrap().lines().count(), 1);
+
+    fs::write(&job, "targets:\n  - path: x\nsuppress:\n  - daily: 25:00-26:00\n").unwrap();
+    let output = run(["run", job.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid daily window"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn job_files_can_use_anchors_and_aliases() {
+    let dir = scratch_dir("job-anchors");
+    fs::write(dir.join("a.txt"), "hello world ".repeat(100)).unwrap();
+    fs::write(dir.join("b.txt"), "hello world ".repeat(100)).unwrap();
+    let job = dir.join("job.yaml");
+    fs::write(
+        &job,
+        format!(
+            "targets:\n  - path: &notes {}\n    options: &options\n      min-entropy: 7.0\n      include: [\"*.txt\"]\n  - path: *notes\n    options: *options\n  - path: {}\n    options: *options\n",
+            dir.join("a.txt").display(),
+            dir.join("b.txt").display()
+        )
+    ).unwrap();
+    let output = run(["run", job.to_str().unwrap(), "-f", "json"]);
+    assert!(output.status.success(), "run failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["job"]["results"].as_array().unwrap().len(), 3);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ run, scratch_dir };
+
+/// The cluster size of the test volume, one 512-byte sector.
+const CLUSTER: usize = 512;
+
+/// The file record size of the test volume.
+const RECORD: usize = 1024;
+
+/// The cluster the test volume's `$MFT` starts at.
+const MFT_CLUSTER: usize = 4;
+
+/// Encode `name` as UTF-16LE.
+fn utf16(name: &str) -> Vec<u8> {
+    name.encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect()
+}
+
+/// Pad `attribute` to a multiple of 8 bytes and write its length.
+fn finish(mut attribute: Vec<u8>) -> Vec<u8> {
+    attribute.resize(attribute.len().div_ceil(8) * 8, 0);
+    let length = attribute.len() as u32;
+    attribute[4..8].copy_from_slice(&length.to_le_bytes());
+    attribute
+}
+
+/// Build a resident attribute of type `kind`, named `name`, holding `value`.
+fn resident(kind: u32, name: &str, value: &[u8]) -> Vec<u8> {
+    let name = utf16(name);
+    let mut attribute = vec![0u8; 0x18];
+    attribute[0..4].copy_from_slice(&kind.to_le_bytes());
+    attribute[9] = (name.len() / 2) as u8;
+    attribute[0x0a..0x0c].copy_from_slice(&0x18u16.to_le_bytes());
+    attribute[0x10..0x14].copy_from_slice(&(value.len() as u32).to_le_bytes());
+    let value_offset = (0x18 + name.len()).div_ceil(8) * 8;
+    attribute[0x14..0x16].copy_from_slice(&(value_offset as u16).to_le_bytes());
+    attribute.extend_from_slice(&name);
+    attribute.resize(value_offset, 0);
+    attribute.extend_from_slice(value);
+    finish(attribute)
+}
+
+/// Build an unnamed, non-resident `$DATA` attribute of `size` bytes stored in `runs`.
+fn non_resident(size: u64, runs: &[u8]) -> Vec<u8> {
+    let mut attribute = vec![0u8; 0x40];
+    attribute[0..4].copy_from_slice(&0x80u32.to_le_bytes());
+    attribute[8] = 1;
+    attribute[0x20..0x22].copy_from_slice(&0x40u16.to_le_bytes());
+    let allocated = size.div_ceil(CLUSTER as u64) * (CLUSTER as u64);
+    attribute[0x28..0x30].copy_from_slice(&allocated.to_le_bytes());
+    attribute[0x30..0x38].copy_from_slice(&size.to_le_bytes());
+    attribute[0x38..0x40].copy_from_slice(&size.to_le_bytes());
+    attribute.extend_from_slice(runs);
+    finish(attribute)
+}
+
+/// Build the value of a Win32 `$FILE_NAME` attribute for `name`.
+fn file_name(name: &str) -> Vec<u8> {
+    let mut value = vec![0u8; 0x42];
+    value[0x40] = name.encode_utf16().count() as u8;
+    value[0x41] = 1;
+    value.extend_from_slice(&utf16(name));
+    value
+}
+
+/// Build an in-use file record holding `attributes`, with its update sequence applied.
+fn record(attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut record = vec![0u8; RECORD];
+    record[..4].copy_from_slice(b"FILE");
+    record[4..6].copy_from_slice(&0x30u16.to_le_bytes());
+    record[6..8].copy_from_slice(&3u16.to_le_bytes());
+    record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+    record[0x16..0x18].copy_from_slice(&1u16.to_le_bytes());
+    let mut at = 0x38;
+    for attribute in attributes {
+        record[at..at + attribute.len()].copy_from_slice(attribute);
+        at += attribute.len();
+    }
+    record[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    record[0x30..0x32].copy_from_slice(&[1, 0]);
+    for i in 1..3 {
+        let end = i * 512;
+        record.copy_within(end - 2..end, 0x30 + i * 2);
+        record[end - 2..end].copy_from_slice(&[1, 0]);
+    }
+    record
+}
+
+/// Build a small NTFS volume with a resident file, a named stream, a file whose slack holds every byte value, and random bytes left in unallocated clusters 40 to 47.
+fn ntfs_image(dir: &Path) -> std::path::PathBuf {
+    let mut image = vec![0u8; 64 * CLUSTER];
+    image[3..11].copy_from_slice(b"NTFS    ");
+    image[0x0b..0x0d].copy_from_slice(&512u16.to_le_bytes());
+    image[0x0d] = 1;
+    image[0x28..0x30].copy_from_slice(&64u64.to_le_bytes());
+    image[0x30..0x38].copy_from_slice(&(MFT_CLUSTER as u64).to_le_bytes());
+    image[0x40] = (-10i8) as u8;
+
+    let hidden: Vec<u8> = (0..200u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    let records = [
+        record(&[non_resident((7 * RECORD) as u64, &[0x11, 14, MFT_CLUSTER as u8, 0])]),
+        record(&[
+            resident(0x30, "", &file_name("note.txt")),
+            resident(0x80, "", b"hello hello hello"),
+            resident(0x80, "hidden", &hidden),
+        ]),
+        record(&[resident(0x30, "", &file_name("big.bin")), non_resident(700, &[0x11, 2, 20, 0])]),
+    ];
+    for (i, record) in records.iter().enumerate() {
+        let at = MFT_CLUSTER * CLUSTER + i * RECORD;
+        image[at..at + RECORD].copy_from_slice(record);
+    }
+    // $Bitmap fills cluster 18 and marks clusters 0 to 18, 20, and 21 in use.
+    let bitmap = record(&[resident(0x30, "", &file_name("$Bitmap")), non_resident(512, &[0x11, 1, 18, 0])]);
+    let at = MFT_CLUSTER * CLUSTER + 6 * RECORD;
+    image[at..at + RECORD].copy_from_slice(&bitmap);
+    image[18 * CLUSTER..18 * CLUSTER + 3].copy_from_slice(&[0xff, 0xff, 0x37]);
+    for (i, byte) in image[40 * CLUSTER..48 * CLUSTER].iter_mut().enumerate() {
+        *byte = ((i as u32).wrapping_mul(2654435761) >> 11) as u8;
+    }
+    for (i, byte) in image[21 * CLUSTER + 188..22 * CLUSTER].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    let path = dir.join("ntfs.img");
+    fs::write(&path, image).unwrap();
+    path
+}
+
+/// Run `ntfs --format json` over `image` with `extra` arguments and return the data found.
+fn mft_json(image: &Path, extra: &[&str]) -> Vec<serde_json::Value> {
+    let mut args = vec!["ntfs", "-t", image.to_str().unwrap(), "-f", "json"];
+    args.extend(extra);
+    let output = run(args);
+    assert!(output.status.success(), "ntfs failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    report["mft_data"].as_array().unwrap().clone()
+}
+
+#[test]
+fn ntfs_reports_resident_data_and_slack() {
+    let dir = scratch_dir("ntfs");
+    let image = ntfs_image(&dir);
+    let found = mft_json(&image, &[]);
+    let names: Vec<(&str, &str)> = found
+        .iter()
+        .map(|data| (data["name"].as_str().unwrap(), data["kind"].as_str().unwrap()))
+        .collect();
+    assert_eq!(names, [("note.txt", "resident"), ("note.txt:hidden", "resident"), ("big.bin", "slack")]);
+    assert_eq!(found[0]["length"], 17);
+    assert_eq!(found[1]["length"], 200);
+    assert_eq!(found[2]["offset"], 21 * CLUSTER + 188);
+    assert_eq!(found[2]["length"], 324);
+    assert!(found[2]["entropy"].as_f64().unwrap() > 7.5);
+
+    let slack = mft_json(&image, &["--slack"]);
+    assert_eq!(slack.len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ntfs_maps_unallocated_clusters() {
+    let dir = scratch_dir("ntfs-unallocated");
+    let image = ntfs_image(&dir);
+    let found = mft_json(&image, &["--unallocated"]);
+    let names: Vec<&str> = found
+        .iter()
+        .map(|data| data["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["clusters 19-19", "clusters 22-63"]);
+    assert_eq!(found[0]["entropy"], 0.0);
+    assert_eq!(found[1]["offset"], 22 * CLUSTER);
+    assert_eq!(found[1]["length"], 42 * CLUSTER);
+    assert!(found[1]["entropy"].as_f64().unwrap() > 1.0);
+    fs::remove_dir_all(dir).unwrap();
+}
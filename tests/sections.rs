@@ -0,0 +1,68 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{ run, scratch_dir };
+
+/// Run `scan --sections --format json` over `target` and return the sections reported for its only file.
+fn sections_of(target: &Path) -> Vec<serde_json::Value> {
+    let output = run([Path::new("scan"), Path::new("-t"), target, Path::new("--sections"), Path::new("-f"), Path::new("json")]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    report["entropies"][0]["sections"]
+        .as_array()
+        .unwrap_or_else(|| panic!("no sections reported: {report}"))
+        .clone()
+}
+
+/// Build a PE executable with an empty `.text` section, a `UPX1` section holding every byte value, and 16 bytes of overlay.
+fn packed_pe() -> Vec<u8> {
+    let mut pe = vec![0u8; 0x200];
+    pe[..2].copy_from_slice(b"MZ");
+    pe[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+    pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+    // The machine and the section count, with no optional header.
+    pe[0x44..0x46].copy_from_slice(&0x14cu16.to_le_bytes());
+    pe[0x46..0x48].copy_from_slice(&2u16.to_le_bytes());
+    for (index, (name, offset)) in [(&b".text"[..], 0x200u32), (b"UPX1", 0x300)].into_iter().enumerate() {
+        let entry = 0x58 + index * 40;
+        pe[entry..entry + name.len()].copy_from_slice(name);
+        pe[entry + 16..entry + 20].copy_from_slice(&0x100u32.to_le_bytes());
+        pe[entry + 20..entry + 24].copy_from_slice(&offset.to_le_bytes());
+    }
+    pe.extend([0u8; 0x100]);
+    pe.extend(0..=255u8);
+    pe.extend([0xcc; 16]);
+    pe
+}
+
+#[test]
+fn pe_sections_and_overlay_are_measured_apart() {
+    let dir = scratch_dir("sections-pe");
+    let path = dir.join("packed.exe");
+    fs::write(&path, packed_pe()).unwrap();
+    let sections = sections_of(&path);
+    let names: Vec<&str> = sections.iter().map(|s| s["name"].as_str().unwrap()).collect();
+    assert_eq!(names, [".text", "UPX1", "overlay"]);
+    assert_eq!(sections[0]["entropy"].as_f64().unwrap(), 0.0);
+    assert_eq!(sections[1]["entropy"].as_f64().unwrap(), 8.0);
+    assert_eq!(sections[2]["offset"].as_u64().unwrap(), 0x400);
+    assert_eq!(sections[2]["size"].as_u64().unwrap(), 16);
+
+    // A truncated section table isn't an error, the file is still scanned whole.
+    let mut truncated = packed_pe();
+    truncated.truncate(0x70);
+    fs::write(&path, truncated).unwrap();
+    let output = run([Path::new("scan"), Path::new("-t"), &path, Path::new("--sections"), Path::new("-f"), Path::new("json")]);
+    assert!(output.status.success(), "scan failed: {:?}", output);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn elf_sections_are_named_from_the_section_name_table() {
+    let sections = sections_of(Path::new(env!("CARGO_BIN_EXE_entropyscan")));
+    assert!(sections.iter().any(|s| s["name"] == ".text" && s["entropy"].as_f64().unwrap() > 4.0));
+    assert!(sections.iter().all(|s| s["name"] != "overlay"));
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use common::{ fixtures, run, scratch_dir };
+
+/// Build a directory of identical text files plus one random file, which is always an outlier.
+fn outlier_dir(name: &str) -> PathBuf {
+    let corpus = fixtures(&format!("{name}-corpus"));
+    let dir = scratch_dir(name);
+    let text = fs::read(corpus.join("text/lorem.txt")).unwrap();
+    for i in 0..8 {
+        fs::write(dir.join(format!("text-{i}.txt")), &text).unwrap();
+    }
+    fs::copy(corpus.join("random.bin"), dir.join("random.bin")).unwrap();
+    dir
+}
+
+/// Run `stats --format json` over `target` with `extra` arguments and return the parsed report.
+fn stats_json(target: &Path, extra: &[&str]) -> serde_json::Value {
+    let mut args: Vec<&OsStr> = vec!["stats".as_ref(), "-t".as_ref(), target.as_os_str(), "-f".as_ref(), "json".as_ref()];
+    args.extend(extra.iter().map(OsStr::new));
+    let output = run(args);
+    assert!(output.status.code().is_some_and(|code| code < 2), "stats failed: {:?}", output);
+    serde_json::from_slice(&output.stdout).expect("stats JSON is a single document")
+}
+
+#[test]
+fn stats_json_is_a_single_envelope() {
+    let dir = outlier_dir("stats-envelope");
+    let report = stats_json(&dir, &[]);
+    let scan_id = report["scan_id"].as_str().unwrap();
+    let groups: Vec<&str> = scan_id.split('-').collect();
+    assert_eq!(groups.iter().map(|group| group.len()).collect::<Vec<_>>(), [8, 4, 4, 4, 12], "{scan_id}");
+    assert!(groups[2].starts_with('4') && groups[3].starts_with(['8', '9', 'a', 'b']), "not a version 4 UUID: {scan_id}");
+    assert_eq!(report["stats"]["total"], 9);
+    for key in ["target", "mean", "median", "variance", "iqr"] {
+        assert!(report["stats"].get(key).is_some(), "{key}");
+    }
+    assert!(report["stats"].get("scan_id").is_none());
+
+    let outliers = report["outliers"].as_array().unwrap();
+    assert_eq!(outliers.len(), 1);
+    assert!(outliers[0]["path"].as_str().unwrap().ends_with("random.bin"));
+    assert!(report.get("recent_outliers").is_none());
+}
+
+#[test]
+fn stats_json_omits_outliers_when_disabled() {
+    let dir = outlier_dir("stats-no-outliers");
+    let report = stats_json(&dir, &["-n"]);
+    assert!(report["stats"].is_object());
+    assert!(report.get("outliers").is_none());
+    assert!(report.get("recent_outliers").is_none());
+}
+
+#[test]
+fn stats_json_includes_recent_outliers() {
+    let dir = outlier_dir("stats-recent");
+    let report = stats_json(&dir, &["--highlight-recent", "1h"]);
+    assert!(report["outliers"].is_array());
+    assert!(report["recent_outliers"].is_array());
+}
+
+#[test]
+fn stats_csv_is_a_single_table() {
+    let dir = outlier_dir("stats-csv");
+    let output = run(["stats", "-t", dir.to_str().unwrap(), "-f", "csv", "--highlight-recent", "1h"]);
+    assert!(output.status.code().is_some_and(|code| code < 2), "stats failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split(',').collect())
+        .collect();
+    assert_eq!(rows[0][0], "section");
+    assert!(rows.iter().all(|row| row.len() == rows[0].len()), "ragged CSV: {stdout}");
+    let sections: Vec<&str> = rows[1..].iter().map(|row| row[0]).collect();
+    assert_eq!(sections[0], "stats");
+    assert!(sections.contains(&"recent_outlier"));
+    assert!(sections.contains(&"outlier"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stats_json_records_sample_seed() {
+    let dir = outlier_dir("stats-seed");
+    let report = stats_json(&dir, &["--random-sample", "4", "--seed", "7"]);
+    assert_eq!(report["seed"], 7);
+    assert_eq!(report["stats"]["total"], 4);
+}
+
+#[test]
+fn stats_merges_several_targets() {
+    let first = outlier_dir("stats-first");
+    let second = outlier_dir("stats-second");
+    let report = stats_json(&first, &["-t", second.to_str().unwrap(), "-t", first.join("random.bin").to_str().unwrap()]);
+    // The random file of the first directory is only counted once.
+    assert_eq!(report["stats"]["total"], 18);
+    assert_eq!(report["outliers"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn scan_fails_on_entropy_gates() {
+    let dir = outlier_dir("fail-gates");
+    let code = |extra: &[&str]| {
+        let mut args: Vec<&OsStr> = vec!["scan".as_ref(), "-t".as_ref(), dir.as_os_str(), "-f".as_ref(), "csv".as_ref()];
+        args.extend(extra.iter().map(OsStr::new));
+        run(args).status.code()
+    };
+    assert_eq!(code(&[]), Some(0));
+    assert_eq!(code(&["--fail-above", "7.5"]), Some(1));
+    assert_eq!(code(&["--fail-above", "8.0"]), Some(0));
+    assert_eq!(code(&["--fail-if-outliers"]), Some(1));
+    // Files below --min-entropy aren't displayed but still count against --fail-above.
+    assert_eq!(code(&["--min-entropy", "8.0", "--fail-above", "7.5"]), Some(1));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn outlier_gates_pass_with_too_few_files_for_quartiles() {
+    let dir = scratch_dir("fail-outliers-few");
+    fs::write(dir.join("a.txt"), "aaaa").unwrap();
+    fs::write(dir.join("b.bin"), (0..=255u8).collect::<Vec<u8>>()).unwrap();
+    let output = run(["scan", "-t", dir.to_str().unwrap(), "-f", "csv", "--fail-if-outliers"]);
+    assert_eq!(output.status.code(), Some(0), "scan failed: {:?}", output);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn summaries_of_a_few_files_leave_quartiles_out() {
+    let dir = scratch_dir("summarize-few");
+    let targets = dir.join("targets");
+    fs::create_dir(&targets).unwrap();
+    fs::write(targets.join("a.txt"), "aaaa").unwrap();
+    fs::write(targets.join("b.bin"), (0..=255u8).collect::<Vec<u8>>()).unwrap();
+    let report = dir.join("report.json");
+    let output = run(["scan", "-t", targets.to_str().unwrap(), "-f", "json", "-o", report.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "scan failed: {:?}", output);
+
+    let template = dir.join("summary.tmpl");
+    fs::write(&template, "{{total}} {{iqr}} {{outliers}}").unwrap();
+    let output = run(["summarize", report.to_str().unwrap(), "--template", template.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "summarize failed: {:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "2 n/a n/a");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stats_of_a_few_files_leave_quartiles_out() {
+    let dir = scratch_dir("stats-few");
+    let targets = dir.join("targets");
+    fs::create_dir(&targets).unwrap();
+    fs::write(targets.join("a.txt"), "aaaa").unwrap();
+    fs::write(targets.join("b.bin"), (0..=255u8).collect::<Vec<u8>>()).unwrap();
+    let report = stats_json(&targets, &[]);
+    assert_eq!(report["stats"]["total"], 2);
+    assert!(report["stats"]["iqr"].is_null());
+
+    let path = dir.join("report.json");
+    let output = run(["scan", "-t", targets.to_str().unwrap(), "-f", "json", "-o", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "scan failed: {:?}", output);
+    let output = run(["compare-stats", path.to_str().unwrap(), path.to_str().unwrap(), "-f", "json"]);
+    assert_eq!(output.status.code(), Some(0), "compare-stats failed: {:?}", output);
+    let comparison: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let metrics: Vec<_> = comparison["comparison"]["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| change["metric"].as_str().unwrap())
+        .collect();
+    assert_eq!(metrics, ["files", "mean", "median", "variance"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compare_stats_flags_a_shifted_corpus() {
+    let dir = scratch_dir("compare-stats");
+    let (before, after) = (dir.join("before"), dir.join("after"));
+    fs::create_dir_all(&before).unwrap();
+    fs::create_dir_all(&after).unwrap();
+    for i in 0..20u32 {
+        let text: Vec<u8> = (0..4096u32).map(|j| b'a' + ((j * (i + 1)) % 7) as u8).collect();
+        let random: Vec<u8> = (0..4096u32).map(|j| ((j ^ i).wrapping_mul(2654435761) >> 13) as u8).collect();
+        fs::write(before.join(format!("{i}.txt")), &text).unwrap();
+        fs::write(after.join(format!("{i}.txt")), if i < 15 { random } else { text }).unwrap();
+    }
+    let report = |target: &Path| {
+        let path = target.with_extension("json");
+        let output = run([Path::new("scan"), Path::new("-t"), target, Path::new("-f"), Path::new("json"), Path::new("-o"), &path]);
+        assert!(output.status.success(), "scan failed: {:?}", output);
+        path
+    };
+    let (before, after) = (report(&before), report(&after));
+    let compare = |a: &Path, b: &Path| {
+        let output = run([Path::new("compare-stats"), a, b, Path::new("-f"), Path::new("json")]);
+        let comparison: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (output.status.code(), comparison["comparison"].clone())
+    };
+
+    let (code, comparison) = compare(&before, &after);
+    assert_eq!(code, Some(1));
+    assert_eq!(comparison["changed"], true);
+    assert_eq!(comparison["ks_distance"], 0.75);
+    assert!(comparison["changes"][1]["change"].as_f64().unwrap() > 1.0);
+
+    let (code, comparison) = compare(&before, &before);
+    assert_eq!(code, Some(0));
+    assert_eq!(comparison["ks_distance"], 0.0);
+    assert_eq!(comparison["p_value"], 1.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn diff_lists_files_that_changed_since_the_baseline() {
+    let dir = scratch_dir("baseline-diff");
+    let tree = dir.join("share");
+    fs::create_dir_all(&tree).unwrap();
+    let text: Vec<u8> = (0..4096u32).map(|j| b'a' + (j % 7) as u8).collect();
+    for name in ["kept.txt", "encrypted.txt", "deleted.txt", "wiped.txt"] {
+        fs::write(tree.join(name), &text).unwrap();
+    }
+    let baseline = dir.join("baseline.json");
+    let output = run([Path::new("baseline"), Path::new("-t"), &tree, Path::new("--out"), &baseline]);
+    assert!(output.status.success(), "baseline failed: {:?}", output);
+
+    let diff = || {
+        let output = run([Path::new("diff"), Path::new("--baseline"), &baseline, Path::new("-t"), &tree, Path::new("-f"), Path::new("json")]);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (output.status.code(), report["diff"].clone())
+    };
+    let (code, diff_report) = diff();
+    assert_eq!(code, Some(0));
+    assert_eq!(diff_report["unchanged"], 4);
+
+    let random: Vec<u8> = (0..4096u32).map(|j| (j.wrapping_mul(2654435761) >> 13) as u8).collect();
+    fs::write(tree.join("encrypted.txt"), random).unwrap();
+    fs::remove_file(tree.join("deleted.txt")).unwrap();
+    fs::write(tree.join("ransom-note.txt"), &text).unwrap();
+    fs::write(tree.join("wiped.txt"), vec![0u8; 4096]).unwrap();
+    let (code, diff_report) = diff();
+    assert_eq!(code, Some(1));
+    let changes: Vec<(String, String)> = diff_report["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| {
+            let name = Path::new(change["path"].as_str().unwrap()).file_name().unwrap();
+            (name.to_string_lossy().to_string(), change["change"].as_str().unwrap().to_string())
+        })
+        .collect();
+    let expected = [("deleted.txt", "removed"), ("encrypted.txt", "changed"), ("ransom-note.txt", "new"), ("wiped.txt", "wiped")];
+    assert_eq!(changes, expected.map(|(name, change)| (name.to_string(), change.to_string())));
+    assert_eq!(diff_report["unchanged"], 1);
+    assert_eq!(diff_report["wiped"], 1);
+
+    // Wiped files are reported however large a change the delta lets through.
+    let output = run([Path::new("diff"), Path::new("--baseline"), &baseline, Path::new("-t"), &tree, Path::new("--delta"), Path::new("8"), Path::new("-f"), Path::new("json")]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!((report["diff"]["changed"].as_u64(), report["diff"]["wiped"].as_u64()), (Some(0), Some(1)));
+    fs::remove_dir_all(dir).unwrap();
+}
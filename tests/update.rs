@@ -0,0 +1,97 @@
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use ed25519_dalek::{ Signer, SigningKey };
+
+use common::{ run, scratch_dir };
+
+/// The seed of the key test releases are signed with.
+const SEED: [u8; 32] = [7; 32];
+
+/// The "binary" test releases ship.
+const BINARY: &[u8] = &[0xaf, 0x82];
+
+/// The SHA-256 of [BINARY], as hex.
+const BINARY_SHA256: &str = "0598c67e908a9766b29fe74fd2d7035524a17616ce6a5d1bf3bf9db9b3a6ace7";
+
+/// The public key test releases are signed with, as hex.
+fn public_key() -> String {
+    SigningKey::from_bytes(&SEED)
+        .verifying_key()
+        .to_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Write a release source into `dir` offering `version` for this platform as the binary `file` with hash `sha256`, and sign its manifest.
+fn release(dir: &Path, version: &str, file: &str, sha256: &str) {
+    let platform = format!("{}-{}", env::consts::OS, env::consts::ARCH);
+    let manifest = serde_json::json!({ "version": version, "assets": { platform: { "file": file, "sha256": sha256 } } });
+    let manifest = manifest.to_string();
+    let signature = SigningKey::from_bytes(&SEED).sign(manifest.as_bytes());
+    fs::write(dir.join("latest.json"), &manifest).unwrap();
+    fs::write(dir.join("latest.json.sig"), signature.to_bytes()).unwrap();
+    fs::write(dir.join("entropyscan-next"), BINARY).unwrap();
+}
+
+#[test]
+fn check_only_reports_newer_signed_releases() {
+    let dir = scratch_dir("update-check");
+    let check = || run(["self-update", "--source", dir.to_str().unwrap(), "--public-key", &public_key(), "--check-only"]);
+    release(&dir, "0.0.1", "entropyscan-next", BINARY_SHA256);
+    let output = check();
+    assert_eq!(output.status.code(), Some(0), "check failed: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("up to date"));
+
+    release(&dir, "999.0.0", "entropyscan-next", BINARY_SHA256);
+    let output = check();
+    assert_eq!(output.status.code(), Some(1), "check failed: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("-> 999.0.0"));
+
+    // A manifest changed after signing, e.g. to advertise an older binary as new, isn't trusted.
+    let manifest = fs::read_to_string(dir.join("latest.json")).unwrap();
+    fs::write(dir.join("latest.json"), manifest.replace("999.0.0", "998.0.0")).unwrap();
+    let output = check();
+    assert_eq!(output.status.code(), Some(3), "check didn't fail: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("doesn't match"));
+
+    release(&dir, "999.0.0", "../entropyscan-next", BINARY_SHA256);
+    let output = check();
+    assert_eq!(output.status.code(), Some(3), "check didn't fail: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("plain relative path"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_binaries_matching_the_signed_manifest_are_installed() {
+    let dir = scratch_dir("update-install");
+    let source = dir.join("releases");
+    fs::create_dir(&source).unwrap();
+    // Update a copy, so the binary the other tests run stays in place.
+    let exe = dir.join("entropyscan");
+    fs::copy(env!("CARGO_BIN_EXE_entropyscan"), &exe).unwrap();
+    let update = || {
+        Command::new(&exe)
+            .args(["self-update", "--source", source.to_str().unwrap(), "--public-key", &public_key()])
+            .output()
+            .unwrap()
+    };
+
+    release(&source, "999.0.0", "entropyscan-next", &"0".repeat(64));
+    let output = update();
+    assert_eq!(output.status.code(), Some(3), "update didn't fail: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("doesn't match the signed manifest"));
+    assert_ne!(fs::read(&exe).unwrap(), BINARY);
+
+    release(&source, "999.0.0", "entropyscan-next", BINARY_SHA256);
+    let output = update();
+    assert!(output.status.success(), "update failed: {:?}", output);
+    assert_eq!(fs::read(&exe).unwrap(), BINARY);
+    assert!(!dir.join("entropyscan.new").exists());
+    fs::remove_dir_all(dir).unwrap();
+}